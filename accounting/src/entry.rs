@@ -0,0 +1,338 @@
+use std::collections::{BTreeMap, HashMap};
+
+use common::client::Client;
+use common::credit_note::CreditNote;
+use common::currency::BASE_CURRENCY;
+use common::invoice::Invoice;
+use common::product::Product;
+use common::vat::VatBreakdown;
+
+/// Code and label of the sales journal, where invoices and credit notes are posted
+pub const SALES_JOURNAL: (&str, &str) = ("VE", "Journal des ventes");
+
+const RECEIVABLE: (&str, &str) = ("411000", "Clients");
+const VAT_COLLECTED: (&str, &str) = ("445710", "TVA collectée");
+/// Where the rounding difference between the prices and their VAT breakdown, and the one of the
+/// conversion into the base currency, goes, depending on whether it is a credit or a debit
+const ROUNDING_GAIN: (&str, &str) = ("758000", "Produits divers de gestion courante");
+const ROUNDING_LOSS: (&str, &str) = ("658000", "Charges diverses de gestion courante");
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RevenueAccount {
+    pub account: String,
+    pub label: String,
+}
+
+/// Revenue account of the products of an invoice, from their category.
+#[derive(Debug, Clone)]
+pub struct RevenueAccounts {
+    pub by_product: HashMap<i32, RevenueAccount>,
+    /// Account of the products whose category, or the product itself, is unknown
+    pub misc: RevenueAccount,
+}
+
+impl RevenueAccounts {
+    pub fn of(&self, product_id: i32) -> &RevenueAccount {
+        self.by_product.get(&product_id).unwrap_or(&self.misc)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Invoice,
+    CreditNote,
+}
+
+impl Source {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Source::Invoice => "invoice",
+            Source::CreditNote => "credit_note",
+        }
+    }
+}
+
+/// A line of a journal entry, with its amounts in cents of the base currency. One of them is zero.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub account: String,
+    pub account_label: String,
+    /// Client sub-account of the receivable lines
+    pub aux_account: Option<String>,
+    pub aux_label: Option<String>,
+    pub debit: i64,
+    pub credit: i64,
+    /// Amount in cents of the currency of the invoice, when it isn't the base currency
+    pub currency_amount: Option<i64>,
+}
+
+impl Line {
+    /// A debit if `amount` is positive, a credit otherwise.
+    fn new((account, label): (&str, &str), amount: i64) -> Self {
+        Line {
+            account: account.to_string(),
+            account_label: label.to_string(),
+            aux_account: None,
+            aux_label: None,
+            debit: amount.max(0),
+            credit: (-amount).max(0),
+            currency_amount: None,
+        }
+    }
+
+    /// Converts the amounts with `rate`, keeping the amount before conversion.
+    fn convert(&mut self, rate: f64) {
+        let amount = self.debit - self.credit;
+        let converted = (amount as f64 * rate).round() as i64;
+        self.currency_amount = Some(amount.abs());
+        self.debit = converted.max(0);
+        self.credit = (-converted).max(0);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub source: Source,
+    pub source_id: i32,
+    pub piece_ref: String,
+    /// Date of the invoice or credit note, as `YYYY-MM-DD`
+    pub piece_date: String,
+    pub label: String,
+    /// Currency of the invoice or credit note
+    pub currency: String,
+    /// Rate its amounts were converted with into the base currency, on the piece date
+    pub exchange_rate: f64,
+    pub lines: Vec<Line>,
+}
+
+fn cents(amount: f64) -> i64 {
+    (amount * 100.0).round() as i64
+}
+
+impl Entry {
+    /// Debits the client of what the invoice is due and credits the revenue of each category of
+    /// product and the VAT collected. `exchange_rate` converts the currency of the invoice into the
+    /// base currency.
+    pub fn from_invoice(invoice: &Invoice, revenue: &RevenueAccounts, vat_rate: f64, exchange_rate: f64) -> Self {
+        let number = if invoice.number.is_empty() {
+            invoice.id.to_string()
+        } else {
+            invoice.number.clone()
+        };
+        Entry::new(
+            Source::Invoice,
+            invoice.id,
+            number.clone(),
+            invoice.date.clone(),
+            format!("Facture {} {}", number, invoice.client.name),
+            &invoice.client,
+            &invoice.products,
+            revenue,
+            vat_rate,
+            &invoice.currency,
+            exchange_rate,
+        )
+    }
+
+    /// The reverse of the invoice entry for the credited lines: their prices are negative, so the
+    /// client is credited and the revenue and VAT debited.
+    pub fn from_credit_note(
+        credit_note: &CreditNote,
+        revenue: &RevenueAccounts,
+        vat_rate: f64,
+        exchange_rate: f64,
+    ) -> Self {
+        Entry::new(
+            Source::CreditNote,
+            credit_note.id,
+            format!("AV-{:06}", credit_note.id),
+            credit_note.date.clone(),
+            format!("Avoir {} facture {} {}", credit_note.id, credit_note.invoice_id, credit_note.client.name),
+            &credit_note.client,
+            &credit_note.products,
+            revenue,
+            vat_rate,
+            &credit_note.currency,
+            exchange_rate,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        source: Source,
+        source_id: i32,
+        piece_ref: String,
+        piece_date: String,
+        label: String,
+        client: &Client,
+        products: &[Product],
+        revenue: &RevenueAccounts,
+        vat_rate: f64,
+        currency: &str,
+        exchange_rate: f64,
+    ) -> Self {
+        let vat = VatBreakdown::new(products.iter().map(|product| product.price), vat_rate);
+
+        let mut receivable = Line::new(RECEIVABLE, cents(vat.payable));
+        receivable.aux_account = Some(format!("C{:06}", client.id));
+        receivable.aux_label = Some(client.name.clone());
+        let mut lines = vec![receivable];
+
+        // Sorted by account, so that the same invoice always gives the same lines
+        let mut by_account: BTreeMap<&str, (&str, i64)> = BTreeMap::new();
+        for (product, net) in products.iter().zip(&vat.lines) {
+            let account = revenue.of(product.id);
+            by_account.entry(&account.account).or_insert((&account.label, 0)).1 += cents(*net);
+        }
+        lines.extend(by_account.into_iter().map(|(account, (label, net))| Line::new((account, label), -net)));
+        lines.push(Line::new(VAT_COLLECTED, -cents(vat.vat)));
+
+        let foreign = currency != BASE_CURRENCY;
+        if foreign {
+            lines.iter_mut().for_each(|line| line.convert(exchange_rate));
+        }
+
+        // Balances the entry: without conversion, this is the rounding of the VAT breakdown
+        let rounding = lines.iter().map(|line| line.credit - line.debit).sum::<i64>();
+        let mut rounding_line = Line::new(if rounding > 0 { ROUNDING_LOSS } else { ROUNDING_GAIN }, rounding);
+        if foreign {
+            rounding_line.currency_amount = Some(cents(vat.rounding).abs());
+        }
+        lines.push(rounding_line);
+
+        lines.retain(|line| line.debit != 0 || line.credit != 0);
+        Entry {
+            source,
+            source_id,
+            piece_ref,
+            piece_date,
+            label,
+            currency: currency.to_string(),
+            exchange_rate,
+            lines,
+        }
+    }
+
+    pub fn debit(&self) -> i64 {
+        self.lines.iter().map(|line| line.debit).sum()
+    }
+
+    pub fn credit(&self) -> i64 {
+        self.lines.iter().map(|line| line.credit).sum()
+    }
+
+    pub fn is_balanced(&self) -> bool {
+        self.debit() == self.credit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn product(id: i32, price: f64) -> Product {
+        Product {
+            id,
+            name: format!("Product {}", id),
+            price,
+            command_id: 4217,
+            backordered: false,
+            currency: String::from("EUR"),
+            exchange_rate: 1.0,
+            unit: None,
+        }
+    }
+
+    fn client() -> Client {
+        Client {
+            id: 88,
+            name: String::from("Hélène Dupont"),
+            ..Client::default()
+        }
+    }
+
+    fn invoice(currency: &str) -> Invoice {
+        let products = vec![product(17, 9.99), product(23, 3.33), product(42, 1249.0)];
+        Invoice {
+            id: 4217,
+            number: String::from("OMS-2026-000123"),
+            date: String::from("2026-03-01"),
+            client: client(),
+            total_price: 1262.32,
+            size: products.len() as i32,
+            products,
+            currency: currency.to_string(),
+        }
+    }
+
+    fn revenue() -> RevenueAccounts {
+        let account = |account: &str, label: &str| RevenueAccount {
+            account: account.to_string(),
+            label: label.to_string(),
+        };
+        RevenueAccounts {
+            by_product: HashMap::from([(17, account("707100", "Épicerie")), (42, account("707500", "Électronique"))]),
+            misc: account("707000", "Ventes de marchandises"),
+        }
+    }
+
+    fn amounts(entry: &Entry) -> Vec<(&str, i64, i64)> {
+        entry.lines.iter().map(|line| (line.account.as_str(), line.debit, line.credit)).collect()
+    }
+
+    #[test]
+    fn invoice_entry_balances_with_its_rounding() {
+        let entry = Entry::from_invoice(&invoice("EUR"), &revenue(), 20.0, 1.0);
+        assert_eq!(
+            amounts(&entry),
+            [
+                ("411000", 126232, 0),
+                // Product 23 has no category account
+                ("707000", 0, 278),
+                ("707100", 0, 833),
+                ("707500", 0, 104083),
+                ("445710", 0, 21039),
+                // The lines without VAT are rounded up by a cent
+                ("658000", 1, 0),
+            ]
+        );
+        assert_eq!(entry.lines[0].aux_account.as_deref(), Some("C000088"));
+        assert_eq!(entry.piece_ref, "OMS-2026-000123");
+        assert_eq!(entry.label, "Facture OMS-2026-000123 Hélène Dupont");
+        assert!(entry.is_balanced());
+        assert_eq!(entry.debit(), 126233);
+    }
+
+    #[test]
+    fn credit_note_entry_reverses_the_credited_lines() {
+        let invoice = invoice("EUR");
+        let mut credit_note = CreditNote::new(7, &invoice, String::from("2026-03-09"), String::from("Défaut"));
+        credit_note.credit_product(&invoice.products[0]);
+        credit_note.credit_product(&invoice.products[2]);
+        let entry = Entry::from_credit_note(&credit_note, &revenue(), 20.0, 1.0);
+        assert_eq!(entry.piece_ref, "AV-000007");
+        assert_eq!(
+            amounts(&entry),
+            [("411000", 0, 125899), ("707100", 833, 0), ("707500", 104083, 0), ("445710", 20983, 0)]
+        );
+        assert!(entry.is_balanced());
+    }
+
+    #[test]
+    fn converted_entry_balances_and_keeps_the_amounts_before_conversion() {
+        let entry = Entry::from_invoice(&invoice("USD"), &revenue(), 20.0, 0.9137);
+        assert!(entry.is_balanced());
+        assert_eq!((entry.currency.as_str(), entry.exchange_rate), ("USD", 0.9137));
+        let receivable = &entry.lines[0];
+        assert_eq!((receivable.debit, receivable.currency_amount), (115338, Some(126232)));
+        assert!(entry.lines.iter().all(|line| line.currency_amount.is_some()));
+    }
+
+    #[test]
+    fn unbalanced_entries_are_detected() {
+        let mut entry = Entry::from_invoice(&invoice("EUR"), &revenue(), 20.0, 1.0);
+        entry.lines.pop();
+        assert!(!entry.is_balanced());
+        assert_eq!((entry.debit(), entry.credit()), (126232, 126233));
+    }
+}
\ No newline at end of file
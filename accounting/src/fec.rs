@@ -0,0 +1,242 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use chrono::NaiveDate;
+use sqlx::PgPool;
+
+use crate::entry::SALES_JOURNAL;
+
+/// Columns of a FEC, in order, as article A47 A-1 of the Livre des procédures fiscales lists them
+const HEADER: [&str; 18] = [
+    "JournalCode",
+    "JournalLib",
+    "EcritureNum",
+    "EcritureDate",
+    "CompteNum",
+    "CompteLib",
+    "CompAuxNum",
+    "CompAuxLib",
+    "PieceRef",
+    "PieceDate",
+    "EcritureLib",
+    "Debit",
+    "Credit",
+    "EcritureLet",
+    "DateLet",
+    "ValidDate",
+    "Montantdevise",
+    "Idevise",
+];
+
+/// A journal line with its entry, dates as `YYYYMMDD`.
+#[derive(Debug, sqlx::FromRow)]
+pub struct FecLine {
+    pub journal: String,
+    /// Number of the entry in the order it was posted
+    pub number: i32,
+    pub piece_date: String,
+    pub account: String,
+    pub account_label: String,
+    pub aux_account: Option<String>,
+    pub aux_label: Option<String>,
+    pub piece_ref: String,
+    pub label: String,
+    pub debit: i64,
+    pub credit: i64,
+    pub valid_date: String,
+    pub currency: String,
+    pub currency_amount: Option<i64>,
+}
+
+/// Lines of the entries dated from `from` to `to`, both included, in the order of their dates.
+pub async fn lines_between(pool: &PgPool, from: NaiveDate, to: NaiveDate) -> Result<Vec<FecLine>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT e.journal, e.number, TO_CHAR(e.pieceDate, 'YYYYMMDD') AS piece_date,
+            l.account, l.accountLabel AS account_label, l.auxAccount AS aux_account, l.auxLabel AS aux_label,
+            e.pieceRef AS piece_ref, e.label, l.debit, l.credit,
+            TO_CHAR(e.postedAt AT TIME ZONE 'UTC', 'YYYYMMDD') AS valid_date,
+            e.currency, l.currencyAmount AS currency_amount
+        FROM JournalEntry e JOIN JournalLine l ON l.entryId = e.id
+        WHERE e.pieceDate BETWEEN $1::DATE AND $2::DATE
+        ORDER BY e.pieceDate, e.number, l.lineNumber",
+    )
+    .bind(from.to_string())
+    .bind(to.to_string())
+    .fetch_all(pool)
+    .await
+}
+
+/// Name the administration expects: the SIREN of the company, then the closing date.
+pub fn file_name(siren: &str, closing: NaiveDate) -> String {
+    format!("{}FEC{}.txt", siren, closing.format("%Y%m%d"))
+}
+
+/// French amount, with a comma and two decimals.
+fn amount(cents: i64) -> String {
+    format!("{},{:02}", cents / 100, cents % 100)
+}
+
+/// Fields can't hold the separator nor line breaks.
+fn text(value: &str) -> String {
+    value.replace(['|', '\t', '\r', '\n'], " ")
+}
+
+/// Writes the lines as a FEC separated by `|`, and returns the total debits and credits.
+///
+/// Entries are posted in the order their documents arrive, not in the order of their dates, so
+/// they are sorted by date, then by posting order, and `EcritureNum` numbers them again from 1 in
+/// that order: the numbers of the file follow its dates without gaps. Nothing is written if an
+/// entry is unbalanced.
+pub fn write(mut writer: impl Write, lines: &[FecLine]) -> io::Result<(i64, i64)> {
+    let mut balances: BTreeMap<i32, i64> = BTreeMap::new();
+    for line in lines {
+        *balances.entry(line.number).or_default() += line.debit - line.credit;
+    }
+    if let Some((number, _)) = balances.iter().find(|(_, balance)| **balance != 0) {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Entry {} is unbalanced", number)));
+    }
+
+    let mut sorted: Vec<&FecLine> = lines.iter().collect();
+    // Stable, so the lines of an entry keep their order
+    sorted.sort_by(|a, b| (&a.piece_date, a.number).cmp(&(&b.piece_date, b.number)));
+
+    writeln!(writer, "{}", HEADER.join("|"))?;
+    let (mut debit, mut credit) = (0, 0);
+    let (mut entry, mut previous) = (0, None);
+    for line in sorted {
+        if previous != Some(line.number) {
+            entry += 1;
+            previous = Some(line.number);
+        }
+        let fields = [
+            text(&line.journal),
+            text(if line.journal == SALES_JOURNAL.0 { SALES_JOURNAL.1 } else { &line.journal }),
+            entry.to_string(),
+            line.piece_date.clone(),
+            text(&line.account),
+            text(&line.account_label),
+            text(line.aux_account.as_deref().unwrap_or("")),
+            text(line.aux_label.as_deref().unwrap_or("")),
+            text(&line.piece_ref),
+            line.piece_date.clone(),
+            text(&line.label),
+            amount(line.debit),
+            amount(line.credit),
+            String::new(),
+            String::new(),
+            line.valid_date.clone(),
+            // Only for the lines of invoices in another currency than the base one
+            line.currency_amount.map(amount).unwrap_or_default(),
+            line.currency_amount.map(|_| text(&line.currency)).unwrap_or_default(),
+        ];
+        writeln!(writer, "{}", fields.join("|"))?;
+        debit += line.debit;
+        credit += line.credit;
+    }
+    writer.flush()?;
+    Ok((debit, credit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(number: i32, piece_date: &str, account: &str, debit: i64, credit: i64) -> FecLine {
+        FecLine {
+            journal: SALES_JOURNAL.0.to_string(),
+            number,
+            piece_date: piece_date.to_string(),
+            account: account.to_string(),
+            account_label: String::from("Clients | divers"),
+            aux_account: None,
+            aux_label: None,
+            piece_ref: format!("OMS-2026-{:06}", number),
+            label: String::from("Facture\nOMS"),
+            debit,
+            credit,
+            valid_date: String::from("20260315"),
+            currency: String::from("EUR"),
+            currency_amount: None,
+        }
+    }
+
+    fn written(lines: &[FecLine]) -> io::Result<(String, (i64, i64))> {
+        let mut buffer = Vec::new();
+        let totals = write(&mut buffer, lines)?;
+        Ok((String::from_utf8(buffer).unwrap(), totals))
+    }
+
+    #[test]
+    fn amounts_have_a_decimal_comma() {
+        assert_eq!(amount(0), "0,00");
+        assert_eq!(amount(5), "0,05");
+        assert_eq!(amount(1262), "12,62");
+        assert_eq!(amount(12_345_678), "123456,78");
+    }
+
+    #[test]
+    fn file_name_ends_with_the_closing_date() {
+        let closing = NaiveDate::from_ymd_opt(2026, 12, 31).unwrap();
+        assert_eq!(file_name("123456789", closing), "123456789FEC20261231.txt");
+    }
+
+    #[test]
+    fn entries_are_numbered_in_the_order_of_their_dates() {
+        // Entry 1 was posted first, for a later invoice
+        let lines = [
+            line(1, "20260310", "411000", 1200, 0),
+            line(1, "20260310", "707000", 0, 1200),
+            line(2, "20260301", "411000", 500, 0),
+            line(2, "20260301", "707000", 0, 500),
+            line(3, "20260310", "411000", 0, 300),
+            line(3, "20260310", "707000", 300, 0),
+        ];
+        let (fec, totals) = written(&lines).unwrap();
+        assert_eq!(totals, (2000, 2000));
+
+        let rows: Vec<Vec<&str>> = fec.lines().map(|row| row.split('|').collect()).collect();
+        assert_eq!(rows[0], HEADER);
+        assert!(rows.iter().all(|row| row.len() == HEADER.len()));
+        let numbered: Vec<(&str, &str, &str)> = rows[1..].iter().map(|row| (row[2], row[3], row[8])).collect();
+        assert_eq!(
+            numbered,
+            [
+                ("1", "20260301", "OMS-2026-000002"),
+                ("1", "20260301", "OMS-2026-000002"),
+                ("2", "20260310", "OMS-2026-000001"),
+                ("2", "20260310", "OMS-2026-000001"),
+                ("3", "20260310", "OMS-2026-000003"),
+                ("3", "20260310", "OMS-2026-000003"),
+            ]
+        );
+        // Separators and line breaks can't break the columns
+        assert_eq!((rows[1][5], rows[1][10]), ("Clients   divers", "Facture OMS"));
+        assert_eq!((rows[1][11], rows[1][12]), ("5,00", "0,00"));
+    }
+
+    #[test]
+    fn foreign_lines_carry_their_currency() {
+        let mut lines = [line(1, "20260301", "411000", 1200, 0), line(1, "20260301", "707000", 0, 1200)];
+        for line in &mut lines {
+            line.currency = String::from("USD");
+            line.currency_amount = Some(1313);
+        }
+        let (fec, _) = written(&lines).unwrap();
+        let row: Vec<&str> = fec.lines().nth(1).unwrap().split('|').collect();
+        assert_eq!((row[16], row[17]), ("13,13", "USD"));
+    }
+
+    #[test]
+    fn unbalanced_entries_are_not_exported() {
+        let lines = [
+            line(1, "20260301", "411000", 500, 0),
+            line(1, "20260301", "707000", 0, 500),
+            line(2, "20260302", "411000", 1200, 0),
+            line(2, "20260302", "707000", 0, 1199),
+        ];
+        let mut buffer = Vec::new();
+        let error = write(&mut buffer, &lines).unwrap_err();
+        assert_eq!(error.to_string(), "Entry 2 is unbalanced");
+        assert!(buffer.is_empty());
+    }
+}
\ No newline at end of file
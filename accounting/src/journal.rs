@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+
+use common::product::Product;
+use sqlx::PgPool;
+
+use crate::entry::{Entry, RevenueAccount, RevenueAccounts, SALES_JOURNAL};
+
+/// Revenue account of each of `products`, from the category of the product in the catalog.
+pub async fn revenue_accounts(pool: &PgPool, products: &[Product]) -> Result<RevenueAccounts, sqlx::Error> {
+    let (account, label): (String, String) =
+        sqlx::query_as("SELECT account, label FROM RevenueAccount WHERE category = 'misc'")
+            .fetch_one(pool)
+            .await?;
+    let misc = RevenueAccount { account, label };
+
+    let rows: Vec<(i32, String, String)> = sqlx::query_as(
+        "SELECT Product.id, RevenueAccount.account, RevenueAccount.label
+        FROM Product JOIN RevenueAccount ON RevenueAccount.category = Product.category
+        WHERE Product.id = ANY($1)",
+    )
+    .bind(products.iter().map(|product| product.id).collect::<Vec<i32>>())
+    .fetch_all(pool)
+    .await?;
+    let by_product: HashMap<i32, RevenueAccount> = rows
+        .into_iter()
+        .map(|(id, account, label)| (id, RevenueAccount { account, label }))
+        .collect();
+    Ok(RevenueAccounts { by_product, misc })
+}
+
+/// Posts the entry and returns its number, or `None` if its invoice or credit note was already
+/// posted. Entries are numbered in the order they are posted, without gaps: the table is locked
+/// until the entry is committed.
+pub async fn post(pool: &PgPool, entry: &Entry) -> Result<Option<i32>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("LOCK TABLE JournalEntry IN SHARE ROW EXCLUSIVE MODE")
+        .execute(&mut *tx)
+        .await?;
+
+    let posted: Option<i32> = sqlx::query_scalar("SELECT number FROM JournalEntry WHERE sourceType = $1 AND sourceId = $2")
+        .bind(entry.source.as_str())
+        .bind(entry.source_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    if posted.is_some() {
+        tx.rollback().await?;
+        return Ok(None);
+    }
+
+    let (id, number): (i32, i32) = sqlx::query_as(
+        "INSERT INTO JournalEntry (number, journal, sourceType, sourceId, pieceRef, pieceDate, label, currency, exchangeRate)
+        SELECT COALESCE(MAX(number), 0) + 1, $1, $2, $3, $4, $5::DATE, $6, $7, $8 FROM JournalEntry
+        RETURNING id, number",
+    )
+    .bind(SALES_JOURNAL.0)
+    .bind(entry.source.as_str())
+    .bind(entry.source_id)
+    .bind(&entry.piece_ref)
+    .bind(&entry.piece_date)
+    .bind(&entry.label)
+    .bind(&entry.currency)
+    .bind(entry.exchange_rate)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let lines = &entry.lines;
+    sqlx::query(
+        "INSERT INTO JournalLine (entryId, lineNumber, account, accountLabel, auxAccount, auxLabel, debit, credit, currencyAmount)
+        SELECT $1, * FROM UNNEST($2::INT[], $3::VARCHAR[], $4::VARCHAR[], $5::VARCHAR[], $6::VARCHAR[], $7::BIGINT[], $8::BIGINT[], $9::BIGINT[])",
+    )
+    .bind(id)
+    .bind((1..=lines.len() as i32).collect::<Vec<i32>>())
+    .bind(lines.iter().map(|line| line.account.clone()).collect::<Vec<String>>())
+    .bind(lines.iter().map(|line| line.account_label.clone()).collect::<Vec<String>>())
+    .bind(lines.iter().map(|line| line.aux_account.clone()).collect::<Vec<Option<String>>>())
+    .bind(lines.iter().map(|line| line.aux_label.clone()).collect::<Vec<Option<String>>>())
+    .bind(lines.iter().map(|line| line.debit).collect::<Vec<i64>>())
+    .bind(lines.iter().map(|line| line.credit).collect::<Vec<i64>>())
+    .bind(lines.iter().map(|line| line.currency_amount).collect::<Vec<Option<i64>>>())
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(Some(number))
+}
\ No newline at end of file
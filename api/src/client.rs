@@ -0,0 +1,90 @@
+use axum::extract::{Path, Query, State};
+use axum::Json;
+use chrono::NaiveDate;
+use common::invoice::Invoice;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::error::{ApiError, ErrorBody};
+use crate::invoice::{with_lines, InvoiceRow, INVOICE_COLUMNS};
+
+const DEFAULT_PER_PAGE: i64 = 20;
+const MAX_PER_PAGE: i64 = 100;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct InvoiceFilter {
+    /// First invoice date to include
+    from: Option<NaiveDate>,
+    /// Last invoice date to include
+    to: Option<NaiveDate>,
+    /// Page number, starting at 1
+    page: Option<i64>,
+    /// Invoices per page, at most 100
+    per_page: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct InvoicePage {
+    items: Vec<Invoice>,
+    page: i64,
+    per_page: i64,
+    total: i64,
+}
+
+/// List the invoices of a client, most recent first
+#[utoipa::path(
+    get,
+    path = "/clients/{id}/invoices",
+    params(("id" = i32, Path, description = "Client id"), InvoiceFilter),
+    responses(
+        (status = 200, description = "A page of invoices", body = InvoicePage),
+        (status = 404, description = "No client with this id", body = ErrorBody),
+    )
+)]
+pub async fn get_client_invoices(
+    State(pool): State<PgPool>,
+    Path(id): Path<i32>,
+    Query(filter): Query<InvoiceFilter>,
+) -> Result<Json<InvoicePage>, ApiError> {
+    let exists: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM Client WHERE id = $1)")
+        .bind(id)
+        .fetch_one(&pool)
+        .await?;
+    if !exists {
+        return Err(ApiError::NotFound(format!("Client with ID {} not found", id)));
+    }
+
+    let page = filter.page.unwrap_or(1).max(1);
+    let per_page = filter.per_page.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE);
+    let condition = "clientId = $1 AND ($2::date IS NULL OR date >= $2) AND ($3::date IS NULL OR date <= $3)";
+
+    let total: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM Invoice WHERE {}", condition))
+        .bind(id)
+        .bind(filter.from)
+        .bind(filter.to)
+        .fetch_one(&pool)
+        .await?;
+
+    let query = format!(
+        "SELECT {} FROM Invoice WHERE {} ORDER BY date DESC, id DESC LIMIT $4 OFFSET $5",
+        INVOICE_COLUMNS, condition
+    );
+    let rows = sqlx::query_as::<_, InvoiceRow>(&query)
+        .bind(id)
+        .bind(filter.from)
+        .bind(filter.to)
+        .bind(per_page)
+        // A page too far to count is past the last invoice anyway
+        .bind((page - 1).saturating_mul(per_page))
+        .fetch_all(&pool)
+        .await?;
+
+    Ok(Json(InvoicePage {
+        items: with_lines(&pool, rows).await?,
+        page,
+        per_page,
+        total,
+    }))
+}
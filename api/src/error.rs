@@ -0,0 +1,35 @@
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Debug)]
+pub enum ApiError {
+    NotFound(String),
+    Database(sqlx::Error),
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ErrorBody {
+    pub error: String,
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(error: sqlx::Error) -> Self {
+        ApiError::Database(error)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, error) = match self {
+            ApiError::NotFound(message) => (StatusCode::NOT_FOUND, message),
+            ApiError::Database(e) => {
+                eprintln!("Database error: {:?}", e);
+                (StatusCode::INTERNAL_SERVER_ERROR, String::from("Internal server error"))
+            }
+        };
+        (status, Json(ErrorBody { error })).into_response()
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+use axum::extract::{Path, State};
+use axum::Json;
+use common::client::Client;
+use common::invoice::Invoice;
+use common::product::Product;
+use sqlx::{FromRow, PgPool};
+
+use crate::error::{ApiError, ErrorBody};
+
+#[derive(FromRow)]
+pub struct InvoiceRow {
+    id: i32,
+    number: String,
+    client_id: i32,
+    client_name: String,
+    client_email: String,
+    client_address: String,
+    date: String,
+    total_price: f64,
+    size: i32,
+    currency: String,
+}
+
+#[derive(FromRow)]
+struct InvoiceLineRow {
+    invoice_id: i32,
+    product_id: i32,
+    name: String,
+    price: f64,
+    backordered: bool,
+    currency: String,
+    exchange_rate: f64,
+}
+
+pub const INVOICE_COLUMNS: &str = r#"id, number, clientId AS client_id, clientName AS client_name, clientEmail AS client_email,
+    clientAddress AS client_address, TO_CHAR(date, 'YYYY-MM-DD') AS date, totalPrice AS total_price, size, currency"#;
+
+/// Loads the lines of the given invoices and assembles the `Invoice` objects, keeping the row order.
+pub async fn with_lines(pool: &PgPool, rows: Vec<InvoiceRow>) -> Result<Vec<Invoice>, sqlx::Error> {
+    let ids: Vec<i32> = rows.iter().map(|row| row.id).collect();
+    let lines = sqlx::query_as::<_, InvoiceLineRow>(
+        r#"SELECT invoiceId AS invoice_id, productId AS product_id, name, price, backordered, currency, exchangeRate AS exchange_rate
+        FROM InvoiceLine
+        WHERE invoiceId = ANY($1)
+        ORDER BY invoiceId, lineNumber"#,
+    )
+    .bind(&ids)
+    .fetch_all(pool)
+    .await?;
+
+    let mut products: HashMap<i32, Vec<Product>> = HashMap::new();
+    for line in lines {
+        products.entry(line.invoice_id).or_default().push(Product {
+            id: line.product_id,
+            name: line.name,
+            price: line.price,
+            command_id: line.invoice_id,
+            backordered: line.backordered,
+            currency: line.currency,
+            exchange_rate: line.exchange_rate,
+            unit: None,
+        });
+    }
+
+    Ok(rows
+        .into_iter()
+        .map(|row| Invoice {
+            id: row.id,
+            number: row.number,
+            date: row.date,
+            client: Client {
+                id: row.client_id,
+                name: row.client_name,
+                email: row.client_email,
+                address: row.client_address,
+                // The invoice table keeps the client data, not its version
+                version: 0,
+                updated_at: String::new(),
+                currency: row.currency.clone(),
+            },
+            products: products.remove(&row.id).unwrap_or_default(),
+            total_price: row.total_price,
+            size: row.size,
+            currency: row.currency,
+        })
+        .collect())
+}
+
+pub async fn find_invoice(pool: &PgPool, id: i32) -> Result<Option<Invoice>, sqlx::Error> {
+    let query = format!("SELECT {} FROM Invoice WHERE id = $1", INVOICE_COLUMNS);
+    let rows = sqlx::query_as::<_, InvoiceRow>(&query)
+        .bind(id)
+        .fetch_all(pool)
+        .await?;
+    Ok(with_lines(pool, rows).await?.pop())
+}
+
+/// Get an invoice with its lines
+#[utoipa::path(
+    get,
+    path = "/invoices/{id}",
+    params(("id" = i32, Path, description = "Invoice id, which is the id of the invoiced command")),
+    responses(
+        (status = 200, description = "The invoice", body = Invoice),
+        (status = 404, description = "No invoice with this id", body = ErrorBody),
+    )
+)]
+pub async fn get_invoice(State(pool): State<PgPool>, Path(id): Path<i32>) -> Result<Json<Invoice>, ApiError> {
+    find_invoice(&pool, id)
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("Invoice with ID {} not found", id)))
+}
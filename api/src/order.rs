@@ -0,0 +1,141 @@
+use axum::extract::{Path, State};
+use axum::Json;
+use common::command::Command;
+use common::currency::default_rate;
+use common::lifecycle::LifecycleStatus;
+use common::product::Product;
+use serde::Serialize;
+use sqlx::{FromRow, PgPool};
+use utoipa::ToSchema;
+
+use crate::error::{ApiError, ErrorBody};
+
+/// Where the order stands in the pipeline.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderStatus {
+    /// Not merged into an invoice yet
+    Pending,
+    /// An invoice was stored for the order
+    Invoiced,
+    /// The merger moved the order or one of its products to the dead letter queue
+    DeadLettered,
+}
+
+/// A product of the order, at the price and in the currency it was ordered with.
+#[derive(Serialize, ToSchema)]
+pub struct OrderLine {
+    #[serde(flatten)]
+    product: Product,
+    /// Number of units ordered
+    quantity: i32,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Order {
+    command: Command,
+    products: Vec<OrderLine>,
+    status: OrderStatus,
+    /// Where the order stands between its placement and its delivery
+    lifecycle: LifecycleStatus,
+}
+
+#[derive(FromRow)]
+struct OrderRow {
+    id: i32,
+    client_id: i32,
+    date: String,
+    size: i64,
+    lifecycle: String,
+    invoiced: bool,
+    dead_lettered: bool,
+}
+
+#[derive(FromRow)]
+struct ProductRow {
+    id: i32,
+    name: String,
+    price: f64,
+    quantity: i32,
+    backordered: bool,
+    currency: String,
+}
+
+/// Get an order with its products and status
+#[utoipa::path(
+    get,
+    path = "/orders/{id}",
+    params(("id" = i32, Path, description = "Command id")),
+    responses(
+        (status = 200, description = "The order", body = Order),
+        (status = 404, description = "No order with this id", body = ErrorBody),
+    )
+)]
+pub async fn get_order(State(pool): State<PgPool>, Path(id): Path<i32>) -> Result<Json<Order>, ApiError> {
+    let row = sqlx::query_as::<_, OrderRow>(
+        r#"SELECT id, clientId AS client_id, TO_CHAR(date, 'YYYY-MM-DD') AS date,
+            (SELECT COALESCE(SUM(quantity), 0) FROM CommandProduct WHERE commandId = Command.id) AS size,
+            status AS lifecycle,
+            EXISTS(SELECT 1 FROM Invoice WHERE Invoice.id = Command.id) AS invoiced,
+            EXISTS(SELECT 1 FROM DeadLetter WHERE commandId = Command.id) AS dead_lettered
+        FROM Command
+        WHERE id = $1"#,
+    )
+    .bind(id)
+    .fetch_optional(&pool)
+    .await?
+    .ok_or_else(|| ApiError::NotFound(format!("Order with ID {} not found", id)))?;
+
+    let products = sqlx::query_as::<_, ProductRow>(
+        r#"SELECT Product.id, Product.name, CommandProduct.price, CommandProduct.quantity,
+            CommandProduct.backordered > 0 AS backordered, CommandProduct.currency
+        FROM CommandProduct
+        JOIN Product ON Product.id = CommandProduct.productId
+        WHERE CommandProduct.commandId = $1"#,
+    )
+    .bind(id)
+    .fetch_all(&pool)
+    .await?
+    .into_iter()
+    .map(|product| OrderLine {
+        product: Product {
+            id: product.id,
+            name: product.name,
+            price: product.price,
+            command_id: id,
+            backordered: product.backordered,
+            currency: product.currency,
+            exchange_rate: default_rate(),
+            unit: None,
+        },
+        quantity: product.quantity,
+    })
+    .collect();
+
+    let status = if row.invoiced {
+        OrderStatus::Invoiced
+    } else if row.dead_lettered {
+        OrderStatus::DeadLettered
+    } else {
+        OrderStatus::Pending
+    };
+
+    let lifecycle = row
+        .lifecycle
+        .parse()
+        .map_err(|e: String| ApiError::Database(sqlx::Error::Decode(e.into())))?;
+
+    Ok(Json(Order {
+        command: Command {
+            id: row.id,
+            client_id: row.client_id,
+            date: row.date,
+            size: row.size as i32,
+            client_version: 0,
+            client_snapshot: None,
+        },
+        products,
+        status,
+        lifecycle,
+    }))
+}
@@ -0,0 +1,60 @@
+use schema_registry_converter::async_impl::avro::AvroDecoder;
+use serde_json::Value;
+
+/// Decodes a schema registry framed Avro payload into `T`.
+pub async fn decode_payload<T: for<'de> serde::Deserialize<'de> + std::fmt::Debug>(
+    decoder: &AvroDecoder<'_>,
+    payload: &[u8],
+) -> Result<T, Box<dyn std::error::Error>> {
+    let decoded = decoder.decode(Option::from(payload)).await?;
+    let value: T = serde_json::from_value::<T>(Value::try_from(decoded.value)?)?;
+    Ok(value)
+}
+
+
+/// Adds a default value to the fields of `schema` named in `defaults`, in nested records too.
+///
+/// The derived schemas have no defaults, which the registry requires to accept a new field as a
+/// backward compatible change.
+pub fn with_defaults(schema: &str, defaults: &[(&str, Value)]) -> String {
+    fn visit(value: &mut Value, defaults: &[(&str, Value)]) {
+        match value {
+            Value::Object(object) => {
+                if let Some(Value::Array(fields)) = object.get_mut("fields") {
+                    for field in fields.iter_mut() {
+                        let name = field.get("name").and_then(Value::as_str).unwrap_or_default().to_string();
+                        if let Some((_, default)) = defaults.iter().find(|(field_name, _)| *field_name == name) {
+                            field["default"] = default.clone();
+                        }
+                    }
+                }
+                object.values_mut().for_each(|value| visit(value, defaults));
+            }
+            Value::Array(values) => values.iter_mut().for_each(|value| visit(value, defaults)),
+            _ => {}
+        }
+    }
+
+    match serde_json::from_str::<Value>(schema) {
+        Ok(mut value) => {
+            visit(&mut value, defaults);
+            value.to_string()
+        }
+        Err(_) => schema.to_string(),
+    }
+}
+
+/// Defaults of the fields added to the schemas after their first version.
+pub fn added_field_defaults() -> Vec<(&'static str, Value)> {
+    vec![
+        ("version", Value::from(0)),
+        ("updated_at", Value::from("")),
+        ("client_version", Value::from(0)),
+        ("backordered", Value::from(false)),
+        ("number", Value::from("")),
+        ("currency", Value::from("EUR")),
+        ("exchange_rate", Value::from(1.0)),
+        ("client_snapshot", Value::Null),
+        ("unit", Value::Null),
+    ]
+}
\ No newline at end of file
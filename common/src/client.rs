@@ -2,18 +2,38 @@ use serde::{Deserialize, Serialize};
 use serde_avro_derive::BuildSchema;
 use sqlx::PgPool;
 use async_trait::async_trait;
+use rand::rngs::StdRng;
 
-#[derive(Debug, Serialize, Deserialize, Clone, BuildSchema)]
+use crate::currency::default_currency;
+
+#[derive(Debug, Serialize, Deserialize, Clone, BuildSchema, sqlx::FromRow)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct Client {
     pub id: i32,
     pub name: String,
     pub email: String,
     pub address: String,
+    /// Incremented by every update of the client, so that consumers keep the latest one
+    #[serde(default)]
+    #[sqlx(default)]
+    pub version: i32,
+    /// Time of the last update, in RFC 3339
+    #[serde(default)]
+    #[sqlx(default)]
+    pub updated_at: String,
+    /// Currency the client is invoiced in
+    #[serde(default = "default_currency")]
+    #[sqlx(default)]
+    pub currency: String,
 }
 
+/// Columns to select to read a `Client`, with its version.
+pub const CLIENT_COLUMNS: &str =
+    r#"id, name, email, address, version, TO_CHAR(updatedAt AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS.MS"Z"') AS updated_at, currency"#;
+
 #[async_trait]
 pub trait ClientInterface {
-    fn generate_random() -> Self;
+    fn generate_random(rng: &mut StdRng) -> Self;
     async fn insert_into_db(&self, pool: &PgPool) -> Result<(), sqlx::Error>;
 }
 
@@ -24,6 +44,9 @@ impl Default for Client {
             name: "John Doe".to_string(),
             email: "john.doe@kafka.fr".to_string(),
             address: "123 Kafka Street".to_string(),
+            version: 1,
+            updated_at: String::new(),
+            currency: default_currency(),
         }
     }
 }
\ No newline at end of file
@@ -1,16 +1,27 @@
 use async_trait::async_trait;
-use rdkafka::producer::BaseProducer;
+use rand::rngs::StdRng;
 use schema_registry_converter::async_impl::schema_registry::SrSettings;
 use serde::{Deserialize, Serialize};
 use serde_avro_derive::BuildSchema;
 use sqlx::PgPool;
 
+use crate::client::Client;
+use crate::delivery::Sender;
+
 #[derive(Debug, Serialize, Deserialize, Clone, BuildSchema)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct Command {
     pub id: i32,
     pub client_id: i32,
     pub date: String,
     pub size: i32,
+    /// Version of the client when the order was placed, 0 if unknown
+    #[serde(default)]
+    pub client_version: i32,
+    /// The client as it was when the order was placed, so that the invoice doesn't depend on
+    /// the history of the compacted `Client` topic. Missing from older commands
+    #[serde(default)]
+    pub client_snapshot: Option<Client>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -27,12 +38,59 @@ impl From<(CommandFromDb, i32)> for Command {
             client_id: command.client_id,
             date: command.date,
             size,
+            client_version: 0,
+            client_snapshot: None,
         }
     }
 }
 
 #[async_trait]
 pub trait CommandInterface {
-    fn generate_random() -> Self;
-    async fn process_command(&self, pool: &PgPool, producer: &BaseProducer, sr_settings: &SrSettings) -> Result<(), sqlx::Error>;
+    fn generate_random(rng: &mut StdRng) -> Self;
+    async fn process_command(&self, pool: &PgPool, sender: &Sender, sr_settings: &SrSettings) -> Result<(), sqlx::Error>;
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_avro_fast::ser::SerializerConfig;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::avro::{added_field_defaults, with_defaults};
+
+    fn command(client_snapshot: Option<Client>) -> Command {
+        Command {
+            id: 42,
+            client_id: 7,
+            date: String::from("2025-01-20"),
+            size: 2,
+            client_version: 3,
+            client_snapshot,
+        }
+    }
+
+    #[test]
+    fn the_client_snapshot_is_optional_in_the_registered_schema() {
+        let schema = with_defaults(Command::schema().unwrap().json(), &added_field_defaults());
+        let schema: Value = serde_json::from_str(&schema).unwrap();
+        let fields = schema["fields"].as_array().unwrap();
+        let snapshot = fields.iter().find(|field| field["name"] == "client_snapshot").unwrap();
+        assert_eq!(snapshot["type"][0], "null");
+        assert!(snapshot["type"][1]["name"].as_str().unwrap().ends_with("Client"));
+        assert_eq!(snapshot["default"], Value::Null);
+    }
+
+    #[test]
+    fn the_client_snapshot_survives_encoding() {
+        let schema = Command::schema().unwrap();
+        let client: Client = serde_json::from_value(serde_json::json!({
+            "id": 7, "name": "Ada", "email": "ada@example.com", "address": "Paris", "version": 3, "currency": "GBP"
+        }))
+        .unwrap();
+        for command in [command(Some(client)), command(None)] {
+            let datum = serde_avro_fast::to_datum_vec(&command, &mut SerializerConfig::new(&schema)).unwrap();
+            let decoded: Command = serde_avro_fast::from_datum_slice(&datum, &schema).unwrap();
+            assert_eq!(serde_json::to_value(&decoded).unwrap(), serde_json::to_value(&command).unwrap());
+        }
+    }
 }
\ No newline at end of file
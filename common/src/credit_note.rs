@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+use serde_avro_derive::BuildSchema;
+
+use crate::currency::default_currency;
+use crate::{client::Client, invoice::Invoice, product::Product};
+
+/// Cancels all or part of an invoice. Its lines are the credited units of the invoice, with
+/// negative prices, so that the invoice and its credit notes add up to what the client owes.
+#[derive(Deserialize, Serialize, Debug, Clone, BuildSchema)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreditNote {
+    /// Id of the cancellation or refund event
+    pub id: i32,
+    pub invoice_id: i32,
+    pub date: String,
+    pub reason: String,
+    pub client: Client,
+    pub products: Vec<Product>,
+    pub total_price: f64,
+    pub size: i32,
+    /// Currency of the invoice
+    #[serde(default = "default_currency")]
+    pub currency: String,
+}
+
+impl CreditNote {
+    pub fn new(id: i32, invoice: &Invoice, date: String, reason: String) -> Self {
+        CreditNote {
+            id,
+            invoice_id: invoice.id,
+            date,
+            reason,
+            client: invoice.client.clone(),
+            products: vec![],
+            total_price: 0.0,
+            size: 0,
+            currency: invoice.currency.clone(),
+        }
+    }
+
+    /// Adds a line cancelling `product`, an invoiced unit.
+    pub fn credit_product(&mut self, product: &Product) {
+        self.total_price -= product.price;
+        self.size += 1;
+        self.products.push(Product {
+            price: -product.price,
+            ..product.clone()
+        });
+    }
+}
\ No newline at end of file
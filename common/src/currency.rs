@@ -0,0 +1,142 @@
+use sqlx::PgPool;
+
+use crate::vat::round_cents;
+
+/// Currency of the catalog and of the accounts
+pub const BASE_CURRENCY: &str = "EUR";
+
+/// Currencies the clients pay in
+pub const CURRENCIES: [&str; 3] = ["EUR", "USD", "GBP"];
+
+pub fn default_currency() -> String {
+    BASE_CURRENCY.to_string()
+}
+
+pub fn default_rate() -> f64 {
+    1.0
+}
+
+/// A row of the `ExchangeRate` table, with `valid_from` as `YYYY-MM-DD`
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct ExchangeRate {
+    pub base_currency: String,
+    pub quote_currency: String,
+    pub valid_from: String,
+    pub rate: f64,
+}
+
+/// Rate converting an amount in `from` into `to` on `date`, as `YYYY-MM-DD`: the latest rate of
+/// the pair valid from that date or before, or the inverse of the reverse pair. `None` if there
+/// is no such rate.
+pub async fn rate_on(pool: &PgPool, from: &str, to: &str, date: &str) -> Result<Option<f64>, sqlx::Error> {
+    if from == to {
+        return Ok(Some(1.0));
+    }
+    let rates: Vec<ExchangeRate> = sqlx::query_as(
+        "SELECT baseCurrency AS base_currency, quoteCurrency AS quote_currency,
+            TO_CHAR(validFrom, 'YYYY-MM-DD') AS valid_from, rate
+        FROM ExchangeRate
+        WHERE (baseCurrency = $1 AND quoteCurrency = $2) OR (baseCurrency = $2 AND quoteCurrency = $1)",
+    )
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await?;
+    Ok(rate_among(&rates, from, to, date))
+}
+
+/// Rate converting `from` into `to` on `date` among `rates`, as `rate_on` looks it up. The rate of
+/// the pair wins over the inverse of the reverse pair valid from the same day.
+pub fn rate_among(rates: &[ExchangeRate], from: &str, to: &str, date: &str) -> Option<f64> {
+    if from == to {
+        return Some(1.0);
+    }
+    rates
+        .iter()
+        .filter(|rate| rate.valid_from.as_str() <= date)
+        .filter_map(|rate| {
+            if rate.base_currency == from && rate.quote_currency == to {
+                Some((rate.valid_from.as_str(), true, rate.rate))
+            } else if rate.base_currency == to && rate.quote_currency == from {
+                Some((rate.valid_from.as_str(), false, 1.0 / rate.rate))
+            } else {
+                None
+            }
+        })
+        .max_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)))
+        .map(|(_, _, rate)| rate)
+}
+
+/// `amount` converted with `rate`, rounded to the cent.
+pub fn convert(amount: f64, rate: f64) -> f64 {
+    round_cents(amount * rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rate(base_currency: &str, quote_currency: &str, valid_from: &str, rate: f64) -> ExchangeRate {
+        ExchangeRate {
+            base_currency: base_currency.to_string(),
+            quote_currency: quote_currency.to_string(),
+            valid_from: valid_from.to_string(),
+            rate,
+        }
+    }
+
+    fn rates() -> Vec<ExchangeRate> {
+        vec![
+            rate("EUR", "USD", "2023-01-01", 1.07),
+            rate("EUR", "USD", "2025-01-01", 1.04),
+            rate("EUR", "GBP", "2023-01-01", 0.88),
+            rate("GBP", "EUR", "2025-01-01", 1.25),
+        ]
+    }
+
+    #[test]
+    fn takes_the_latest_rate_valid_on_the_date() {
+        let rates = rates();
+        assert_eq!(rate_among(&rates, "EUR", "USD", "2024-06-30"), Some(1.07));
+        assert_eq!(rate_among(&rates, "EUR", "USD", "2026-03-01"), Some(1.04));
+    }
+
+    #[test]
+    fn a_rate_is_valid_from_its_first_day() {
+        let rates = rates();
+        assert_eq!(rate_among(&rates, "EUR", "USD", "2024-12-31"), Some(1.07));
+        assert_eq!(rate_among(&rates, "EUR", "USD", "2025-01-01"), Some(1.04));
+        assert_eq!(rate_among(&rates, "EUR", "USD", "2022-12-31"), None);
+    }
+
+    #[test]
+    fn inverts_the_reverse_pair() {
+        let rates = rates();
+        assert_eq!(rate_among(&rates, "USD", "EUR", "2024-01-01"), Some(1.0 / 1.07));
+        // The reverse pair has its own rate from 2025
+        assert_eq!(rate_among(&rates, "EUR", "GBP", "2024-12-31"), Some(0.88));
+        assert_eq!(rate_among(&rates, "EUR", "GBP", "2025-01-01"), Some(1.0 / 1.25));
+        assert_eq!(rate_among(&rates, "GBP", "EUR", "2024-01-01"), Some(1.0 / 0.88));
+    }
+
+    #[test]
+    fn prefers_the_pair_over_the_reverse_pair_of_the_same_day() {
+        let rates = vec![rate("GBP", "USD", "2025-01-01", 1.25), rate("USD", "GBP", "2025-01-01", 0.79)];
+        assert_eq!(rate_among(&rates, "USD", "GBP", "2025-01-01"), Some(0.79));
+        assert_eq!(rate_among(&rates, "GBP", "USD", "2025-01-01"), Some(1.25));
+    }
+
+    #[test]
+    fn same_currency_or_unknown_pair() {
+        assert_eq!(rate_among(&[], "USD", "USD", "2025-01-01"), Some(1.0));
+        assert_eq!(rate_among(&rates(), "USD", "GBP", "2025-01-01"), None);
+    }
+
+    #[test]
+    fn converts_to_the_cent() {
+        assert_eq!(convert(10.0, 1.17), 11.7);
+        assert_eq!(convert(19.99, 1.0 / 1.07), 18.68);
+        assert_eq!(convert(0.125, 1.0), 0.13);
+        assert_eq!(convert(3.3333, 0.87), 2.9);
+    }
+}
\ No newline at end of file
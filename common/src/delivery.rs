@@ -0,0 +1,92 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rdkafka::error::KafkaError;
+use rdkafka::producer::{FutureProducer, FutureRecord, Producer};
+use rdkafka::util::Timeout;
+use rdkafka::ClientConfig;
+
+/// Time librdkafka has to deliver a message, retries included, before reporting it as failed.
+pub const DELIVERY_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TopicStats {
+    pub acked: u64,
+    pub failed: u64,
+}
+
+/// A Kafka producer that waits for the broker acknowledgement of every message and counts the
+/// deliveries per topic.
+#[derive(Clone)]
+pub struct Sender {
+    producer: FutureProducer,
+    stats: Arc<Mutex<BTreeMap<String, TopicStats>>>,
+}
+
+impl Sender {
+    /// Creates an idempotent producer. librdkafka retries the retriable failures itself, with
+    /// the sequence number of the first attempt, so a retried message is neither written twice
+    /// nor reordered. The application never sends a message again, which would make it a new one.
+    pub fn new(bootstrap_servers: &str) -> Self {
+        let producer = ClientConfig::new()
+            .set("bootstrap.servers", bootstrap_servers)
+            .set("enable.idempotence", "true")
+            .set("delivery.timeout.ms", DELIVERY_TIMEOUT.as_millis().to_string())
+            .create()
+            .expect("Failed to create Kafka producer");
+        Sender {
+            producer,
+            stats: Arc::new(Mutex::new(BTreeMap::new())),
+        }
+    }
+
+    pub fn producer(&self) -> &FutureProducer {
+        &self.producer
+    }
+
+    /// Sends a message and waits for its acknowledgement. Messages are enqueued in the order of
+    /// the first poll of the returned futures, so joined sends keep their order.
+    ///
+    /// A full local queue is waited on before enqueuing, without a timeout. Once enqueued, an
+    /// error means librdkafka gave up after `DELIVERY_TIMEOUT`: the message may or may not have
+    /// been written, and it isn't sent again.
+    pub async fn send(&self, topic: &str, key: &str, payload: &[u8], timestamp: Option<i64>) -> Result<(), KafkaError> {
+        let mut record = FutureRecord::to(topic).key(key).payload(payload);
+        if let Some(timestamp) = timestamp {
+            record = record.timestamp(timestamp);
+        }
+        match self.producer.send(record, Timeout::Never).await {
+            Ok(_) => {
+                self.update(topic, |stats| stats.acked += 1);
+                Ok(())
+            }
+            Err((e, _)) => {
+                self.update(topic, |stats| stats.failed += 1);
+                Err(e)
+            }
+        }
+    }
+
+    /// Waits for the messages still in flight to be delivered.
+    pub fn flush(&self, timeout: Duration) {
+        if let Err(e) = self.producer.flush(timeout) {
+            eprintln!("Failed to flush the producer: {:?}", e);
+        }
+    }
+
+    pub fn stats(&self) -> BTreeMap<String, TopicStats> {
+        self.stats.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+
+    pub fn print_stats(&self) {
+        for (topic, stats) in self.stats() {
+            println!("Deliveries to {}: {} acked, {} failed", topic, stats.acked, stats.failed);
+        }
+    }
+
+    fn update(&self, topic: &str, update: impl FnOnce(&mut TopicStats)) {
+        let mut stats = self.stats.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        update(stats.entry(topic.to_string()).or_default());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+use serde_avro_derive::BuildSchema;
+
+/// An invoice rendered as an e-invoicing document, published to the `InvoiceDocument` topic keyed
+/// by invoice id, one message per format.
+#[derive(Debug, Serialize, Deserialize, Clone, BuildSchema)]
+pub struct InvoiceDocument {
+    pub invoice_id: i32,
+    /// Legal number of the invoice
+    pub number: String,
+    /// `ubl` for UBL 2.1 or `cii` for the Factur-X cross industry invoice
+    pub format: String,
+    pub content_type: String,
+    /// The XML document
+    pub content: String,
+}
\ No newline at end of file
@@ -0,0 +1,97 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::{Json, Router};
+use rdkafka::client::{Client, ClientContext};
+use schema_registry_converter::async_impl::schema_registry::{get_schema_by_subject, SrSettings};
+use schema_registry_converter::schema_registry_common::SubjectNameStrategy;
+
+/// Delay between two rounds of readiness probes.
+pub const PROBE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Named readiness checks shared between the probe loop of a service and its HTTP server.
+///
+/// Every check starts as not ready, and the service is ready once all of them pass.
+#[derive(Debug, Clone, Default)]
+pub struct Readiness {
+    checks: Arc<RwLock<BTreeMap<&'static str, bool>>>,
+}
+
+impl Readiness {
+    pub fn new(checks: &[&'static str]) -> Self {
+        let checks = checks.iter().map(|check| (*check, false)).collect();
+        Readiness {
+            checks: Arc::new(RwLock::new(checks)),
+        }
+    }
+
+    pub fn set(&self, check: &'static str, ready: bool) {
+        self.checks.write().unwrap().insert(check, ready);
+    }
+
+    pub fn get(&self, check: &'static str) -> bool {
+        self.checks.read().unwrap().get(check).copied().unwrap_or(false)
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.checks.read().unwrap().values().all(|ready| *ready)
+    }
+
+    pub fn snapshot(&self) -> BTreeMap<&'static str, bool> {
+        self.checks.read().unwrap().clone()
+    }
+}
+
+/// Routes for `/healthz` (the process is alive) and `/readyz` (every check passes).
+pub fn router(readiness: Readiness) -> Router {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .with_state(readiness)
+}
+
+pub async fn serve(addr: &str, router: Router) -> std::io::Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    println!("Health server listening on {}", addr);
+    axum::serve(listener, router).await
+}
+
+async fn healthz() -> &'static str {
+    "ok"
+}
+
+async fn readyz(State(readiness): State<Readiness>) -> (StatusCode, Json<BTreeMap<&'static str, bool>>) {
+    let status = if readiness.is_ready() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(readiness.snapshot()))
+}
+
+/// Checks that the value schema of every topic is registered in the schema registry.
+pub async fn subjects_registered(sr_settings: &SrSettings, topics: &[&str]) -> bool {
+    for topic in topics {
+        let strategy = SubjectNameStrategy::TopicNameStrategy(topic.to_string(), false);
+        if let Err(e) = get_schema_by_subject(sr_settings, &strategy).await {
+            eprintln!("Schema for topic {} is not registered: {}", topic, e);
+            return false;
+        }
+    }
+    true
+}
+
+/// Checks that the Kafka client can fetch the cluster metadata. This call blocks.
+pub fn kafka_connected<C: ClientContext>(client: &Client<C>) -> bool {
+    match client.fetch_metadata(None, Duration::from_secs(1)) {
+        Ok(metadata) => !metadata.brokers().is_empty(),
+        Err(e) => {
+            eprintln!("Failed to fetch Kafka metadata: {:?}", e);
+            false
+        }
+    }
+}
@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+use serde_avro_derive::BuildSchema;
+
+/// Published to the `StockLevel` topic, keyed by product id, whenever the stock of a product
+/// changes.
+#[derive(Debug, Serialize, Deserialize, Clone, BuildSchema)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct StockChange {
+    pub product_id: i32,
+    /// Units in stock after the change
+    pub available: i32,
+    /// Units added, or removed if negative
+    pub change: i32,
+    /// `order`, `cancellation`, `restock` or `backorder`, when a restock fills pending backorders
+    pub reason: String,
+    /// Time of the change, in RFC 3339
+    pub at: String,
+}
\ No newline at end of file
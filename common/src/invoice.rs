@@ -1,27 +1,40 @@
 use serde::{Deserialize, Serialize};
 use serde_avro_derive::BuildSchema;
 
+use crate::currency::default_currency;
 use crate::{client::Client, command::Command, product::Product};
 
 #[derive(Deserialize, Serialize, Debug, Clone, BuildSchema)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct Invoice {
+    /// Id of the invoiced command
     pub id: i32,
+    /// Legal number, continuous per fiscal year, such as `OMS-2026-000123`
+    #[serde(default)]
+    pub number: String,
     pub date: String,
     pub client: Client,
     pub products: Vec<Product>,
     pub total_price: f64,
     pub size: i32,
+    /// Currency of the prices, the one of the client when the order was placed
+    #[serde(default = "default_currency")]
+    pub currency: String,
 }
 
 impl From<Command> for Invoice {
     fn from(command: Command) -> Self {
         Invoice {
             id: command.id,
+            // Assigned by the numbering service once the invoice is complete
+            number: String::new(),
             date: command.date,
             client: Client::default(),
             products: vec![],
             total_price: 0.0,
             size: command.size,
+            // The currency of the client, once it is known
+            currency: default_currency(),
         }
     }
 }
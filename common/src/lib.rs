@@ -1,4 +1,15 @@
+pub mod avro;
 pub mod client;
 pub mod command;
+pub mod credit_note;
+pub mod currency;
+pub mod delivery;
+pub mod document;
+pub mod health;
+pub mod inventory;
+pub mod lifecycle;
+pub mod payment;
 pub mod product;
+pub mod rng;
+pub mod vat;
 pub mod invoice;
\ No newline at end of file
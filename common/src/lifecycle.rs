@@ -0,0 +1,150 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use serde_avro_derive::BuildSchema;
+
+/// Where an order stands between its placement and its delivery, stored in `Command.status`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum LifecycleStatus {
+    Placed,
+    Paid,
+    Shipped,
+    Cancelled,
+    PartiallyRefunded,
+}
+
+impl LifecycleStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LifecycleStatus::Placed => "placed",
+            LifecycleStatus::Paid => "paid",
+            LifecycleStatus::Shipped => "shipped",
+            LifecycleStatus::Cancelled => "cancelled",
+            LifecycleStatus::PartiallyRefunded => "partially_refunded",
+        }
+    }
+
+    /// The statuses an order in this status can move to. An order is cancelled before it ships,
+    /// and refunded once paid. A cancelled order doesn't change anymore.
+    pub fn next(&self) -> &'static [LifecycleStatus] {
+        match self {
+            LifecycleStatus::Placed => &[LifecycleStatus::Paid, LifecycleStatus::Cancelled],
+            LifecycleStatus::Paid => &[
+                LifecycleStatus::Shipped,
+                LifecycleStatus::Cancelled,
+                LifecycleStatus::PartiallyRefunded,
+            ],
+            LifecycleStatus::Shipped => &[LifecycleStatus::PartiallyRefunded],
+            LifecycleStatus::Cancelled => &[],
+            LifecycleStatus::PartiallyRefunded => &[LifecycleStatus::PartiallyRefunded],
+        }
+    }
+
+    pub fn can_become(&self, status: LifecycleStatus) -> bool {
+        self.next().contains(&status)
+    }
+}
+
+impl fmt::Display for LifecycleStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for LifecycleStatus {
+    type Err = String;
+
+    fn from_str(status: &str) -> Result<Self, Self::Err> {
+        match status {
+            "placed" => Ok(LifecycleStatus::Placed),
+            "paid" => Ok(LifecycleStatus::Paid),
+            "shipped" => Ok(LifecycleStatus::Shipped),
+            "cancelled" => Ok(LifecycleStatus::Cancelled),
+            "partially_refunded" => Ok(LifecycleStatus::PartiallyRefunded),
+            _ => Err(format!("Unknown order status {}", status)),
+        }
+    }
+}
+
+/// Published to the `Cancellation` topic, keyed by command id, when an order is cancelled.
+#[derive(Debug, Serialize, Deserialize, Clone, BuildSchema)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Cancellation {
+    /// Id of the order event, also the id of the credit note
+    pub id: i32,
+    pub command_id: i32,
+    pub date: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, BuildSchema)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RefundLine {
+    pub product_id: i32,
+    pub quantity: i32,
+}
+
+/// Published to the `Refund` topic, keyed by command id, when some units of an order are refunded.
+#[derive(Debug, Serialize, Deserialize, Clone, BuildSchema)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Refund {
+    /// Id of the order event, also the id of the credit note
+    pub id: i32,
+    pub command_id: i32,
+    pub date: String,
+    pub reason: String,
+    pub lines: Vec<RefundLine>,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [LifecycleStatus; 5] = [
+        LifecycleStatus::Placed,
+        LifecycleStatus::Paid,
+        LifecycleStatus::Shipped,
+        LifecycleStatus::Cancelled,
+        LifecycleStatus::PartiallyRefunded,
+    ];
+
+    #[test]
+    fn allowed_transitions() {
+        use LifecycleStatus::*;
+        let allowed = [
+            (Placed, Paid),
+            (Placed, Cancelled),
+            (Paid, Shipped),
+            (Paid, Cancelled),
+            (Paid, PartiallyRefunded),
+            (Shipped, PartiallyRefunded),
+            (PartiallyRefunded, PartiallyRefunded),
+        ];
+        for from in ALL {
+            for to in ALL {
+                assert_eq!(from.can_become(to), allowed.contains(&(from, to)), "{} -> {}", from, to);
+            }
+        }
+    }
+
+    #[test]
+    fn cancelled_orders_are_final() {
+        assert!(LifecycleStatus::Cancelled.next().is_empty());
+        // Shipped orders are refunded, not cancelled
+        assert!(!LifecycleStatus::Shipped.can_become(LifecycleStatus::Cancelled));
+        // Nothing goes back to placed
+        assert!(ALL.iter().all(|status| !status.can_become(LifecycleStatus::Placed)));
+    }
+
+    #[test]
+    fn statuses_roundtrip_through_their_column() {
+        for status in ALL {
+            assert_eq!(status.as_str().parse::<LifecycleStatus>(), Ok(status));
+        }
+        assert!("refunded".parse::<LifecycleStatus>().is_err());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+use serde_avro_derive::BuildSchema;
+
+/// Published to the `Payment` topic, keyed by command id, for every payment attempt of an order.
+#[derive(Debug, Serialize, Deserialize, Clone, BuildSchema)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Payment {
+    pub id: i32,
+    pub command_id: i32,
+    /// `authorized`, `captured`, `failed` or `refunded`. Only captured amounts are paid, and
+    /// refunded amounts, which are negative, paid back
+    pub status: String,
+    pub amount: f64,
+    /// `card`, `paypal` or `bank_transfer`
+    pub method: String,
+    /// Time of the event, in RFC 3339
+    pub at: String,
+}
+
+/// Published to the `InvoiceStatus` topic, keyed by invoice id, whenever the reconciliation of an
+/// invoice with its payments changes.
+#[derive(Debug, Serialize, Deserialize, Clone, BuildSchema)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct InvoiceStatus {
+    pub invoice_id: i32,
+    /// Invoice total plus the totals of its credit notes
+    pub due: f64,
+    /// Sum of the captured payments, less the refunds
+    pub paid: f64,
+    /// `paid`, `underpaid`, `overpaid` or `unpaid`
+    pub status: String,
+    /// Time of the reconciliation, in RFC 3339
+    pub at: String,
+}
\ No newline at end of file
@@ -1,23 +1,68 @@
 use async_trait::async_trait;
+use rand::rngs::StdRng;
 use serde::{Deserialize, Serialize};
 use serde_avro_derive::BuildSchema;
 use sqlx::PgPool;
 
+use crate::currency::{default_currency, default_rate};
+
 #[derive(Debug, Serialize, Deserialize, Clone, BuildSchema)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct Product {
     pub id: i32,
     pub name: String,
     pub price: f64,
     pub command_id: i32,
+    /// Whether the unit was out of stock when ordered, and ships once restocked
+    #[serde(default)]
+    pub backordered: bool,
+    /// Base currency of the product. `price` is in it until the unit is invoiced, then in the
+    /// currency of the invoice
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    /// Rate `price` was converted with into the currency of the invoice, 1 if it wasn't
+    #[serde(default = "default_rate")]
+    pub exchange_rate: f64,
+    /// Index of the unit within its order, from 0, so that a message sent twice is counted once.
+    /// `None` in the messages published before it was added
+    #[serde(default)]
+    pub unit: Option<i32>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct ProductFromDb {
     pub id: i32,
     pub name: String,
     pub price: f64,
+    pub currency: String,
 }
 
+/// A product of the catalog, published to the compacted `ProductCatalog` topic keyed by its id.
+#[derive(Debug, Serialize, Deserialize, Clone, BuildSchema, sqlx::FromRow)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CatalogProduct {
+    pub id: i32,
+    pub name: String,
+    pub price: f64,
+    pub category: String,
+    /// Incremented by every update of the product, so that consumers keep the latest one
+    #[serde(default)]
+    #[sqlx(default)]
+    pub version: i32,
+    /// Time of the last update, in RFC 3339
+    #[serde(default)]
+    #[sqlx(default)]
+    pub updated_at: String,
+    /// Currency of `price`
+    #[serde(default = "default_currency")]
+    #[sqlx(default)]
+    pub currency: String,
+}
+
+/// Columns to select to read a `CatalogProduct`, with its version.
+pub const CATALOG_PRODUCT_COLUMNS: &str =
+    r#"id, name, price, category, version, TO_CHAR(updatedAt AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS.MS"Z"') AS updated_at, currency"#;
+
 impl From<(ProductFromDb, i32)> for Product {
     fn from((product, command_id): (ProductFromDb, i32)) -> Self {
         Product {
@@ -25,12 +70,16 @@ impl From<(ProductFromDb, i32)> for Product {
             name: product.name,
             price: product.price,
             command_id,
+            backordered: false,
+            currency: product.currency,
+            exchange_rate: default_rate(),
+            unit: None,
         }
     }
 }
 
 #[async_trait]
 pub trait ProductInterface {
-    fn generate_random() -> Self;
+    fn generate_random(rng: &mut StdRng) -> Self;
     async fn insert_into_db(&self, pool: &PgPool) -> Result<(), sqlx::Error>;
 }
\ No newline at end of file
@@ -0,0 +1,30 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+/// A random number generator shared by every generator of a process.
+///
+/// Created from a seed, it gives the same sequence of values on every run, so that a seeding or
+/// a production run can be reproduced. Without a seed, it is initialized from the OS entropy.
+#[derive(Clone)]
+pub struct SharedRng {
+    rng: Arc<Mutex<StdRng>>,
+}
+
+impl SharedRng {
+    pub fn new(seed: Option<u64>) -> Self {
+        let rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        SharedRng {
+            rng: Arc::new(Mutex::new(rng)),
+        }
+    }
+
+    /// Locks the generator. The guard must be dropped before any `.await`.
+    pub fn lock(&self) -> MutexGuard<'_, StdRng> {
+        self.rng.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,50 @@
+/// Standard French VAT rate, in percent. Catalog prices include it.
+pub const STANDARD_RATE: f64 = 20.0;
+
+pub fn round_cents(amount: f64) -> f64 {
+    (amount * 100.0).round() / 100.0
+}
+
+/// VAT of an invoice or credit note whose prices include VAT at a single rate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VatBreakdown {
+    /// Rate in percent
+    pub rate: f64,
+    /// Amount without VAT of each line, in the order of the products
+    pub lines: Vec<f64>,
+    /// Sum of the line amounts without VAT
+    pub net: f64,
+    /// VAT on `net`
+    pub vat: f64,
+    /// What the client pays: the sum of the prices with VAT
+    pub payable: f64,
+    /// `payable` minus `net + vat`, a cent or so, because VAT is computed on the sum of the
+    /// rounded line amounts rather than on each price
+    pub rounding: f64,
+}
+
+impl VatBreakdown {
+    /// Splits `prices`, which include VAT at `rate` percent, into amounts without VAT and VAT, all
+    /// rounded to the cent.
+    pub fn new(prices: impl IntoIterator<Item = f64>, rate: f64) -> Self {
+        let mut payable = 0.0;
+        let lines: Vec<f64> = prices
+            .into_iter()
+            .map(|price| {
+                payable += price;
+                round_cents(price / (1.0 + rate / 100.0))
+            })
+            .collect();
+        let net = round_cents(lines.iter().sum());
+        let vat = round_cents(net * rate / 100.0);
+        let payable = round_cents(payable);
+        VatBreakdown {
+            rate,
+            lines,
+            net,
+            vat,
+            payable,
+            rounding: round_cents(payable - net - vat),
+        }
+    }
+}
\ No newline at end of file
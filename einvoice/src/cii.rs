@@ -0,0 +1,196 @@
+use std::io;
+
+use common::invoice::Invoice;
+use quick_xml::events::BytesText;
+
+use crate::document::{Document, DocumentOptions, CUSTOMIZATION_ID, INVOICE_TYPE_CODE, STANDARD_CATEGORY, UNIT_CODE};
+use crate::xml::{self, XmlWriter};
+
+const RSM_NS: &str = "urn:un:unece:uncefact:data:standard:CrossIndustryInvoice:100";
+const RAM_NS: &str = "urn:un:unece:uncefact:data:standard:ReusableAggregateBusinessInformationEntity:100";
+const UDT_NS: &str = "urn:un:unece:uncefact:data:standard:UnqualifiedDataType:100";
+
+/// Renders the document as a cross industry invoice, the XML embedded in a Factur-X PDF, with
+/// the EN 16931 profile.
+pub fn render(document: &Document) -> io::Result<String> {
+    let Document { invoice, options, vat, payment } = document;
+    let currency = invoice.currency.as_str();
+
+    let mut writer = xml::writer()?;
+    writer
+        .create_element("rsm:CrossIndustryInvoice")
+        .with_attributes([("xmlns:rsm", RSM_NS), ("xmlns:ram", RAM_NS), ("xmlns:udt", UDT_NS)])
+        .write_inner_content(|writer| {
+            writer.create_element("rsm:ExchangedDocumentContext").write_inner_content(|writer| {
+                writer
+                    .create_element("ram:GuidelineSpecifiedDocumentContextParameter")
+                    .write_inner_content(|writer| xml::text(writer, "ram:ID", CUSTOMIZATION_ID))?;
+                Ok(())
+            })?;
+            writer.create_element("rsm:ExchangedDocument").write_inner_content(|writer| {
+                xml::text(writer, "ram:ID", &invoice.number)?;
+                xml::text(writer, "ram:TypeCode", INVOICE_TYPE_CODE)?;
+                writer
+                    .create_element("ram:IssueDateTime")
+                    .write_inner_content(|writer| date(writer, &invoice.date))?;
+                Ok(())
+            })?;
+
+            writer.create_element("rsm:SupplyChainTradeTransaction").write_inner_content(|writer| {
+                for (index, (product, net)) in invoice.products.iter().zip(&vat.lines).enumerate() {
+                    writer
+                        .create_element("ram:IncludedSupplyChainTradeLineItem")
+                        .write_inner_content(|writer| {
+                            writer
+                                .create_element("ram:AssociatedDocumentLineDocument")
+                                .write_inner_content(|writer| xml::text(writer, "ram:LineID", &(index + 1).to_string()))?;
+                            writer.create_element("ram:SpecifiedTradeProduct").write_inner_content(|writer| {
+                                xml::text(writer, "ram:SellerAssignedID", &product.id.to_string())?;
+                                xml::text(writer, "ram:Name", &product.name)
+                            })?;
+                            writer
+                                .create_element("ram:SpecifiedLineTradeAgreement")
+                                .write_inner_content(|writer| {
+                                    writer
+                                        .create_element("ram:NetPriceProductTradePrice")
+                                        .write_inner_content(|writer| xml::amount(writer, "ram:ChargeAmount", *net))?;
+                                    Ok(())
+                                })?;
+                            writer
+                                .create_element("ram:SpecifiedLineTradeDelivery")
+                                .write_inner_content(|writer| {
+                                    writer
+                                        .create_element("ram:BilledQuantity")
+                                        .with_attribute(("unitCode", UNIT_CODE))
+                                        .write_text_content(BytesText::new("1"))?;
+                                    Ok(())
+                                })?;
+                            writer
+                                .create_element("ram:SpecifiedLineTradeSettlement")
+                                .write_inner_content(|writer| {
+                                    writer.create_element("ram:ApplicableTradeTax").write_inner_content(|writer| {
+                                        xml::text(writer, "ram:TypeCode", "VAT")?;
+                                        xml::text(writer, "ram:CategoryCode", STANDARD_CATEGORY)?;
+                                        xml::amount(writer, "ram:RateApplicablePercent", vat.rate)
+                                    })?;
+                                    writer
+                                        .create_element("ram:SpecifiedTradeSettlementLineMonetarySummation")
+                                        .write_inner_content(|writer| xml::amount(writer, "ram:LineTotalAmount", *net))?;
+                                    Ok(())
+                                })?;
+                            Ok(())
+                        })?;
+                }
+
+                writer
+                    .create_element("ram:ApplicableHeaderTradeAgreement")
+                    .write_inner_content(|writer| {
+                        seller(writer, options)?;
+                        buyer(writer, invoice, options)?;
+                        writer
+                            .create_element("ram:BuyerOrderReferencedDocument")
+                            .write_inner_content(|writer| xml::text(writer, "ram:IssuerAssignedID", &invoice.id.to_string()))?;
+                        Ok(())
+                    })?;
+                writer.create_element("ram:ApplicableHeaderTradeDelivery").write_empty()?;
+
+                writer
+                    .create_element("ram:ApplicableHeaderTradeSettlement")
+                    .write_inner_content(|writer| {
+                        xml::text(writer, "ram:InvoiceCurrencyCode", currency)?;
+                        writer.create_element("ram:ApplicableTradeTax").write_inner_content(|writer| {
+                            xml::amount(writer, "ram:CalculatedAmount", vat.vat)?;
+                            xml::text(writer, "ram:TypeCode", "VAT")?;
+                            xml::amount(writer, "ram:BasisAmount", vat.net)?;
+                            xml::text(writer, "ram:CategoryCode", STANDARD_CATEGORY)?;
+                            xml::amount(writer, "ram:RateApplicablePercent", vat.rate)
+                        })?;
+                        if let Some(payment) = payment {
+                            writer
+                                .create_element("ram:SpecifiedTradePaymentTerms")
+                                .write_inner_content(|writer| {
+                                    xml::text(writer, "ram:Description", &payment.note)?;
+                                    writer
+                                        .create_element("ram:DueDateDateTime")
+                                        .write_inner_content(|writer| date(writer, &payment.due_date))?;
+                                    Ok(())
+                                })?;
+                        }
+                        writer
+                            .create_element("ram:SpecifiedTradeSettlementHeaderMonetarySummation")
+                            .write_inner_content(|writer| {
+                                xml::amount(writer, "ram:LineTotalAmount", vat.net)?;
+                                xml::amount(writer, "ram:TaxBasisTotalAmount", vat.net)?;
+                                xml::money(writer, "ram:TaxTotalAmount", currency, vat.vat)?;
+                                if vat.rounding != 0.0 {
+                                    xml::amount(writer, "ram:RoundingAmount", vat.rounding)?;
+                                }
+                                xml::amount(writer, "ram:GrandTotalAmount", vat.net + vat.vat)?;
+                                xml::amount(writer, "ram:DuePayableAmount", vat.payable)
+                            })?;
+                        Ok(())
+                    })?;
+                Ok(())
+            })?;
+            Ok(())
+        })?;
+    xml::finish(writer)
+}
+
+fn seller(writer: &mut XmlWriter, options: &DocumentOptions) -> io::Result<()> {
+    writer.create_element("ram:SellerTradeParty").write_inner_content(|writer| {
+        xml::text(writer, "ram:Name", &options.seller_name)?;
+        writer.create_element("ram:PostalTradeAddress").write_inner_content(|writer| {
+            xml::text(writer, "ram:PostcodeCode", &options.seller_postcode)?;
+            xml::text(writer, "ram:LineOne", &options.seller_street)?;
+            xml::text(writer, "ram:CityName", &options.seller_city)?;
+            xml::text(writer, "ram:CountryID", &options.seller_country)
+        })?;
+        writer
+            .create_element("ram:SpecifiedTaxRegistration")
+            .write_inner_content(|writer| {
+                // VA is a VAT identifier
+                writer
+                    .create_element("ram:ID")
+                    .with_attribute(("schemeID", "VA"))
+                    .write_text_content(BytesText::new(&options.seller_vat_id))?;
+                Ok(())
+            })?;
+        Ok(())
+    })?;
+    Ok(())
+}
+
+/// The client as it was when the order was placed. Its address is a single line.
+fn buyer(writer: &mut XmlWriter, invoice: &Invoice, options: &DocumentOptions) -> io::Result<()> {
+    let client = &invoice.client;
+    writer.create_element("ram:BuyerTradeParty").write_inner_content(|writer| {
+        xml::text(writer, "ram:ID", &client.id.to_string())?;
+        xml::text(writer, "ram:Name", &client.name)?;
+        writer.create_element("ram:PostalTradeAddress").write_inner_content(|writer| {
+            xml::text(writer, "ram:LineOne", &client.address)?;
+            xml::text(writer, "ram:CountryID", &options.buyer_country)
+        })?;
+        writer
+            .create_element("ram:URIUniversalCommunication")
+            .write_inner_content(|writer| {
+                // EM is an email address
+                writer
+                    .create_element("ram:URIID")
+                    .with_attribute(("schemeID", "EM"))
+                    .write_text_content(BytesText::new(&client.email))?;
+                Ok(())
+            })?;
+        Ok(())
+    })?;
+    Ok(())
+}
+
+fn date(writer: &mut XmlWriter, date: &str) -> io::Result<()> {
+    // Format 102 is YYYYMMDD
+    writer
+        .create_element("udt:DateTimeString")
+        .with_attribute(("format", "102"))
+        .write_text_content(BytesText::new(&date.replace('-', "")))?;
+    Ok(())
+}
\ No newline at end of file
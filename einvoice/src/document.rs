@@ -0,0 +1,126 @@
+use std::io;
+
+use chrono::{Days, NaiveDate};
+use clap::Args;
+use common::invoice::Invoice;
+use common::vat::{round_cents, VatBreakdown, STANDARD_RATE};
+
+use crate::{cii, ubl};
+
+/// Specification both formats follow: EN 16931, without extension.
+pub const CUSTOMIZATION_ID: &str = "urn:cen.eu:en16931:2017";
+
+/// Commercial invoice, from the UNTDID 1001 code list.
+pub const INVOICE_TYPE_CODE: &str = "380";
+
+/// Standard rated VAT category, from the UNTDID 5305 code list.
+pub const STANDARD_CATEGORY: &str = "S";
+
+/// One unit, from the UN/ECE recommendation 20. Every invoice line is one unit.
+pub const UNIT_CODE: &str = "C62";
+
+#[derive(Args, Clone, Debug)]
+pub struct DocumentOptions {
+    /// Legal name of the seller
+    #[arg(long, default_value = "OhMyShop SAS")]
+    pub seller_name: String,
+
+    /// VAT identifier of the seller
+    #[arg(long, default_value = "FR40123456789")]
+    pub seller_vat_id: String,
+
+    #[arg(long, default_value = "10 rue de la Paix")]
+    pub seller_street: String,
+
+    #[arg(long, default_value = "Paris")]
+    pub seller_city: String,
+
+    #[arg(long, default_value = "75002")]
+    pub seller_postcode: String,
+
+    /// ISO 3166-1 alpha-2 code of the seller country
+    #[arg(long, default_value = "FR")]
+    pub seller_country: String,
+
+    /// ISO 3166-1 alpha-2 code of the buyer country, which client addresses don't carry
+    #[arg(long, default_value = "FR")]
+    pub buyer_country: String,
+
+    /// VAT rate included in the catalog prices, in percent
+    #[arg(long, default_value_t = STANDARD_RATE)]
+    pub vat_rate: f64,
+
+    /// Days after the issue date the amount due is payable
+    #[arg(long, default_value_t = 30)]
+    pub payment_terms_days: u64,
+}
+
+/// When the amount due is payable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaymentTerms {
+    /// Payment due date (BT-9), `YYYY-MM-DD`
+    pub due_date: String,
+    /// Payment terms (BT-20)
+    pub note: String,
+}
+
+impl PaymentTerms {
+    /// The terms of an invoice issued on `date`, `None` if it isn't a `YYYY-MM-DD` date.
+    pub fn new(date: &str, days: u64) -> Option<Self> {
+        let due_date = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?.checked_add_days(Days::new(days))?;
+        Some(PaymentTerms {
+            due_date: due_date.format("%Y-%m-%d").to_string(),
+            note: format!("Payment within {} days of the invoice date", days),
+        })
+    }
+}
+
+/// An invoice with the seller, the VAT breakdown and the payment terms its documents show.
+pub struct Document<'a> {
+    pub invoice: &'a Invoice,
+    pub options: &'a DocumentOptions,
+    pub vat: VatBreakdown,
+    pub payment: Option<PaymentTerms>,
+}
+
+impl<'a> Document<'a> {
+    pub fn new(invoice: &'a Invoice, options: &'a DocumentOptions) -> Self {
+        Document {
+            invoice,
+            options,
+            vat: VatBreakdown::new(invoice.products.iter().map(|product| product.price), options.vat_rate),
+            payment: PaymentTerms::new(&invoice.date, options.payment_terms_days),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Format {
+    /// UBL 2.1 invoice
+    Ubl,
+    /// UN/CEFACT cross industry invoice, the XML of Factur-X
+    Cii,
+}
+
+impl Format {
+    pub const ALL: [Format; 2] = [Format::Ubl, Format::Cii];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Format::Ubl => "ubl",
+            Format::Cii => "cii",
+        }
+    }
+
+    pub fn render(&self, document: &Document) -> io::Result<String> {
+        match self {
+            Format::Ubl => ubl::render(document),
+            Format::Cii => cii::render(document),
+        }
+    }
+}
+
+/// Formats an amount with two decimals, never as `-0.00`.
+pub fn format_amount(value: f64) -> String {
+    format!("{:.2}", round_cents(value) + 0.0)
+}
\ No newline at end of file
@@ -0,0 +1,7 @@
+pub mod cii;
+pub mod document;
+pub mod ubl;
+pub mod validate;
+pub mod xml;
+
+pub use document::{Document, DocumentOptions, Format};
\ No newline at end of file
@@ -0,0 +1,219 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use clap::Parser;
+use common::avro::decode_payload;
+use common::document::InvoiceDocument;
+use common::invoice::Invoice;
+use einvoice::validate::{self, Violation};
+use einvoice::{Document, DocumentOptions, Format};
+use object_store::path::Path;
+use object_store::{ObjectStore, PutPayload};
+use rdkafka::config::RDKafkaLogLevel;
+use rdkafka::consumer::{CommitMode, Consumer, StreamConsumer};
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::ClientConfig;
+use rdkafka::Message;
+use schema_registry_converter::async_impl::avro::{AvroDecoder, AvroEncoder};
+use schema_registry_converter::async_impl::schema_registry::{post_schema, SrSettings};
+use schema_registry_converter::schema_registry_common::{SchemaType, SubjectNameStrategy, SuppliedSchema};
+use serde_avro_derive::BuildSchema;
+use url::Url;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    #[command(flatten)]
+    document: DocumentOptions,
+
+    /// If provided, doesn't publish the documents to the `InvoiceDocument` topic
+    #[arg(long)]
+    no_publish: bool,
+
+    /// If provided, also writes the documents to object storage: `s3://bucket/prefix` for MinIO or
+    /// `file:///absolute/path` for a local directory
+    #[arg(long)]
+    storage_url: Option<String>,
+
+    /// S3 endpoint, used for `s3://` storage URLs
+    #[arg(long, default_value = "http://localhost:9000")]
+    s3_endpoint: String,
+
+    #[arg(long, default_value = "minio")]
+    s3_access_key: String,
+
+    #[arg(long, default_value = "password")]
+    s3_secret_key: String,
+}
+
+/// Where the documents go.
+struct Outputs<'a> {
+    producer: Option<FutureProducer>,
+    encoder: AvroEncoder<'a>,
+    storage: Option<(Arc<dyn ObjectStore>, Path)>,
+}
+
+impl Outputs<'_> {
+    async fn send(&self, document: &InvoiceDocument) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(producer) = &self.producer {
+            let document_msg = self
+                .encoder
+                .encode_struct(
+                    document,
+                    &SubjectNameStrategy::TopicNameStrategy("InvoiceDocument".to_string(), false),
+                )
+                .await?;
+            producer
+                .send(
+                    FutureRecord::to("InvoiceDocument")
+                        .key(&document.invoice_id.to_string())
+                        .payload(&document_msg),
+                    Duration::from_secs(0),
+                )
+                .await
+                .map_err(|(e, _)| e)?;
+        }
+        if let Some((store, root)) = &self.storage {
+            // Grouped by fiscal year, which the legal number carries
+            let year = document.number.split('-').nth(1).unwrap_or("unknown");
+            let path = root
+                .child(year)
+                .child(format!("{}.{}.xml", document.number, document.format));
+            store.put(&path, PutPayload::from(document.content.clone())).await?;
+        }
+        Ok(())
+    }
+
+    /// Sends an invoice breaking a business rule to the dead letter queue, keyed by invoice id,
+    /// where the sink keeps it for review. Without publishing, it is only logged.
+    async fn reject(&self, invoice: &Invoice, violations: &[Violation]) -> Result<(), Box<dyn std::error::Error>> {
+        for violation in violations {
+            eprintln!("Invoice {} is not EN 16931 compliant, {}", invoice.id, violation);
+        }
+        if let Some(producer) = &self.producer {
+            let dead_letter_msg = serde_json::to_string(invoice)?;
+            producer
+                .send(
+                    FutureRecord::to("DeadLetterQueue")
+                        .key(&invoice.id.to_string())
+                        .payload(&dead_letter_msg),
+                    Duration::from_secs(0),
+                )
+                .await
+                .map_err(|(e, _)| e)?;
+            println!("Invoice {} moved to dead letter queue.", invoice.id);
+        }
+        Ok(())
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+    let sr_settings = SrSettings::new(String::from("http://localhost:8085"));
+    let decoder = AvroDecoder::new(sr_settings.clone());
+
+    let storage = match &cli.storage_url {
+        Some(storage_url) => {
+            let options = [
+                ("aws_endpoint", cli.s3_endpoint.clone()),
+                ("aws_access_key_id", cli.s3_access_key.clone()),
+                ("aws_secret_access_key", cli.s3_secret_key.clone()),
+                ("aws_region", String::from("us-east-1")),
+                ("aws_allow_http", String::from("true")),
+            ];
+            let (store, root) = object_store::parse_url_opts(&Url::parse(storage_url)?, options)?;
+            Some((Arc::from(store), root))
+        }
+        None => None,
+    };
+
+    let producer = match cli.no_publish {
+        true => None,
+        false => {
+            let document_schema = InvoiceDocument::schema().unwrap();
+            let document_supplied_schema = SuppliedSchema {
+                name: Some(String::from("InvoiceDocument")),
+                schema_type: SchemaType::Avro,
+                schema: String::from(document_schema.json()),
+                references: vec![],
+            };
+            if let Err(e) = post_schema(&sr_settings, "InvoiceDocument-value".to_string(), document_supplied_schema).await {
+                eprintln!("Failed to post invoice document schema: {}", e);
+            };
+            let producer: FutureProducer = ClientConfig::new()
+                .set("bootstrap.servers", "localhost:19092")
+                .create()
+                .expect("Failed to create Kafka producer");
+            Some(producer)
+        }
+    };
+    let outputs = Outputs {
+        producer,
+        encoder: AvroEncoder::new(sr_settings),
+        storage,
+    };
+
+    // Offsets are committed only once the documents of the invoice are sent
+    let consumer: StreamConsumer = ClientConfig::new()
+        .set("group.id", "einvoice")
+        .set("bootstrap.servers", "localhost:19092")
+        .set("enable.partition.eof", "false")
+        .set("auto.offset.reset", "earliest")
+        .set("session.timeout.ms", "6000")
+        .set("enable.auto.commit", "false")
+        .set_log_level(RDKafkaLogLevel::Debug)
+        .create()
+        .expect("Consumer creation failed");
+
+    consumer
+        .subscribe(&["Invoice"])
+        .expect("Failed to subscribe to topics");
+
+    loop {
+        match consumer.recv().await {
+            Ok(message) => {
+                if let Some(payload) = message.payload() {
+                    match decode_payload::<Invoice>(&decoder, payload).await {
+                        Ok(invoice) => process_invoice(&invoice, &cli.document, &outputs).await?,
+                        Err(e) => eprintln!(
+                            "Skipping message {}/{}/{}: {}",
+                            message.topic(),
+                            message.partition(),
+                            message.offset(),
+                            e
+                        ),
+                    }
+                }
+                consumer.commit_message(&message, CommitMode::Async)?;
+            }
+            Err(e) => eprintln!("Error while consuming: {:?}", e),
+        }
+    }
+}
+
+/// Renders the invoice in every format and sends the documents, unless it breaks a business rule:
+/// then it is rejected to the dead letter queue. Either way its offset is committed once sent.
+async fn process_invoice(
+    invoice: &Invoice,
+    options: &DocumentOptions,
+    outputs: &Outputs<'_>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let document = Document::new(invoice, options);
+    let violations = validate::validate(&document);
+    if !violations.is_empty() {
+        return outputs.reject(invoice, &violations).await;
+    }
+    for format in Format::ALL {
+        let document = InvoiceDocument {
+            invoice_id: invoice.id,
+            number: invoice.number.clone(),
+            format: format.as_str().to_string(),
+            content_type: String::from("application/xml"),
+            content: format.render(&document)?,
+        };
+        outputs.send(&document).await?;
+    }
+    println!("Documents of invoice {} ({}) sent.", invoice.number, invoice.id);
+    Ok(())
+}
\ No newline at end of file
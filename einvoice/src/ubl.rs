@@ -0,0 +1,155 @@
+use std::io;
+
+use common::invoice::Invoice;
+use quick_xml::events::BytesText;
+
+use crate::document::{Document, DocumentOptions, CUSTOMIZATION_ID, INVOICE_TYPE_CODE, STANDARD_CATEGORY, UNIT_CODE};
+use crate::xml::{self, XmlWriter};
+
+const INVOICE_NS: &str = "urn:oasis:names:specification:ubl:schema:xsd:Invoice-2";
+const CAC_NS: &str = "urn:oasis:names:specification:ubl:schema:xsd:CommonAggregateComponents-2";
+const CBC_NS: &str = "urn:oasis:names:specification:ubl:schema:xsd:CommonBasicComponents-2";
+
+/// Renders the document as a UBL 2.1 invoice.
+pub fn render(document: &Document) -> io::Result<String> {
+    let Document { invoice, options, vat, payment } = document;
+    let currency = invoice.currency.as_str();
+
+    let mut writer = xml::writer()?;
+    writer
+        .create_element("Invoice")
+        .with_attributes([("xmlns", INVOICE_NS), ("xmlns:cac", CAC_NS), ("xmlns:cbc", CBC_NS)])
+        .write_inner_content(|writer| {
+            xml::text(writer, "cbc:CustomizationID", CUSTOMIZATION_ID)?;
+            xml::text(writer, "cbc:ID", &invoice.number)?;
+            xml::text(writer, "cbc:IssueDate", &invoice.date)?;
+            if let Some(payment) = payment {
+                xml::text(writer, "cbc:DueDate", &payment.due_date)?;
+            }
+            xml::text(writer, "cbc:InvoiceTypeCode", INVOICE_TYPE_CODE)?;
+            xml::text(writer, "cbc:DocumentCurrencyCode", currency)?;
+            writer
+                .create_element("cac:OrderReference")
+                .write_inner_content(|writer| xml::text(writer, "cbc:ID", &invoice.id.to_string()))?;
+            writer
+                .create_element("cac:AccountingSupplierParty")
+                .write_inner_content(|writer| seller(writer, options))?;
+            writer
+                .create_element("cac:AccountingCustomerParty")
+                .write_inner_content(|writer| buyer(writer, invoice, options))?;
+            if let Some(payment) = payment {
+                writer
+                    .create_element("cac:PaymentTerms")
+                    .write_inner_content(|writer| xml::text(writer, "cbc:Note", &payment.note))?;
+            }
+
+            writer.create_element("cac:TaxTotal").write_inner_content(|writer| {
+                xml::money(writer, "cbc:TaxAmount", currency, vat.vat)?;
+                writer.create_element("cac:TaxSubtotal").write_inner_content(|writer| {
+                    xml::money(writer, "cbc:TaxableAmount", currency, vat.net)?;
+                    xml::money(writer, "cbc:TaxAmount", currency, vat.vat)?;
+                    tax_category(writer, "cac:TaxCategory", vat.rate)
+                })?;
+                Ok(())
+            })?;
+
+            writer.create_element("cac:LegalMonetaryTotal").write_inner_content(|writer| {
+                xml::money(writer, "cbc:LineExtensionAmount", currency, vat.net)?;
+                xml::money(writer, "cbc:TaxExclusiveAmount", currency, vat.net)?;
+                xml::money(writer, "cbc:TaxInclusiveAmount", currency, vat.net + vat.vat)?;
+                if vat.rounding != 0.0 {
+                    xml::money(writer, "cbc:PayableRoundingAmount", currency, vat.rounding)?;
+                }
+                xml::money(writer, "cbc:PayableAmount", currency, vat.payable)
+            })?;
+
+            for (index, (product, net)) in invoice.products.iter().zip(&vat.lines).enumerate() {
+                writer.create_element("cac:InvoiceLine").write_inner_content(|writer| {
+                    xml::text(writer, "cbc:ID", &(index + 1).to_string())?;
+                    writer
+                        .create_element("cbc:InvoicedQuantity")
+                        .with_attribute(("unitCode", UNIT_CODE))
+                        .write_text_content(BytesText::new("1"))?;
+                    xml::money(writer, "cbc:LineExtensionAmount", currency, *net)?;
+                    writer.create_element("cac:Item").write_inner_content(|writer| {
+                        xml::text(writer, "cbc:Name", &product.name)?;
+                        writer
+                            .create_element("cac:SellersItemIdentification")
+                            .write_inner_content(|writer| xml::text(writer, "cbc:ID", &product.id.to_string()))?;
+                        tax_category(writer, "cac:ClassifiedTaxCategory", vat.rate)
+                    })?;
+                    writer
+                        .create_element("cac:Price")
+                        .write_inner_content(|writer| xml::money(writer, "cbc:PriceAmount", currency, *net))?;
+                    Ok(())
+                })?;
+            }
+            Ok(())
+        })?;
+    xml::finish(writer)
+}
+
+fn seller(writer: &mut XmlWriter, options: &DocumentOptions) -> io::Result<()> {
+    writer.create_element("cac:Party").write_inner_content(|writer| {
+        writer.create_element("cac:PostalAddress").write_inner_content(|writer| {
+            xml::text(writer, "cbc:StreetName", &options.seller_street)?;
+            xml::text(writer, "cbc:CityName", &options.seller_city)?;
+            xml::text(writer, "cbc:PostalZone", &options.seller_postcode)?;
+            country(writer, &options.seller_country)
+        })?;
+        writer.create_element("cac:PartyTaxScheme").write_inner_content(|writer| {
+            xml::text(writer, "cbc:CompanyID", &options.seller_vat_id)?;
+            tax_scheme(writer)
+        })?;
+        writer
+            .create_element("cac:PartyLegalEntity")
+            .write_inner_content(|writer| xml::text(writer, "cbc:RegistrationName", &options.seller_name))?;
+        Ok(())
+    })?;
+    Ok(())
+}
+
+/// The client as it was when the order was placed. Its address is a single line.
+fn buyer(writer: &mut XmlWriter, invoice: &Invoice, options: &DocumentOptions) -> io::Result<()> {
+    let client = &invoice.client;
+    writer.create_element("cac:Party").write_inner_content(|writer| {
+        writer
+            .create_element("cac:PartyIdentification")
+            .write_inner_content(|writer| xml::text(writer, "cbc:ID", &client.id.to_string()))?;
+        writer.create_element("cac:PostalAddress").write_inner_content(|writer| {
+            xml::text(writer, "cbc:StreetName", &client.address)?;
+            country(writer, &options.buyer_country)
+        })?;
+        writer
+            .create_element("cac:PartyLegalEntity")
+            .write_inner_content(|writer| xml::text(writer, "cbc:RegistrationName", &client.name))?;
+        writer
+            .create_element("cac:Contact")
+            .write_inner_content(|writer| xml::text(writer, "cbc:ElectronicMail", &client.email))?;
+        Ok(())
+    })?;
+    Ok(())
+}
+
+fn country(writer: &mut XmlWriter, code: &str) -> io::Result<()> {
+    writer
+        .create_element("cac:Country")
+        .write_inner_content(|writer| xml::text(writer, "cbc:IdentificationCode", code))?;
+    Ok(())
+}
+
+fn tax_category(writer: &mut XmlWriter, name: &str, rate: f64) -> io::Result<()> {
+    writer.create_element(name).write_inner_content(|writer| {
+        xml::text(writer, "cbc:ID", STANDARD_CATEGORY)?;
+        xml::amount(writer, "cbc:Percent", rate)?;
+        tax_scheme(writer)
+    })?;
+    Ok(())
+}
+
+fn tax_scheme(writer: &mut XmlWriter) -> io::Result<()> {
+    writer
+        .create_element("cac:TaxScheme")
+        .write_inner_content(|writer| xml::text(writer, "cbc:ID", "VAT"))?;
+    Ok(())
+}
\ No newline at end of file
@@ -0,0 +1,84 @@
+use std::fmt;
+
+use common::vat::round_cents;
+
+use crate::document::Document;
+
+/// A broken EN 16931 business rule.
+#[derive(Debug)]
+pub struct Violation {
+    /// Identifier of the rule in EN 16931, such as `BR-02`
+    pub rule: &'static str,
+    pub message: &'static str,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.rule, self.message)
+    }
+}
+
+fn is_code(value: &str, length: usize) -> bool {
+    value.len() == length && value.chars().all(|c| c.is_ascii_uppercase())
+}
+
+fn is_date(value: &str) -> bool {
+    let bytes = value.as_bytes();
+    bytes.len() == 10
+        && bytes.iter().enumerate().all(|(index, byte)| match index {
+            4 | 7 => *byte == b'-',
+            _ => byte.is_ascii_digit(),
+        })
+}
+
+fn is_blank(value: &str) -> bool {
+    value.trim().is_empty()
+}
+
+/// Checks the mandatory fields of the document: seller, buyer, lines, VAT breakdown, totals and
+/// payment terms.
+pub fn validate(document: &Document) -> Vec<Violation> {
+    let Document { invoice, options, vat, payment } = document;
+    let line_total = round_cents(vat.lines.iter().sum());
+    let payable_when = payment
+        .as_ref()
+        .is_some_and(|payment| is_date(&payment.due_date) || !is_blank(&payment.note));
+    let rules = [
+        (!is_blank(&invoice.number), "BR-02", "the invoice has no legal number"),
+        (is_date(&invoice.date), "BR-03", "the issue date is not a YYYY-MM-DD date"),
+        (is_code(&invoice.currency, 3), "BR-05", "the currency is not an ISO 4217 code"),
+        (!is_blank(&options.seller_name), "BR-06", "the seller has no name"),
+        (!is_blank(&invoice.client.name), "BR-07", "the buyer has no name"),
+        (
+            !is_blank(&options.seller_street) && !is_blank(&options.seller_city) && !is_blank(&options.seller_postcode),
+            "BR-08",
+            "the seller has no postal address",
+        ),
+        (is_code(&options.seller_country, 2), "BR-09", "the seller country is not an ISO 3166-1 code"),
+        (!is_blank(&invoice.client.address), "BR-10", "the buyer has no postal address"),
+        (is_code(&options.buyer_country, 2), "BR-11", "the buyer country is not an ISO 3166-1 code"),
+        (!invoice.products.is_empty(), "BR-16", "the invoice has no line"),
+        (!is_blank(&options.seller_vat_id), "BR-S-02", "the seller has no VAT identifier"),
+        (vat.rate > 0.0, "BR-S-05", "the standard VAT rate is not greater than zero"),
+        (
+            (vat.net - line_total).abs() < 0.005,
+            "BR-CO-10",
+            "the sum of the line amounts differs from the total without VAT",
+        ),
+        (
+            (vat.payable - round_cents(invoice.total_price)).abs() < 0.005,
+            "BR-CO-16",
+            "the amount due differs from the invoice total",
+        ),
+        (
+            vat.payable <= 0.0 || payable_when,
+            "BR-CO-25",
+            "a positive amount due has neither a payment due date nor payment terms",
+        ),
+    ];
+    rules
+        .into_iter()
+        .filter(|(valid, _, _)| !valid)
+        .map(|(_, rule, message)| Violation { rule, message })
+        .collect()
+}
\ No newline at end of file
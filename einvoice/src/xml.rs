@@ -0,0 +1,38 @@
+use std::io::{self, Cursor};
+
+use quick_xml::events::{BytesDecl, BytesText, Event};
+use quick_xml::Writer;
+
+use crate::document::format_amount;
+
+pub type XmlWriter = Writer<Cursor<Vec<u8>>>;
+
+/// An indented writer, with the XML declaration already written.
+pub fn writer() -> io::Result<XmlWriter> {
+    let mut writer = Writer::new_with_indent(Cursor::new(Vec::new()), b' ', 2);
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+    Ok(writer)
+}
+
+pub fn finish(writer: XmlWriter) -> io::Result<String> {
+    String::from_utf8(writer.into_inner().into_inner()).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Writes an element holding `value`, escaped.
+pub fn text(writer: &mut XmlWriter, name: &str, value: &str) -> io::Result<()> {
+    writer.create_element(name).write_text_content(BytesText::new(value))?;
+    Ok(())
+}
+
+pub fn amount(writer: &mut XmlWriter, name: &str, value: f64) -> io::Result<()> {
+    text(writer, name, &format_amount(value))
+}
+
+/// Writes an amount with its currency in a `currencyID` attribute.
+pub fn money(writer: &mut XmlWriter, name: &str, currency: &str, value: f64) -> io::Result<()> {
+    writer
+        .create_element(name)
+        .with_attribute(("currencyID", currency))
+        .write_text_content(BytesText::new(&format_amount(value)))?;
+    Ok(())
+}
\ No newline at end of file
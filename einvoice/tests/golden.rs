@@ -0,0 +1,120 @@
+//! Checks the fixture invoice against the business rules and compares its documents with the
+//! files of `tests/golden`. Run with `UPDATE_GOLDEN=1` to write the files again after an intended
+//! change, then review their diff.
+
+use std::fs;
+use std::path::Path;
+
+use common::invoice::Invoice;
+use einvoice::validate::validate;
+use einvoice::{Document, DocumentOptions, Format};
+
+fn invoice() -> Invoice {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/invoice.json");
+    serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+}
+
+fn options() -> DocumentOptions {
+    DocumentOptions {
+        seller_name: String::from("OhMyShop SAS"),
+        seller_vat_id: String::from("FR40123456789"),
+        seller_street: String::from("10 rue de la Paix"),
+        seller_city: String::from("Paris"),
+        seller_postcode: String::from("75002"),
+        seller_country: String::from("FR"),
+        buyer_country: String::from("FR"),
+        vat_rate: 20.0,
+        payment_terms_days: 30,
+    }
+}
+
+fn rules(document: &Document) -> Vec<&'static str> {
+    validate(document).into_iter().map(|violation| violation.rule).collect()
+}
+
+fn assert_golden(name: &str, actual: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("Cannot read {}: {}. Run with UPDATE_GOLDEN=1 to create it", path.display(), e));
+    assert_eq!(expected, actual, "{} is outdated", path.display());
+}
+
+#[test]
+fn vat_breakdown() {
+    let (invoice, options) = (invoice(), options());
+    let vat = Document::new(&invoice, &options).vat;
+    // 9.99 / 1.2 = 8.325, 3.33 / 1.2 = 2.775 and 1249 / 1.2 = 1040.8333...
+    assert_eq!(vat.lines, [8.33, 2.78, 1040.83]);
+    assert_eq!(vat.net, 1051.94);
+    assert_eq!(vat.vat, 210.39);
+    assert_eq!(vat.payable, 1262.32);
+    // The lines rounded up make the total with VAT a cent more than the prices
+    assert_eq!(vat.rounding, -0.01);
+}
+
+#[test]
+fn payment_terms() {
+    let (invoice, options) = (invoice(), options());
+    let payment = Document::new(&invoice, &options).payment.unwrap();
+    assert_eq!(payment.due_date, "2026-03-31");
+    assert_eq!(payment.note, "Payment within 30 days of the invoice date");
+}
+
+#[test]
+fn fixture_is_compliant() {
+    let (invoice, options) = (invoice(), options());
+    assert!(rules(&Document::new(&invoice, &options)).is_empty());
+}
+
+#[test]
+fn violations() {
+    let options = options();
+
+    let mut invoice = invoice();
+    invoice.number = String::new();
+    invoice.client.name = String::from("  ");
+    assert_eq!(rules(&Document::new(&invoice, &options)), ["BR-02", "BR-07"]);
+
+    // Without a date there is no due date either
+    let mut invoice = self::invoice();
+    invoice.date = String::from("01/03/2026");
+    assert_eq!(rules(&Document::new(&invoice, &options)), ["BR-03", "BR-CO-25"]);
+
+    let mut invoice = self::invoice();
+    invoice.total_price += 1.0;
+    assert_eq!(rules(&Document::new(&invoice, &options)), ["BR-CO-16"]);
+}
+
+#[test]
+fn amount_due_needs_payment_terms() {
+    let (invoice, options) = (invoice(), options());
+    let mut document = Document::new(&invoice, &options);
+    document.payment = None;
+    assert_eq!(rules(&document), ["BR-CO-25"]);
+
+    // Nothing is due, so nothing needs terms
+    let mut invoice = self::invoice();
+    for product in &mut invoice.products {
+        product.price = 0.0;
+    }
+    invoice.total_price = 0.0;
+    let mut document = Document::new(&invoice, &options);
+    document.payment = None;
+    assert!(rules(&document).is_empty());
+}
+
+#[test]
+fn ubl() {
+    let (invoice, options) = (invoice(), options());
+    assert_golden("invoice.ubl.xml", &Format::Ubl.render(&Document::new(&invoice, &options)).unwrap());
+}
+
+#[test]
+fn cii() {
+    let (invoice, options) = (invoice(), options());
+    assert_golden("invoice.cii.xml", &Format::Cii.render(&Document::new(&invoice, &options)).unwrap());
+}
\ No newline at end of file
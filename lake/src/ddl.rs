@@ -0,0 +1,63 @@
+use arrow::datatypes::{DataType, Schema};
+
+use crate::schema::PARTITION_COLUMN;
+
+/// Trino statements declaring the Parquet files under `location` as a partitioned table of the
+/// `hive` catalog, and registering the partitions already written.
+pub fn trino_ddl(schema: &Schema, table: &str, location: &str) -> String {
+    // Trino expects the partition columns last
+    let mut columns: Vec<String> = schema
+        .fields()
+        .iter()
+        .filter(|field| field.name() != PARTITION_COLUMN)
+        .map(|field| format!("  {} {}", field.name(), sql_type(field.data_type())))
+        .collect();
+    columns.push(format!("  {} VARCHAR", PARTITION_COLUMN));
+
+    format!(
+        "CREATE SCHEMA IF NOT EXISTS hive.lake;\n\n\
+        CREATE TABLE IF NOT EXISTS hive.lake.{table} (\n{columns}\n)\n\
+        WITH (\n  external_location = '{location}',\n  format = 'PARQUET',\n  partitioned_by = ARRAY['{partition}']\n);\n\n\
+        CALL hive.system.sync_partition_metadata('lake', '{table}', 'FULL');\n",
+        table = table,
+        columns = columns.join(",\n"),
+        location = location.trim_end_matches('/'),
+        partition = PARTITION_COLUMN,
+    )
+}
+
+fn sql_type(data_type: &DataType) -> String {
+    match data_type {
+        DataType::Boolean => String::from("BOOLEAN"),
+        DataType::Int32 => String::from("INTEGER"),
+        DataType::Int64 => String::from("BIGINT"),
+        DataType::Float32 => String::from("REAL"),
+        DataType::Float64 => String::from("DOUBLE"),
+        DataType::Binary => String::from("VARBINARY"),
+        DataType::List(item) => format!("ARRAY({})", sql_type(item.data_type())),
+        DataType::Struct(fields) => {
+            let fields: Vec<String> = fields
+                .iter()
+                .map(|field| format!("{} {}", field.name(), sql_type(field.data_type())))
+                .collect();
+            format!("ROW({})", fields.join(", "))
+        }
+        _ => String::from("VARCHAR"),
+    }
+}
+
+/// Trino statements registering the Iceberg tables written under `location` in the `iceberg` catalog.
+///
+/// Trino pins the metadata file found at registration, so the calls are re-run to read later commits.
+pub fn iceberg_ddl(tables: &[&str], location: &str) -> String {
+    let mut ddl = String::from("CREATE SCHEMA IF NOT EXISTS iceberg.lake;\n");
+    for table in tables {
+        ddl.push_str(&format!(
+            "\nCALL iceberg.system.unregister_table(schema_name => 'lake', table_name => '{table}');\n\
+            CALL iceberg.system.register_table(schema_name => 'lake', table_name => '{table}', table_location => '{location}/{table}');\n",
+            table = table,
+            location = location.trim_end_matches('/'),
+        ));
+    }
+    ddl
+}
@@ -0,0 +1,832 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use arrow::datatypes::{DataType, Field, Fields, Schema};
+use arrow::json::reader::ReaderBuilder;
+use arrow::record_batch::RecordBatch;
+use object_store::path::Path;
+use object_store::{ObjectStore, PutMode, PutOptions, PutPayload};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::manifest::{
+    read_manifest, read_manifest_list, write_manifest, write_manifest_list, DataFile, ManifestEntry, ManifestFile,
+    Partition, CONTENT_DATA, STATUS_ADDED, STATUS_DELETED,
+};
+use crate::schema::PARTITION_COLUMN;
+use crate::writer::encode;
+
+const FORMAT_VERSION: i32 = 2;
+/// Partition field ids start at 1000 in Iceberg, to be told apart from column ids.
+const PARTITION_FIELD_ID: i32 = 1000;
+const VERSION_HINT: &str = "version-hint.text";
+/// Number of times a commit is retried when another writer committed the same version first.
+const COMMIT_ATTEMPTS: usize = 5;
+/// Prefix of the snapshot summary properties holding, per Kafka partition, the offset of the
+/// next message to append.
+const OFFSET_PROPERTY: &str = "kafka-offset.";
+
+/// Kafka partition and offset of the message a row comes from.
+pub type Position = (i32, i64);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum IcebergType {
+    Primitive(String),
+    Struct(StructType),
+    List(ListType),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StructType {
+    #[serde(rename = "type")]
+    kind: String,
+    fields: Vec<NestedField>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ListType {
+    #[serde(rename = "type")]
+    kind: String,
+    element_id: i32,
+    element: Box<IcebergType>,
+    element_required: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NestedField {
+    id: i32,
+    name: String,
+    required: bool,
+    #[serde(rename = "type")]
+    field_type: IcebergType,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct IcebergSchema {
+    #[serde(rename = "type")]
+    kind: String,
+    schema_id: i32,
+    fields: Vec<NestedField>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PartitionSpec {
+    spec_id: i32,
+    fields: Vec<PartitionField>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PartitionField {
+    source_id: i32,
+    field_id: i32,
+    name: String,
+    transform: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SortOrder {
+    order_id: i32,
+    fields: Vec<Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Snapshot {
+    snapshot_id: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent_snapshot_id: Option<i64>,
+    sequence_number: i64,
+    timestamp_ms: i64,
+    manifest_list: String,
+    summary: BTreeMap<String, String>,
+    schema_id: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SnapshotLogEntry {
+    timestamp_ms: i64,
+    snapshot_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct MetadataLogEntry {
+    timestamp_ms: i64,
+    metadata_file: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SnapshotRef {
+    snapshot_id: i64,
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+/// The table metadata file of the Iceberg spec, format version 2.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TableMetadata {
+    format_version: i32,
+    table_uuid: String,
+    location: String,
+    last_sequence_number: i64,
+    last_updated_ms: i64,
+    last_column_id: i32,
+    current_schema_id: i32,
+    schemas: Vec<IcebergSchema>,
+    default_spec_id: i32,
+    partition_specs: Vec<PartitionSpec>,
+    last_partition_id: i32,
+    default_sort_order_id: i32,
+    sort_orders: Vec<SortOrder>,
+    properties: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    current_snapshot_id: Option<i64>,
+    snapshots: Vec<Snapshot>,
+    snapshot_log: Vec<SnapshotLogEntry>,
+    metadata_log: Vec<MetadataLogEntry>,
+    refs: BTreeMap<String, SnapshotRef>,
+}
+
+impl TableMetadata {
+    fn new(location: &str, schema: &Schema) -> Self {
+        let mut last_column_id = 0;
+        let fields = assign_ids(schema.fields(), None, &mut last_column_id, true);
+        let date_id = fields
+            .iter()
+            .find(|field| field.name == PARTITION_COLUMN)
+            .map(|field| field.id)
+            .unwrap_or_default();
+        TableMetadata {
+            format_version: FORMAT_VERSION,
+            table_uuid: uuid::Uuid::new_v4().to_string(),
+            location: location.to_string(),
+            last_sequence_number: 0,
+            last_updated_ms: chrono::Utc::now().timestamp_millis(),
+            last_column_id,
+            current_schema_id: 0,
+            schemas: vec![IcebergSchema {
+                kind: String::from("struct"),
+                schema_id: 0,
+                fields,
+            }],
+            default_spec_id: 0,
+            partition_specs: vec![PartitionSpec {
+                spec_id: 0,
+                fields: vec![PartitionField {
+                    source_id: date_id,
+                    field_id: PARTITION_FIELD_ID,
+                    name: PARTITION_COLUMN.to_string(),
+                    transform: String::from("identity"),
+                }],
+            }],
+            last_partition_id: PARTITION_FIELD_ID,
+            default_sort_order_id: 0,
+            sort_orders: vec![SortOrder {
+                order_id: 0,
+                fields: vec![],
+            }],
+            properties: BTreeMap::new(),
+            current_snapshot_id: None,
+            snapshots: vec![],
+            snapshot_log: vec![],
+            metadata_log: vec![],
+            refs: BTreeMap::new(),
+        }
+    }
+
+    fn current_snapshot(&self) -> Option<&Snapshot> {
+        self.snapshots
+            .iter()
+            .find(|snapshot| Some(snapshot.snapshot_id) == self.current_snapshot_id)
+    }
+
+    /// Per Kafka partition, the offset of the next message to append, as recorded by the current
+    /// snapshot.
+    fn offsets(&self) -> BTreeMap<i32, i64> {
+        let Some(snapshot) = self.current_snapshot() else {
+            return BTreeMap::new();
+        };
+        snapshot
+            .summary
+            .iter()
+            .filter_map(|(key, value)| {
+                let partition = key.strip_prefix(OFFSET_PROPERTY)?.parse().ok()?;
+                Some((partition, value.parse().ok()?))
+            })
+            .collect()
+    }
+
+    fn current_schema(&self) -> &IcebergSchema {
+        self.schemas
+            .iter()
+            .find(|schema| schema.schema_id == self.current_schema_id)
+            .expect("Current schema missing from table metadata")
+    }
+
+    /// Makes `schema` the current schema if its columns differ from the current ones.
+    ///
+    /// Columns keep their id when their name is unchanged. New columns get fresh ids and are
+    /// optional, since the files already written have no value for them.
+    fn evolve(&mut self, schema: &Schema) {
+        let current = self.current_schema().fields.clone();
+        let mut last_column_id = self.last_column_id;
+        let fields = assign_ids(schema.fields(), Some(&current), &mut last_column_id, false);
+        if fields == current {
+            return;
+        }
+        self.last_column_id = last_column_id;
+        let schema_id = self.schemas.iter().map(|schema| schema.schema_id).max().unwrap_or(0) + 1;
+        println!("Table {} evolves to schema {}", self.location, schema_id);
+        self.schemas.push(IcebergSchema {
+            kind: String::from("struct"),
+            schema_id,
+            fields,
+        });
+        self.current_schema_id = schema_id;
+    }
+}
+
+/// Converts Arrow fields into Iceberg fields, reusing the ids of `previous` fields of the same name.
+fn assign_ids(fields: &Fields, previous: Option<&[NestedField]>, last_id: &mut i32, creating: bool) -> Vec<NestedField> {
+    fields
+        .iter()
+        .map(|field| {
+            let existing = previous.and_then(|previous| previous.iter().find(|old| old.name == *field.name()));
+            let id = existing.map(|old| old.id).unwrap_or_else(|| next_id(last_id));
+            let field_type = iceberg_type(field.data_type(), existing.map(|old| &old.field_type), last_id, creating);
+            NestedField {
+                id,
+                name: field.name().clone(),
+                required: !field.is_nullable() && (creating || existing.is_some_and(|old| old.required)),
+                field_type,
+            }
+        })
+        .collect()
+}
+
+fn next_id(last_id: &mut i32) -> i32 {
+    *last_id += 1;
+    *last_id
+}
+
+fn iceberg_type(data_type: &DataType, previous: Option<&IcebergType>, last_id: &mut i32, creating: bool) -> IcebergType {
+    match data_type {
+        DataType::Struct(fields) => {
+            let previous = match previous {
+                Some(IcebergType::Struct(previous)) => Some(previous.fields.as_slice()),
+                _ => None,
+            };
+            IcebergType::Struct(StructType {
+                kind: String::from("struct"),
+                fields: assign_ids(fields, previous, last_id, creating || previous.is_none()),
+            })
+        }
+        DataType::List(item) => {
+            let previous = match previous {
+                Some(IcebergType::List(previous)) => Some(previous),
+                _ => None,
+            };
+            let element_id = previous.map(|old| old.element_id).unwrap_or_else(|| next_id(last_id));
+            IcebergType::List(ListType {
+                kind: String::from("list"),
+                element_id,
+                element: Box::new(iceberg_type(
+                    item.data_type(),
+                    previous.map(|old| old.element.as_ref()),
+                    last_id,
+                    creating || previous.is_none(),
+                )),
+                element_required: !item.is_nullable(),
+            })
+        }
+        DataType::Boolean => IcebergType::Primitive(String::from("boolean")),
+        DataType::Int32 => IcebergType::Primitive(String::from("int")),
+        DataType::Int64 => IcebergType::Primitive(String::from("long")),
+        DataType::Float32 => IcebergType::Primitive(String::from("float")),
+        DataType::Float64 => IcebergType::Primitive(String::from("double")),
+        DataType::Binary => IcebergType::Primitive(String::from("binary")),
+        _ => IcebergType::Primitive(String::from("string")),
+    }
+}
+
+/// The Arrow schema of the data files, carrying the Iceberg field ids as Parquet field ids.
+fn arrow_schema(schema: &IcebergSchema) -> Schema {
+    Schema::new(schema.fields.iter().map(arrow_field).collect::<Vec<_>>())
+}
+
+fn arrow_field(field: &NestedField) -> Field {
+    with_field_id(Field::new(&field.name, arrow_type(&field.field_type), !field.required), field.id)
+}
+
+fn with_field_id(field: Field, id: i32) -> Field {
+    field.with_metadata(HashMap::from([(String::from("PARQUET:field_id"), id.to_string())]))
+}
+
+fn arrow_type(field_type: &IcebergType) -> DataType {
+    match field_type {
+        IcebergType::Struct(struct_type) => {
+            DataType::Struct(struct_type.fields.iter().map(arrow_field).collect::<Vec<_>>().into())
+        }
+        IcebergType::List(list) => {
+            let item = Field::new("element", arrow_type(&list.element), !list.element_required);
+            DataType::List(Arc::new(with_field_id(item, list.element_id)))
+        }
+        IcebergType::Primitive(name) => match name.as_str() {
+            "boolean" => DataType::Boolean,
+            "int" => DataType::Int32,
+            "long" => DataType::Int64,
+            "float" => DataType::Float32,
+            "double" => DataType::Float64,
+            "binary" => DataType::Binary,
+            _ => DataType::Utf8,
+        },
+    }
+}
+
+/// An Iceberg table stored in an object store, using `metadata/version-hint.text` as catalog pointer
+/// to the current `metadata/v<N>.metadata.json`.
+pub struct IcebergTable {
+    store: Arc<dyn ObjectStore>,
+    path: Path,
+    location: String,
+}
+
+impl IcebergTable {
+    /// `path` is the table root in the store, and `location` the same root as a URL for readers.
+    pub fn new(store: Arc<dyn ObjectStore>, path: Path, location: String) -> Self {
+        IcebergTable { store, path, location }
+    }
+
+    fn metadata_path(&self, file: &str) -> Path {
+        self.path.child("metadata").child(file)
+    }
+
+    fn url(&self, path: &Path) -> String {
+        let relative = path.as_ref().strip_prefix(self.path.as_ref()).unwrap_or(path.as_ref());
+        format!("{}/{}", self.location.trim_end_matches('/'), relative.trim_start_matches('/'))
+    }
+
+    /// Loads the latest committed metadata, if the table exists.
+    async fn load(&self) -> Result<Option<(i64, TableMetadata)>, Box<dyn std::error::Error>> {
+        let mut version = match self.store.get(&self.metadata_path(VERSION_HINT)).await {
+            Ok(hint) => String::from_utf8(hint.bytes().await?.to_vec())?.trim().parse::<i64>()?,
+            Err(object_store::Error::NotFound { .. }) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        // The hint is written after the metadata file, so it may lag behind the latest commit
+        while self
+            .store
+            .head(&self.metadata_path(&format!("v{}.metadata.json", version + 1)))
+            .await
+            .is_ok()
+        {
+            version += 1;
+        }
+        let metadata = self
+            .store
+            .get(&self.metadata_path(&format!("v{}.metadata.json", version)))
+            .await?
+            .bytes()
+            .await?;
+        Ok(Some((version, serde_json::from_slice(&metadata)?)))
+    }
+
+    /// The data files of the current snapshot, so readers never see files of a failed commit.
+    pub async fn data_files(&self) -> Result<Vec<Path>, Box<dyn std::error::Error>> {
+        let metadata = match self.load().await? {
+            Some((_, metadata)) => metadata,
+            None => return Ok(vec![]),
+        };
+        let snapshot = match metadata.current_snapshot() {
+            Some(snapshot) => snapshot,
+            None => return Ok(vec![]),
+        };
+
+        let manifest_list = self.store.get(&self.path_of(&snapshot.manifest_list)).await?.bytes().await?;
+        let mut files = Vec::new();
+        for manifest in read_manifest_list(&manifest_list)? {
+            let manifest = self.store.get(&self.path_of(&manifest.manifest_path)).await?.bytes().await?;
+            for entry in read_manifest(&manifest)? {
+                if entry.status != STATUS_DELETED {
+                    files.push(self.path_of(&entry.data_file.file_path));
+                }
+            }
+        }
+        Ok(files)
+    }
+
+    /// Appends the rows to the table in a single snapshot.
+    ///
+    /// `schema` is the Arrow schema of the rows, which must have a `date` field. The table schema
+    /// follows it. Data files are written first, and the commit only becomes visible once the
+    /// next metadata version is created, which fails if another writer took that version.
+    ///
+    /// `positions` holds the Kafka position of each row. The snapshot summary records the next
+    /// offset of every partition, and rows before it are skipped, so appending messages that were
+    /// consumed again after a crash leaves the table unchanged.
+    pub async fn append(
+        &self,
+        schema: &Schema,
+        rows: &[Value],
+        positions: &[Position],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        for attempt in 1..=COMMIT_ATTEMPTS {
+            let (version, mut metadata) = match self.load().await? {
+                Some((version, metadata)) => (version, metadata),
+                None => (0, TableMetadata::new(&self.location, schema)),
+            };
+            let mut offsets = metadata.offsets();
+            let rows: Vec<Value> = rows
+                .iter()
+                .zip(positions)
+                .filter(|(_, (partition, offset))| offsets.get(partition).is_none_or(|next| offset >= next))
+                .map(|(row, _)| row.clone())
+                .collect();
+            if rows.is_empty() {
+                return Ok(());
+            }
+            for (partition, offset) in positions {
+                let next = offsets.entry(*partition).or_default();
+                *next = (*next).max(offset + 1);
+            }
+            metadata.evolve(schema);
+            let file_schema = Arc::new(arrow_schema(metadata.current_schema()));
+
+            let snapshot_id = rand::thread_rng().gen_range(1..i64::MAX);
+            let sequence_number = metadata.last_sequence_number + 1;
+            let entries = self.write_data_files(&file_schema, &rows, snapshot_id, sequence_number).await?;
+            let manifest = self.write_manifest(&metadata, &entries, snapshot_id, sequence_number).await?;
+            let manifest_list = self.write_manifest_list(&metadata, manifest, snapshot_id, sequence_number).await?;
+
+            let now = chrono::Utc::now().timestamp_millis();
+            let added_records: i64 = entries.iter().map(|entry| entry.data_file.record_count).sum();
+            let mut summary = BTreeMap::from([
+                (String::from("operation"), String::from("append")),
+                (String::from("added-data-files"), entries.len().to_string()),
+                (String::from("added-records"), added_records.to_string()),
+            ]);
+            summary.extend(
+                offsets
+                    .iter()
+                    .map(|(partition, offset)| (format!("{}{}", OFFSET_PROPERTY, partition), offset.to_string())),
+            );
+            metadata.snapshots.push(Snapshot {
+                snapshot_id,
+                parent_snapshot_id: metadata.current_snapshot_id,
+                sequence_number,
+                timestamp_ms: now,
+                manifest_list,
+                summary,
+                schema_id: metadata.current_schema_id,
+            });
+            metadata.current_snapshot_id = Some(snapshot_id);
+            metadata.last_sequence_number = sequence_number;
+            metadata.last_updated_ms = now;
+            metadata.snapshot_log.push(SnapshotLogEntry {
+                timestamp_ms: now,
+                snapshot_id,
+            });
+            if version > 0 {
+                metadata.metadata_log.push(MetadataLogEntry {
+                    timestamp_ms: now,
+                    metadata_file: self.url(&self.metadata_path(&format!("v{}.metadata.json", version))),
+                });
+            }
+            metadata.refs.insert(
+                String::from("main"),
+                SnapshotRef {
+                    snapshot_id,
+                    kind: String::from("branch"),
+                },
+            );
+
+            let next_version = version + 1;
+            let options = PutOptions {
+                mode: PutMode::Create,
+                ..Default::default()
+            };
+            let payload = PutPayload::from(serde_json::to_vec_pretty(&metadata)?);
+            let metadata_path = self.metadata_path(&format!("v{}.metadata.json", next_version));
+            match self.store.put_opts(&metadata_path, payload, options).await {
+                Ok(_) => {
+                    self.store
+                        .put(&self.metadata_path(VERSION_HINT), PutPayload::from(next_version.to_string()))
+                        .await?;
+                    println!(
+                        "Committed snapshot {} with {} rows to {} (v{})",
+                        snapshot_id, added_records, self.location, next_version
+                    );
+                    return Ok(());
+                }
+                Err(object_store::Error::AlreadyExists { .. }) => {
+                    eprintln!(
+                        "Version {} of {} was committed concurrently, retrying ({}/{})",
+                        next_version, self.location, attempt, COMMIT_ATTEMPTS
+                    );
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Err(format!("Failed to commit to {} after {} attempts", self.location, COMMIT_ATTEMPTS).into())
+    }
+
+    async fn write_data_files(
+        &self,
+        file_schema: &Arc<Schema>,
+        rows: &[Value],
+        snapshot_id: i64,
+        sequence_number: i64,
+    ) -> Result<Vec<ManifestEntry>, Box<dyn std::error::Error>> {
+        let mut partitions: BTreeMap<String, Vec<&Value>> = BTreeMap::new();
+        for row in rows {
+            let date = row.get(PARTITION_COLUMN).and_then(Value::as_str).unwrap_or_default();
+            partitions.entry(date.to_string()).or_default().push(row);
+        }
+
+        let mut entries = Vec::new();
+        for (date, rows) in partitions {
+            let mut decoder = ReaderBuilder::new(Arc::clone(file_schema)).build_decoder()?;
+            decoder.serialize(&rows)?;
+            let batch = decoder
+                .flush()?
+                .unwrap_or_else(|| RecordBatch::new_empty(Arc::clone(file_schema)));
+            let bytes = encode(&batch)?;
+            let path = self
+                .path
+                .child("data")
+                .child(format!("{}={}", PARTITION_COLUMN, date))
+                .child(format!("{}.parquet", uuid::Uuid::new_v4()));
+            let file_size_in_bytes = bytes.len() as i64;
+            self.store.put(&path, PutPayload::from(bytes)).await?;
+
+            entries.push(ManifestEntry {
+                status: STATUS_ADDED,
+                snapshot_id: Some(snapshot_id),
+                sequence_number: Some(sequence_number),
+                file_sequence_number: Some(sequence_number),
+                data_file: DataFile {
+                    content: CONTENT_DATA,
+                    file_path: self.url(&path),
+                    file_format: String::from("PARQUET"),
+                    partition: Partition { date: Some(date) },
+                    record_count: batch.num_rows() as i64,
+                    file_size_in_bytes,
+                },
+            });
+        }
+        Ok(entries)
+    }
+
+    async fn write_manifest(
+        &self,
+        metadata: &TableMetadata,
+        entries: &[ManifestEntry],
+        snapshot_id: i64,
+        sequence_number: i64,
+    ) -> Result<ManifestFile, Box<dyn std::error::Error>> {
+        let spec = &metadata.partition_specs[0];
+        let avro_metadata = BTreeMap::from([
+            ("schema", serde_json::to_string(metadata.current_schema())?),
+            ("schema-id", metadata.current_schema_id.to_string()),
+            ("partition-spec", serde_json::to_string(&spec.fields)?),
+            ("partition-spec-id", spec.spec_id.to_string()),
+            ("format-version", FORMAT_VERSION.to_string()),
+            ("content", String::from("data")),
+        ]);
+        let bytes = write_manifest(entries, PARTITION_FIELD_ID, &avro_metadata)?;
+        let path = self.metadata_path(&format!("{}-m0.avro", uuid::Uuid::new_v4()));
+        let manifest_length = bytes.len() as i64;
+        self.store.put(&path, PutPayload::from(bytes)).await?;
+
+        Ok(ManifestFile {
+            manifest_path: self.url(&path),
+            manifest_length,
+            partition_spec_id: spec.spec_id,
+            content: CONTENT_DATA,
+            sequence_number,
+            min_sequence_number: sequence_number,
+            added_snapshot_id: snapshot_id,
+            added_files_count: entries.len() as i32,
+            existing_files_count: 0,
+            deleted_files_count: 0,
+            added_rows_count: entries.iter().map(|entry| entry.data_file.record_count).sum(),
+            existing_rows_count: 0,
+            deleted_rows_count: 0,
+        })
+    }
+
+    /// Writes the manifest list of the new snapshot: the manifests of the parent snapshot plus the new one.
+    async fn write_manifest_list(
+        &self,
+        metadata: &TableMetadata,
+        manifest: ManifestFile,
+        snapshot_id: i64,
+        sequence_number: i64,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let parent = metadata.current_snapshot();
+        let mut manifests = match parent {
+            Some(parent) => {
+                let path = self.path_of(&parent.manifest_list);
+                read_manifest_list(&self.store.get(&path).await?.bytes().await?)?
+            }
+            None => vec![],
+        };
+        manifests.push(manifest);
+
+        let mut avro_metadata = BTreeMap::from([
+            ("snapshot-id", snapshot_id.to_string()),
+            ("sequence-number", sequence_number.to_string()),
+            ("format-version", FORMAT_VERSION.to_string()),
+        ]);
+        if let Some(parent) = parent {
+            avro_metadata.insert("parent-snapshot-id", parent.snapshot_id.to_string());
+        }
+        let bytes = write_manifest_list(&manifests, &avro_metadata)?;
+        let path = self.metadata_path(&format!("snap-{}-{}.avro", snapshot_id, uuid::Uuid::new_v4()));
+        self.store.put(&path, PutPayload::from(bytes)).await?;
+        Ok(self.url(&path))
+    }
+
+    /// Converts a URL written in the metadata back into a path of the store.
+    fn path_of(&self, url: &str) -> Path {
+        let relative = url
+            .strip_prefix(self.location.trim_end_matches('/'))
+            .unwrap_or(url)
+            .trim_start_matches('/');
+        Path::from(format!("{}/{}", self.path, relative))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use object_store::memory::InMemory;
+    use serde_json::json;
+
+    use super::*;
+
+    const LOCATION: &str = "s3://omelette/iceberg/invoices";
+
+    fn schema() -> Schema {
+        let name = Field::new("name", DataType::Utf8, true);
+        let client = Field::new("client", DataType::Struct(vec![name].into()), true);
+        let item = Field::new("item", DataType::Float64, false);
+        Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new(PARTITION_COLUMN, DataType::Utf8, false),
+            client,
+            Field::new("prices", DataType::List(Arc::new(item)), true),
+        ])
+    }
+
+    fn table() -> (Arc<InMemory>, IcebergTable) {
+        let store = Arc::new(InMemory::new());
+        let table = IcebergTable::new(store.clone(), Path::from("iceberg/invoices"), LOCATION.to_string());
+        (store, table)
+    }
+
+    fn rows(ids: &[i32]) -> Vec<Value> {
+        ids.iter()
+            .map(|id| {
+                let date = format!("2025-01-{:02}", id % 2 + 1);
+                json!({"id": id, "date": date, "client": {"name": "Ada"}, "prices": [1.5]})
+            })
+            .collect()
+    }
+
+    async fn get(store: &InMemory, path: &Path) -> bytes::Bytes {
+        store.get(path).await.unwrap().bytes().await.unwrap()
+    }
+
+    #[test]
+    fn new_metadata_numbers_columns_and_partitions_by_date() {
+        let metadata = TableMetadata::new(LOCATION, &schema());
+        let fields = &metadata.current_schema().fields;
+
+        let ids: Vec<(&str, i32, bool)> =
+            fields.iter().map(|field| (field.name.as_str(), field.id, field.required)).collect();
+        let expected = vec![("id", 1, true), (PARTITION_COLUMN, 2, true), ("client", 3, false), ("prices", 5, false)];
+        assert_eq!(ids, expected);
+        let IcebergType::Struct(client) = &fields[2].field_type else { panic!("client is not a struct") };
+        assert_eq!(client.fields[0].id, 4);
+        let IcebergType::List(prices) = &fields[3].field_type else { panic!("prices is not a list") };
+        assert_eq!((prices.element_id, prices.element_required), (6, true));
+        assert_eq!(metadata.last_column_id, 6);
+
+        let spec = &metadata.partition_specs[0];
+        assert_eq!(spec.fields[0].source_id, 2);
+        assert_eq!(spec.fields[0].field_id, PARTITION_FIELD_ID);
+        assert_eq!(spec.fields[0].transform, "identity");
+        assert_eq!(metadata.current_snapshot_id, None);
+    }
+
+    #[test]
+    fn evolve_keeps_ids_and_adds_optional_columns() {
+        let mut metadata = TableMetadata::new(LOCATION, &schema());
+        metadata.evolve(&schema());
+        assert_eq!(metadata.schemas.len(), 1);
+
+        let mut fields = schema().fields().to_vec();
+        fields.remove(2);
+        fields.push(Arc::new(Field::new("currency", DataType::Utf8, false)));
+        metadata.evolve(&Schema::new(fields));
+
+        assert_eq!(metadata.current_schema_id, 1);
+        let current: Vec<(&str, i32, bool)> = metadata
+            .current_schema()
+            .fields
+            .iter()
+            .map(|field| (field.name.as_str(), field.id, field.required))
+            .collect();
+        let expected = vec![("id", 1, true), (PARTITION_COLUMN, 2, true), ("prices", 5, false), ("currency", 7, false)];
+        assert_eq!(current, expected);
+        assert_eq!(metadata.last_column_id, 7);
+    }
+
+    #[test]
+    fn arrow_schema_carries_the_field_ids() {
+        let metadata = TableMetadata::new(LOCATION, &schema());
+        let arrow = arrow_schema(metadata.current_schema());
+
+        let field_id = |field: &Field| field.metadata()["PARQUET:field_id"].clone();
+        assert_eq!(field_id(arrow.field(0)), "1");
+        let DataType::List(item) = arrow.field(3).data_type() else { panic!("prices is not a list") };
+        assert_eq!(field_id(item), "6");
+        assert!(!arrow.field(0).is_nullable());
+        assert!(arrow.field(2).is_nullable());
+    }
+
+    #[tokio::test]
+    async fn append_commits_a_snapshot_with_its_manifests() {
+        let (store, table) = table();
+        table.append(&schema(), &rows(&[1, 2, 3]), &[(0, 10), (0, 11), (1, 4)]).await.unwrap();
+
+        let (version, metadata) = table.load().await.unwrap().unwrap();
+        assert_eq!(version, 1);
+        let hint = get(&store, &table.metadata_path(VERSION_HINT)).await;
+        assert_eq!(&hint[..], b"1");
+
+        let snapshot = metadata.current_snapshot().unwrap();
+        assert_eq!(snapshot.sequence_number, 1);
+        assert_eq!(snapshot.parent_snapshot_id, None);
+        assert_eq!(snapshot.summary["operation"], "append");
+        assert_eq!(snapshot.summary["added-data-files"], "2");
+        assert_eq!(snapshot.summary["added-records"], "3");
+        assert_eq!(metadata.offsets(), BTreeMap::from([(0, 12), (1, 5)]));
+        assert_eq!(metadata.refs["main"].snapshot_id, snapshot.snapshot_id);
+
+        let manifests = read_manifest_list(&get(&store, &table.path_of(&snapshot.manifest_list)).await).unwrap();
+        assert_eq!(manifests.len(), 1);
+        assert_eq!(manifests[0].added_snapshot_id, snapshot.snapshot_id);
+        assert_eq!((manifests[0].added_files_count, manifests[0].added_rows_count), (2, 3));
+
+        let entries = read_manifest(&get(&store, &table.path_of(&manifests[0].manifest_path)).await).unwrap();
+        let partitions: Vec<(Option<&str>, i64)> = entries
+            .iter()
+            .map(|entry| (entry.data_file.partition.date.as_deref(), entry.data_file.record_count))
+            .collect();
+        assert_eq!(partitions, vec![(Some("2025-01-01"), 1), (Some("2025-01-02"), 2)]);
+        assert!(entries.iter().all(|entry| entry.status == STATUS_ADDED
+            && entry.data_file.file_path.starts_with(LOCATION)
+            && entry.data_file.file_path.ends_with(".parquet")));
+
+        assert_eq!(table.data_files().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn append_skips_the_offsets_already_committed() {
+        let (store, table) = table();
+        table.append(&schema(), &rows(&[1, 2]), &[(0, 10), (0, 11)]).await.unwrap();
+
+        // The same messages consumed again commit nothing
+        table.append(&schema(), &rows(&[1, 2]), &[(0, 10), (0, 11)]).await.unwrap();
+        let (version, _) = table.load().await.unwrap().unwrap();
+        assert_eq!(version, 1);
+
+        // Only the message after the committed offsets is appended
+        table.append(&schema(), &rows(&[2, 3]), &[(0, 11), (0, 12)]).await.unwrap();
+        let (version, metadata) = table.load().await.unwrap().unwrap();
+        assert_eq!(version, 2);
+        let snapshot = metadata.current_snapshot().unwrap();
+        assert_eq!(snapshot.summary["added-records"], "1");
+        assert_eq!(snapshot.sequence_number, 2);
+        assert!(snapshot.parent_snapshot_id.is_some());
+        assert_eq!(metadata.offsets(), BTreeMap::from([(0, 13)]));
+        assert_eq!(metadata.metadata_log.len(), 1);
+
+        let manifests = read_manifest_list(&get(&store, &table.path_of(&snapshot.manifest_list)).await).unwrap();
+        let rows: Vec<i64> = manifests.iter().map(|manifest| manifest.added_rows_count).collect();
+        assert_eq!(rows, vec![2, 1]);
+        assert_eq!(table.data_files().await.unwrap().len(), 3);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,226 @@
+mod ddl;
+mod iceberg;
+mod manifest;
+mod report;
+mod schema;
+mod writer;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use common::avro::decode_payload;
+use common::invoice::Invoice;
+use iceberg::{IcebergTable, Position};
+use report::{OutputFormat, Report, ReportDatabase};
+use object_store::ObjectStore;
+use rdkafka::config::RDKafkaLogLevel;
+use rdkafka::consumer::{CommitMode, Consumer, StreamConsumer};
+use rdkafka::ClientConfig;
+use rdkafka::Message;
+use schema_registry_converter::async_impl::avro::AvroDecoder;
+use schema_registry_converter::async_impl::schema_registry::SrSettings;
+use serde_avro_derive::BuildSchema;
+use url::Url;
+use writer::LakeWriter;
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum TableFormat {
+    /// Hive-partitioned Parquet files under `invoices/`
+    Hive,
+    /// Iceberg tables `invoices` and `invoice_lines` under `iceberg/`
+    Iceberg,
+}
+
+#[derive(Subcommand)]
+enum LakeCommand {
+    /// Runs a SQL report over the invoices of the lake and exits
+    Report {
+        /// Built-in report to run
+        #[arg(value_enum, required_unless_present = "sql")]
+        report: Option<Report>,
+
+        /// SQL query over the `invoices` and `invoice_lines` tables, instead of a built-in report
+        #[arg(long, conflicts_with = "report")]
+        sql: Option<String>,
+
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+}
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    /// Root of the lake: `s3://bucket/prefix` for MinIO or `file:///absolute/path` for a local directory
+    #[arg(long, default_value = "s3://omelette")]
+    lake_url: String,
+
+    /// Layout of the written tables
+    #[arg(long, value_enum, default_value_t = TableFormat::Iceberg)]
+    table_format: TableFormat,
+
+    /// S3 endpoint, used for `s3://` lake URLs
+    #[arg(long, default_value = "http://localhost:9000")]
+    s3_endpoint: String,
+
+    #[arg(long, default_value = "minio")]
+    s3_access_key: String,
+
+    #[arg(long, default_value = "password")]
+    s3_secret_key: String,
+
+    /// Number of buffered invoices that triggers a write
+    #[arg(long, default_value_t = 1000)]
+    flush_size: usize,
+
+    /// Maximum number of seconds an invoice stays buffered
+    #[arg(long, default_value_t = 60)]
+    flush_interval_secs: u64,
+
+    /// If provided, prints the Trino DDL of the tables and exits
+    #[arg(long)]
+    print_ddl: bool,
+
+    #[command(subcommand)]
+    command: Option<LakeCommand>,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+    let schema = schema::arrow_schema(Invoice::schema()?.json())?;
+
+    let url = Url::parse(&cli.lake_url)?;
+    let lake_url = cli.lake_url.trim_end_matches('/');
+    if cli.print_ddl {
+        match cli.table_format {
+            TableFormat::Hive => print!("{}", ddl::trino_ddl(&schema, "invoices", &format!("{}/invoices", lake_url))),
+            TableFormat::Iceberg => print!(
+                "{}",
+                ddl::iceberg_ddl(&["invoices", "invoice_lines"], &format!("{}/iceberg", lake_url))
+            ),
+        }
+        return Ok(());
+    }
+
+    let options = [
+        ("aws_endpoint", cli.s3_endpoint),
+        ("aws_access_key_id", cli.s3_access_key),
+        ("aws_secret_access_key", cli.s3_secret_key),
+        ("aws_region", String::from("us-east-1")),
+        ("aws_allow_http", String::from("true")),
+        // Iceberg commits create the next metadata version only if it does not exist yet
+        ("aws_conditional_put", String::from("etag")),
+    ];
+    let (store, root) = object_store::parse_url_opts(&url, options)?;
+    let store: Arc<dyn ObjectStore> = Arc::from(store);
+
+    if let Some(LakeCommand::Report { report, sql, format }) = cli.command {
+        let mut database = ReportDatabase::new();
+        database.load_lake(&store, &root, lake_url, cli.table_format).await?;
+        let sql = sql.as_deref().or(report.as_ref().map(Report::sql)).unwrap_or_default();
+        let (columns, rows) = database.query(sql)?;
+        report::print(&columns, &rows, format)?;
+        return Ok(());
+    }
+
+    let writer = LakeWriter::new(Arc::clone(&store), root.child("invoices"), &schema);
+
+    let invoices_schema = schema::invoices_schema(&schema);
+    let lines_schema = schema::lines_schema(&schema)?;
+    let iceberg_root = root.child("iceberg");
+    let invoices_table = IcebergTable::new(
+        Arc::clone(&store),
+        iceberg_root.child("invoices"),
+        format!("{}/iceberg/invoices", lake_url),
+    );
+    let lines_table = IcebergTable::new(
+        Arc::clone(&store),
+        iceberg_root.child("invoice_lines"),
+        format!("{}/iceberg/invoice_lines", lake_url),
+    );
+
+    let sr_settings = SrSettings::new(String::from("http://localhost:8085"));
+    let decoder = AvroDecoder::new(sr_settings);
+
+    // Offsets are committed only once the buffered invoices are written
+    let consumer: StreamConsumer = ClientConfig::new()
+        .set("group.id", "lake-sink")
+        .set("bootstrap.servers", "localhost:19092")
+        .set("enable.partition.eof", "false")
+        .set("auto.offset.reset", "earliest")
+        .set("session.timeout.ms", "6000")
+        .set("enable.auto.commit", "false")
+        .set_log_level(RDKafkaLogLevel::Debug)
+        .create()
+        .expect("Consumer creation failed");
+
+    consumer
+        .subscribe(&["Invoice"])
+        .expect("Failed to subscribe to topics");
+
+    let mut buffer: Vec<Invoice> = Vec::new();
+    let mut positions: Vec<Position> = Vec::new();
+    let mut interval = tokio::time::interval(Duration::from_secs(cli.flush_interval_secs));
+
+    loop {
+        let flush = tokio::select! {
+            message = consumer.recv() => match message {
+                Ok(message) => {
+                    // An invoice that can't be decoded is skipped, its offset is committed with
+                    // the next flush
+                    if let Some(payload) = message.payload() {
+                        match decode_payload::<Invoice>(&decoder, payload).await {
+                            Ok(invoice) => {
+                                buffer.push(invoice);
+                                positions.push((message.partition(), message.offset()));
+                            }
+                            Err(e) => eprintln!(
+                                "Skipping message {}/{}/{}: {}",
+                                message.topic(),
+                                message.partition(),
+                                message.offset(),
+                                e
+                            ),
+                        }
+                    }
+                    buffer.len() >= cli.flush_size
+                }
+                Err(e) => {
+                    eprintln!("Error while consuming: {:?}", e);
+                    false
+                }
+            },
+            _ = interval.tick() => !buffer.is_empty(),
+        };
+
+        if flush {
+            match cli.table_format {
+                TableFormat::Hive => {
+                    writer.write(&buffer, &positions).await?;
+                }
+                TableFormat::Iceberg => {
+                    let invoices = buffer
+                        .iter()
+                        .map(serde_json::to_value)
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let (lines, line_positions): (Vec<_>, Vec<_>) = invoices
+                        .iter()
+                        .zip(&positions)
+                        .flat_map(|(invoice, position)| {
+                            schema::line_rows(invoice).into_iter().map(move |line| (line, *position))
+                        })
+                        .unzip();
+                    // Each table skips the offsets it already holds, so a crash between the two
+                    // commits doesn't duplicate the invoices when they are consumed again
+                    invoices_table.append(&invoices_schema, &invoices, &positions).await?;
+                    lines_table.append(&lines_schema, &lines, &line_positions).await?;
+                }
+            }
+            consumer.commit_consumer_state(CommitMode::Sync)?;
+            buffer.clear();
+            positions.clear();
+        }
+    }
+}
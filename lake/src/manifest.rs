@@ -0,0 +1,142 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_avro_fast::object_container_file_encoding::{Compression, Reader, WriterBuilder};
+use serde_avro_fast::ser::SerializerConfig;
+use serde_avro_fast::Schema;
+
+use crate::schema::PARTITION_COLUMN;
+
+/// Avro schema of the entries of a manifest file, with the field ids required by the Iceberg spec.
+/// The partition record only holds the identity partition on the `date` column.
+fn manifest_entry_schema(partition_field_id: i32) -> String {
+    format!(
+        r#"{{"type":"record","name":"manifest_entry","fields":[
+            {{"name":"status","type":"int","field-id":0}},
+            {{"name":"snapshot_id","type":["null","long"],"default":null,"field-id":1}},
+            {{"name":"sequence_number","type":["null","long"],"default":null,"field-id":3}},
+            {{"name":"file_sequence_number","type":["null","long"],"default":null,"field-id":4}},
+            {{"name":"data_file","type":{{"type":"record","name":"r2","fields":[
+                {{"name":"content","type":"int","field-id":134}},
+                {{"name":"file_path","type":"string","field-id":100}},
+                {{"name":"file_format","type":"string","field-id":101}},
+                {{"name":"partition","type":{{"type":"record","name":"r102","fields":[
+                    {{"name":"{partition}","type":["null","string"],"default":null,"field-id":{partition_field_id}}}
+                ]}},"field-id":102}},
+                {{"name":"record_count","type":"long","field-id":103}},
+                {{"name":"file_size_in_bytes","type":"long","field-id":104}}
+            ]}},"field-id":2}}
+        ]}}"#,
+        partition = PARTITION_COLUMN,
+        partition_field_id = partition_field_id,
+    )
+}
+
+const MANIFEST_FILE_SCHEMA: &str = r#"{"type":"record","name":"manifest_file","fields":[
+    {"name":"manifest_path","type":"string","field-id":500},
+    {"name":"manifest_length","type":"long","field-id":501},
+    {"name":"partition_spec_id","type":"int","field-id":502},
+    {"name":"content","type":"int","field-id":517},
+    {"name":"sequence_number","type":"long","field-id":515},
+    {"name":"min_sequence_number","type":"long","field-id":516},
+    {"name":"added_snapshot_id","type":"long","field-id":503},
+    {"name":"added_files_count","type":"int","field-id":504},
+    {"name":"existing_files_count","type":"int","field-id":505},
+    {"name":"deleted_files_count","type":"int","field-id":506},
+    {"name":"added_rows_count","type":"long","field-id":512},
+    {"name":"existing_rows_count","type":"long","field-id":513},
+    {"name":"deleted_rows_count","type":"long","field-id":514}
+]}"#;
+
+/// Status of an entry that was added by the snapshot of its manifest.
+pub const STATUS_ADDED: i32 = 1;
+/// Status of an entry that was removed by the snapshot of its manifest.
+pub const STATUS_DELETED: i32 = 2;
+/// Content type of data files and of manifests listing data files.
+pub const CONTENT_DATA: i32 = 0;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub status: i32,
+    pub snapshot_id: Option<i64>,
+    pub sequence_number: Option<i64>,
+    pub file_sequence_number: Option<i64>,
+    pub data_file: DataFile,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DataFile {
+    pub content: i32,
+    pub file_path: String,
+    pub file_format: String,
+    pub partition: Partition,
+    pub record_count: i64,
+    pub file_size_in_bytes: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Partition {
+    pub date: Option<String>,
+}
+
+/// An entry of a manifest list, pointing to one manifest file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestFile {
+    pub manifest_path: String,
+    pub manifest_length: i64,
+    pub partition_spec_id: i32,
+    pub content: i32,
+    pub sequence_number: i64,
+    pub min_sequence_number: i64,
+    pub added_snapshot_id: i64,
+    pub added_files_count: i32,
+    pub existing_files_count: i32,
+    pub deleted_files_count: i32,
+    pub added_rows_count: i64,
+    pub existing_rows_count: i64,
+    pub deleted_rows_count: i64,
+}
+
+/// Encodes a manifest file. `metadata` holds the table schema and partition spec, as required
+/// by readers of the manifest.
+pub fn write_manifest(
+    entries: &[ManifestEntry],
+    partition_field_id: i32,
+    metadata: &BTreeMap<&str, String>,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let schema: Schema = manifest_entry_schema(partition_field_id).parse()?;
+    write_avro(&schema, entries, metadata)
+}
+
+pub fn write_manifest_list(
+    manifests: &[ManifestFile],
+    metadata: &BTreeMap<&str, String>,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let schema: Schema = MANIFEST_FILE_SCHEMA.parse()?;
+    write_avro(&schema, manifests, metadata)
+}
+
+pub fn read_manifest(bytes: &[u8]) -> Result<Vec<ManifestEntry>, Box<dyn std::error::Error>> {
+    let mut reader = Reader::from_slice(bytes)?;
+    let entries = reader.deserialize::<ManifestEntry>().collect::<Result<Vec<_>, _>>()?;
+    Ok(entries)
+}
+
+pub fn read_manifest_list(bytes: &[u8]) -> Result<Vec<ManifestFile>, Box<dyn std::error::Error>> {
+    let mut reader = Reader::from_slice(bytes)?;
+    let manifests = reader.deserialize::<ManifestFile>().collect::<Result<Vec<_>, _>>()?;
+    Ok(manifests)
+}
+
+fn write_avro<T: Serialize>(
+    schema: &Schema,
+    records: &[T],
+    metadata: &BTreeMap<&str, String>,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut serializer_config = SerializerConfig::new(schema);
+    let mut writer = WriterBuilder::new(&mut serializer_config)
+        .compression(Compression::Null)
+        .build_with_user_metadata(Vec::new(), metadata)?;
+    writer.serialize_all(records.iter())?;
+    Ok(writer.into_inner()?)
+}
@@ -0,0 +1,412 @@
+use std::io::Cursor;
+use std::sync::Arc;
+
+use clap::ValueEnum;
+use comfy_table::Table;
+use futures::TryStreamExt;
+use object_store::path::Path;
+use object_store::ObjectStore;
+use polars::prelude::*;
+use polars::sql::SQLContext;
+use serde_json::{Map, Value};
+
+use crate::iceberg::IcebergTable;
+use crate::schema::{LINES_COLUMN, PARTITION_COLUMN};
+use crate::TableFormat;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Report {
+    /// Number of invoices and revenue per day
+    DailyRevenue,
+    /// The ten products that brought the most revenue
+    TopProducts,
+    /// Average number of products and amount per invoice
+    AverageBasket,
+    /// Number of invoices and revenue per client
+    RevenuePerClient,
+}
+
+impl Report {
+    pub fn sql(&self) -> &'static str {
+        match self {
+            Report::DailyRevenue => {
+                "SELECT date, COALESCE(currency, 'EUR') AS currency, COUNT(*) AS invoices, ROUND(SUM(total_price), 2) AS revenue
+                FROM invoices GROUP BY 1, 2 ORDER BY 1, 2"
+            }
+            Report::TopProducts => {
+                "SELECT id AS product_id, name, COUNT(*) AS units, ROUND(SUM(price), 2) AS revenue
+                FROM invoice_lines GROUP BY id, name ORDER BY revenue DESC LIMIT 10"
+            }
+            Report::AverageBasket => {
+                "SELECT COALESCE(currency, 'EUR') AS currency, COUNT(*) AS invoices, ROUND(AVG(size), 2) AS average_products,
+                    ROUND(AVG(total_price), 2) AS average_amount
+                FROM invoices GROUP BY 1 ORDER BY 1"
+            }
+            Report::RevenuePerClient => {
+                "SELECT client_id, client_name, COALESCE(currency, 'EUR') AS currency, COUNT(*) AS invoices,
+                    ROUND(SUM(total_price), 2) AS revenue
+                FROM invoices GROUP BY 1, 2, 3 ORDER BY revenue DESC"
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Csv,
+    Json,
+}
+
+/// The `invoices` and `invoice_lines` tables of the lake, queried with the SQL engine of Polars
+/// straight from the Parquet files.
+pub struct ReportDatabase {
+    context: SQLContext,
+}
+
+impl ReportDatabase {
+    pub fn new() -> Self {
+        ReportDatabase {
+            context: SQLContext::new(),
+        }
+    }
+
+    /// Registers the invoices of the lake, reading the current snapshot of the Iceberg tables or
+    /// every file of the Hive layout.
+    pub async fn load_lake(
+        &mut self,
+        store: &Arc<dyn ObjectStore>,
+        root: &Path,
+        lake_url: &str,
+        table_format: TableFormat,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let (invoices, lines) = match table_format {
+            TableFormat::Iceberg => {
+                let mut tables = Vec::new();
+                for table in ["invoices", "invoice_lines"] {
+                    let location = format!("{}/iceberg/{}", lake_url, table);
+                    let table = IcebergTable::new(Arc::clone(store), root.child("iceberg").child(table), location);
+                    let mut frames = Vec::new();
+                    for file in table.data_files().await? {
+                        frames.push(read_parquet(store, &file).await?);
+                    }
+                    tables.push(frames);
+                }
+                let lines = tables.pop().unwrap_or_default();
+                (tables.pop().unwrap_or_default(), lines)
+            }
+            TableFormat::Hive => {
+                let files: Vec<_> = store.list(Some(&root.child("invoices"))).try_collect().await?;
+                let mut invoices = Vec::new();
+                for file in files.iter().filter(|file| file.location.as_ref().ends_with(".parquet")) {
+                    // The date of the invoices is only present in the `date=` segment of the path
+                    let date = file
+                        .location
+                        .parts()
+                        .find_map(|part| part.as_ref().strip_prefix("date=").map(String::from));
+                    let invoice = read_parquet(store, &file.location).await?;
+                    invoices.push(match date {
+                        Some(date) => invoice.with_column(lit(date).alias(PARTITION_COLUMN)),
+                        None => invoice,
+                    });
+                }
+                let lines = invoices.iter().cloned().map(line_rows).collect();
+                (invoices, lines)
+            }
+        };
+
+        eprintln!("Reading {} invoice files and {} line files", invoices.len(), lines.len());
+        self.register("invoices", invoices)?;
+        self.register("invoice_lines", lines)?;
+        Ok(())
+    }
+
+    /// Registers `table` as the union of the frames, whose columns may differ after a schema
+    /// change. Nested rows become `parent_child` columns and lists are dropped.
+    pub fn register(&mut self, table: &str, frames: Vec<LazyFrame>) -> PolarsResult<()> {
+        let mut frame = if frames.is_empty() {
+            // Keep the reports runnable on an empty lake
+            let columns = match table {
+                "invoices" => vec![
+                    Column::new_empty("id".into(), &DataType::Int32),
+                    Column::new_empty(PARTITION_COLUMN.into(), &DataType::String),
+                    Column::new_empty("client_id".into(), &DataType::Int32),
+                    Column::new_empty("client_name".into(), &DataType::String),
+                    Column::new_empty("total_price".into(), &DataType::Float64),
+                    Column::new_empty("size".into(), &DataType::Int32),
+                ],
+                _ => vec![
+                    Column::new_empty("invoice_id".into(), &DataType::Int32),
+                    Column::new_empty("line_number".into(), &DataType::Int32),
+                    Column::new_empty(PARTITION_COLUMN.into(), &DataType::String),
+                    Column::new_empty("id".into(), &DataType::Int32),
+                    Column::new_empty("name".into(), &DataType::String),
+                    Column::new_empty("price".into(), &DataType::Float64),
+                ],
+            };
+            DataFrame::new(columns)?.lazy()
+        } else {
+            concat_lf_diagonal(frames, UnionArgs::default())?
+        };
+
+        let schema = frame.collect_schema()?;
+        let mut columns = Vec::new();
+        for (name, data_type) in schema.iter() {
+            flatten(col(name.clone()), name, data_type, &mut columns);
+        }
+        // Invoices written before they had a currency are in euros, which the reports default to
+        if table == "invoices" && !schema.contains("currency") {
+            columns.push(lit(NULL).cast(DataType::String).alias("currency"));
+        }
+        self.context.register(table, frame.select(columns));
+        Ok(())
+    }
+
+    /// Runs the query and returns the column names and the rows.
+    pub fn query(&mut self, sql: &str) -> PolarsResult<(Vec<String>, Vec<Vec<Value>>)> {
+        let result = self.context.execute(sql)?.collect()?;
+        let columns = result.get_column_names().into_iter().map(|name| name.to_string()).collect();
+        let rows = (0..result.height())
+            .map(|index| {
+                result
+                    .get_columns()
+                    .iter()
+                    .map(|column| column.get(index).map(|value| json_value(&value)))
+                    .collect()
+            })
+            .collect::<PolarsResult<_>>()?;
+        Ok((columns, rows))
+    }
+}
+
+async fn read_parquet(store: &Arc<dyn ObjectStore>, path: &Path) -> Result<LazyFrame, Box<dyn std::error::Error>> {
+    let bytes = store.get(path).await?.bytes().await?;
+    Ok(ParquetReader::new(Cursor::new(bytes.to_vec())).finish()?.lazy())
+}
+
+/// One row per line of the invoices of a Hive file, like the lines table of the Iceberg layout.
+fn line_rows(invoices: LazyFrame) -> LazyFrame {
+    invoices
+        .select([
+            col("id").alias("invoice_id"),
+            int_ranges(lit(0), col(LINES_COLUMN).list().len(), lit(1)).alias("line_number"),
+            col(PARTITION_COLUMN),
+            col(LINES_COLUMN),
+        ])
+        .explode(["line_number", LINES_COLUMN])
+        .filter(col(LINES_COLUMN).is_not_null())
+        .with_column(col("line_number").cast(DataType::Int32))
+        .unnest([LINES_COLUMN])
+}
+
+fn flatten(expr: Expr, name: &str, data_type: &DataType, columns: &mut Vec<Expr>) {
+    match data_type {
+        DataType::Struct(fields) => {
+            for field in fields {
+                let child = format!("{}_{}", name, field.name());
+                flatten(expr.clone().struct_().field_by_name(field.name()), &child, field.dtype(), columns);
+            }
+        }
+        DataType::List(_) => (),
+        _ => columns.push(expr.alias(name)),
+    }
+}
+
+fn json_value(value: &AnyValue) -> Value {
+    match value {
+        AnyValue::Null => Value::Null,
+        AnyValue::Boolean(boolean) => Value::from(*boolean),
+        AnyValue::String(text) => Value::from(*text),
+        AnyValue::StringOwned(text) => Value::from(text.as_str()),
+        AnyValue::Float32(real) => Value::from(*real),
+        AnyValue::Float64(real) => Value::from(*real),
+        value if value.is_integer() => value.extract::<i64>().map(Value::from).unwrap_or_default(),
+        value => Value::from(value.to_string()),
+    }
+}
+
+fn text_value(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        value => value.to_string(),
+    }
+}
+
+pub fn print(columns: &[String], rows: &[Vec<Value>], format: OutputFormat) -> Result<(), Box<dyn std::error::Error>> {
+    match format {
+        OutputFormat::Table => {
+            let mut table = Table::new();
+            table.set_header(columns);
+            for row in rows {
+                table.add_row(row.iter().map(text_value));
+            }
+            println!("{}", table);
+        }
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(std::io::stdout());
+            writer.write_record(columns)?;
+            for row in rows {
+                writer.write_record(row.iter().map(text_value))?;
+            }
+            writer.flush()?;
+        }
+        OutputFormat::Json => {
+            let objects: Vec<Map<String, Value>> =
+                rows.iter().map(|row| columns.iter().cloned().zip(row.iter().cloned()).collect()).collect();
+            println!("{}", serde_json::to_string_pretty(&objects)?);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use common::invoice::Invoice;
+    use object_store::memory::InMemory;
+    use serde_avro_derive::BuildSchema;
+    use serde_json::json;
+
+    use super::*;
+    use crate::schema::{arrow_schema, invoices_schema, lines_schema};
+    use crate::writer::LakeWriter;
+
+    const LAKE_URL: &str = "memory://lake";
+
+    fn invoice(id: i32, date: &str, client: i32, currency: &str, prices: &[f64]) -> Invoice {
+        let products: Vec<Value> = prices
+            .iter()
+            .enumerate()
+            .map(|(index, price)| {
+                json!({"id": index, "name": format!("Product {}", index), "price": price, "command_id": id})
+            })
+            .collect();
+        serde_json::from_value(json!({
+            "id": id,
+            "date": date,
+            "client": {"id": client, "name": format!("Client {}", client), "email": "", "address": ""},
+            "products": products,
+            "total_price": prices.iter().sum::<f64>(),
+            "size": prices.len(),
+            "currency": currency,
+        }))
+        .unwrap()
+    }
+
+    fn invoices() -> Vec<Invoice> {
+        vec![
+            invoice(1, "2025-01-01", 7, "EUR", &[10.0, 5.5]),
+            invoice(2, "2025-01-01", 8, "USD", &[3.0]),
+            invoice(3, "2025-01-02", 7, "EUR", &[10.0, 10.0, 1.25]),
+        ]
+    }
+
+    async fn lake(table_format: TableFormat) -> ReportDatabase {
+        let store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let root = Path::from("");
+        let schema = arrow_schema(Invoice::schema().unwrap().json()).unwrap();
+        match table_format {
+            TableFormat::Hive => {
+                let positions: Vec<_> = (0..invoices().len() as i64).map(|offset| (0, offset)).collect();
+                LakeWriter::new(Arc::clone(&store), root.child("invoices"), &schema)
+                    .write(&invoices(), &positions)
+                    .await
+                    .unwrap();
+            }
+            TableFormat::Iceberg => {
+                let rows: Vec<Value> =
+                    invoices().iter().map(|invoice| serde_json::to_value(invoice).unwrap()).collect();
+                let lines: Vec<Value> = rows.iter().flat_map(crate::schema::line_rows).collect();
+                for (table, schema, rows) in [
+                    ("invoices", invoices_schema(&schema), rows),
+                    ("invoice_lines", lines_schema(&schema).unwrap(), lines),
+                ] {
+                    let location = format!("{}/iceberg/{}", LAKE_URL, table);
+                    let positions: Vec<_> = (0..rows.len() as i64).map(|offset| (0, offset)).collect();
+                    IcebergTable::new(Arc::clone(&store), root.child("iceberg").child(table), location)
+                        .append(&schema, &rows, &positions)
+                        .await
+                        .unwrap();
+                }
+            }
+        }
+        let mut database = ReportDatabase::new();
+        database.load_lake(&store, &root, LAKE_URL, table_format).await.unwrap();
+        database
+    }
+
+    fn run(database: &mut ReportDatabase, report: Report) -> Vec<Value> {
+        let (columns, rows) = database.query(report.sql()).unwrap();
+        rows.into_iter()
+            .map(|row| Value::Object(columns.iter().cloned().zip(row).collect()))
+            .collect()
+    }
+
+    async fn check_reports(table_format: TableFormat) {
+        let mut database = lake(table_format).await;
+
+        assert_eq!(
+            run(&mut database, Report::DailyRevenue),
+            vec![
+                json!({"date": "2025-01-01", "currency": "EUR", "invoices": 1, "revenue": 15.5}),
+                json!({"date": "2025-01-01", "currency": "USD", "invoices": 1, "revenue": 3.0}),
+                json!({"date": "2025-01-02", "currency": "EUR", "invoices": 1, "revenue": 21.25}),
+            ]
+        );
+        assert_eq!(
+            run(&mut database, Report::TopProducts),
+            vec![
+                json!({"product_id": 0, "name": "Product 0", "units": 3, "revenue": 23.0}),
+                json!({"product_id": 1, "name": "Product 1", "units": 2, "revenue": 15.5}),
+                json!({"product_id": 2, "name": "Product 2", "units": 1, "revenue": 1.25}),
+            ]
+        );
+        assert_eq!(
+            run(&mut database, Report::AverageBasket),
+            vec![
+                json!({"currency": "EUR", "invoices": 2, "average_products": 2.5, "average_amount": 18.38}),
+                json!({"currency": "USD", "invoices": 1, "average_products": 1.0, "average_amount": 3.0}),
+            ]
+        );
+        assert_eq!(
+            run(&mut database, Report::RevenuePerClient),
+            vec![
+                json!({"client_id": 7, "client_name": "Client 7", "currency": "EUR", "invoices": 2, "revenue": 36.75}),
+                json!({"client_id": 8, "client_name": "Client 8", "currency": "USD", "invoices": 1, "revenue": 3.0}),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_over_iceberg_tables() {
+        check_reports(TableFormat::Iceberg).await;
+    }
+
+    #[tokio::test]
+    async fn reports_over_hive_files() {
+        check_reports(TableFormat::Hive).await;
+    }
+
+    #[tokio::test]
+    async fn reports_over_an_empty_lake() {
+        let store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        for table_format in [TableFormat::Iceberg, TableFormat::Hive] {
+            let mut database = ReportDatabase::new();
+            database.load_lake(&store, &Path::from(""), LAKE_URL, table_format).await.unwrap();
+            for report in [Report::DailyRevenue, Report::TopProducts, Report::AverageBasket, Report::RevenuePerClient] {
+                assert_eq!(run(&mut database, report), Vec::<Value>::new());
+            }
+        }
+    }
+
+    #[test]
+    fn invoices_without_a_currency_are_reported_in_euros() {
+        let mut database = ReportDatabase::new();
+        let invoices = df!("id" => [1], "date" => ["2025-01-01"], "total_price" => [2.5], "size" => [1]).unwrap();
+        database.register("invoices", vec![invoices.lazy()]).unwrap();
+        database.register("invoice_lines", vec![]).unwrap();
+
+        let daily = run(&mut database, Report::DailyRevenue);
+        assert_eq!(daily, vec![json!({"date": "2025-01-01", "currency": "EUR", "invoices": 1, "revenue": 2.5})]);
+    }
+}
\ No newline at end of file
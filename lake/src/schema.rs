@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use arrow::datatypes::{DataType, Field, Fields, Schema};
+use arrow::error::ArrowError;
+use serde_json::Value;
+
+/// Column the files are partitioned by. It is encoded in the object path, not in the files.
+pub const PARTITION_COLUMN: &str = "date";
+
+/// Maps an Avro record schema, as registered in the schema registry, onto an Arrow schema.
+pub fn arrow_schema(avro_schema: &str) -> Result<Schema, ArrowError> {
+    let avro: Value = serde_json::from_str(avro_schema).map_err(|e| ArrowError::JsonError(e.to_string()))?;
+    let mut named = HashMap::new();
+    match convert(&avro, &mut named)? {
+        (DataType::Struct(fields), _) => Ok(Schema::new(fields)),
+        (data_type, _) => Err(ArrowError::SchemaError(format!(
+            "Expected an Avro record at the top level, found {}",
+            data_type
+        ))),
+    }
+}
+
+/// The schema of the Parquet files: every column but the partition one.
+pub fn file_schema(schema: &Schema) -> Schema {
+    let fields: Vec<Arc<Field>> = schema
+        .fields()
+        .iter()
+        .filter(|field| field.name() != PARTITION_COLUMN)
+        .cloned()
+        .collect();
+    Schema::new(fields)
+}
+
+/// Returns the Arrow type of an Avro type and whether it is nullable.
+fn convert(avro: &Value, named: &mut HashMap<String, DataType>) -> Result<(DataType, bool), ArrowError> {
+    match avro {
+        Value::String(name) => primitive(name)
+            .or_else(|| named.get(name).cloned())
+            .map(|data_type| (data_type, false))
+            .ok_or_else(|| ArrowError::SchemaError(format!("Unknown Avro type {}", name))),
+        Value::Array(variants) => {
+            let nullable = variants.iter().any(|variant| variant == "null");
+            let mut types = variants.iter().filter(|variant| *variant != "null");
+            match (types.next(), types.next()) {
+                (Some(variant), None) => Ok((convert(variant, named)?.0, nullable)),
+                _ => Err(ArrowError::SchemaError(format!("Unsupported Avro union {}", avro))),
+            }
+        }
+        Value::Object(object) => {
+            let kind = object.get("type").and_then(Value::as_str).unwrap_or_default();
+            match kind {
+                "record" => {
+                    let mut fields = Vec::new();
+                    for field in object.get("fields").and_then(Value::as_array).into_iter().flatten() {
+                        let name = field.get("name").and_then(Value::as_str).unwrap_or_default();
+                        let field_type = field.get("type").unwrap_or(&Value::Null);
+                        let (data_type, nullable) = convert(field_type, named)?;
+                        fields.push(Field::new(name, data_type, nullable));
+                    }
+                    let data_type = DataType::Struct(Fields::from(fields));
+                    if let Some(name) = object.get("name").and_then(Value::as_str) {
+                        named.insert(name.to_string(), data_type.clone());
+                    }
+                    Ok((data_type, false))
+                }
+                "array" => {
+                    let items = object.get("items").unwrap_or(&Value::Null);
+                    let (data_type, nullable) = convert(items, named)?;
+                    Ok((DataType::List(Arc::new(Field::new("item", data_type, nullable))), false))
+                }
+                "enum" => Ok((DataType::Utf8, false)),
+                _ => convert(&Value::String(kind.to_string()), named),
+            }
+        }
+        _ => Err(ArrowError::SchemaError(format!("Unsupported Avro type {}", avro))),
+    }
+}
+
+fn primitive(name: &str) -> Option<DataType> {
+    match name {
+        "boolean" => Some(DataType::Boolean),
+        "int" => Some(DataType::Int32),
+        "long" => Some(DataType::Int64),
+        "float" => Some(DataType::Float32),
+        "double" => Some(DataType::Float64),
+        "string" => Some(DataType::Utf8),
+        "bytes" => Some(DataType::Binary),
+        _ => None,
+    }
+}
+
+/// Invoice field holding the lines, which are stored in their own table by the Iceberg writer.
+pub const LINES_COLUMN: &str = "products";
+
+/// The invoice columns without the nested lines.
+pub fn invoices_schema(schema: &Schema) -> Schema {
+    let fields: Vec<Arc<Field>> = schema
+        .fields()
+        .iter()
+        .filter(|field| field.name() != LINES_COLUMN)
+        .cloned()
+        .collect();
+    Schema::new(fields)
+}
+
+/// One row per line: the invoice id, the line number and the date of the invoice, followed by
+/// the fields of the line record.
+pub fn lines_schema(schema: &Schema) -> Result<Schema, ArrowError> {
+    let line_fields = match schema.field_with_name(LINES_COLUMN)?.data_type() {
+        DataType::List(item) => match item.data_type() {
+            DataType::Struct(fields) => fields.clone(),
+            data_type => {
+                return Err(ArrowError::SchemaError(format!("Expected records of lines, found {}", data_type)))
+            }
+        },
+        data_type => return Err(ArrowError::SchemaError(format!("Expected a list of lines, found {}", data_type))),
+    };
+    let mut fields = vec![
+        Arc::new(Field::new("invoice_id", DataType::Int32, false)),
+        Arc::new(Field::new("line_number", DataType::Int32, false)),
+        Arc::new(Field::new(PARTITION_COLUMN, DataType::Utf8, false)),
+    ];
+    fields.extend(line_fields.iter().cloned());
+    Ok(Schema::new(fields))
+}
+
+/// Flattens the lines of the invoice into rows matching `lines_schema`.
+pub fn line_rows(invoice: &Value) -> Vec<Value> {
+    let lines = invoice.get(LINES_COLUMN).and_then(Value::as_array).cloned().unwrap_or_default();
+    lines
+        .into_iter()
+        .enumerate()
+        .map(|(line_number, mut line)| {
+            if let Value::Object(line) = &mut line {
+                line.insert(String::from("invoice_id"), invoice["id"].clone());
+                line.insert(String::from("line_number"), Value::from(line_number));
+                line.insert(String::from(PARTITION_COLUMN), invoice[PARTITION_COLUMN].clone());
+            }
+            line
+        })
+        .collect()
+}
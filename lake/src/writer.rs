@@ -0,0 +1,139 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use arrow::datatypes::{Schema, SchemaRef};
+use arrow::json::reader::ReaderBuilder;
+use arrow::record_batch::RecordBatch;
+use common::invoice::Invoice;
+use object_store::path::Path;
+use object_store::{ObjectStore, PutPayload};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+
+use crate::iceberg::Position;
+use crate::schema::{file_schema, PARTITION_COLUMN};
+
+/// Writes batches of invoices as Parquet files, one per `date` partition and Kafka partition, into
+/// an object store.
+pub struct LakeWriter {
+    store: Arc<dyn ObjectStore>,
+    table_path: Path,
+    file_schema: SchemaRef,
+}
+
+impl LakeWriter {
+    pub fn new(store: Arc<dyn ObjectStore>, table_path: Path, schema: &Schema) -> Self {
+        LakeWriter {
+            store,
+            table_path,
+            file_schema: Arc::new(file_schema(schema)),
+        }
+    }
+
+    /// Writes the invoices, read from `positions`, and returns the path of every file created.
+    ///
+    /// A file is named after the Kafka partition and the offset of its first invoice. Invoices
+    /// consumed again after a crash start from the same offset, so their files overwrite the ones
+    /// written before the crash, whatever the size of the replayed batch.
+    pub async fn write(
+        &self,
+        invoices: &[Invoice],
+        positions: &[Position],
+    ) -> Result<Vec<Path>, Box<dyn std::error::Error>> {
+        let mut partitions: BTreeMap<(&str, i32), (i64, Vec<&Invoice>)> = BTreeMap::new();
+        for (invoice, (partition, offset)) in invoices.iter().zip(positions) {
+            partitions
+                .entry((invoice.date.as_str(), *partition))
+                .or_insert_with(|| (*offset, Vec::new()))
+                .1
+                .push(invoice);
+        }
+
+        let mut paths = Vec::new();
+        for ((date, partition), (first_offset, invoices)) in partitions {
+            let batch = self.record_batch(&invoices)?;
+            let path = self
+                .table_path
+                .child(format!("{}={}", PARTITION_COLUMN, date))
+                .child(format!("part-{}-{}.parquet", partition, first_offset));
+            self.store.put(&path, PutPayload::from(encode(&batch)?)).await?;
+            println!("Wrote {} invoices to {}", invoices.len(), path);
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+
+    pub fn record_batch(&self, invoices: &[&Invoice]) -> Result<RecordBatch, Box<dyn std::error::Error>> {
+        // Fields missing from the file schema, like the partition column, are skipped
+        let mut decoder = ReaderBuilder::new(Arc::clone(&self.file_schema)).build_decoder()?;
+        decoder.serialize(invoices)?;
+        Ok(decoder
+            .flush()?
+            .unwrap_or_else(|| RecordBatch::new_empty(Arc::clone(&self.file_schema))))
+    }
+}
+
+pub fn encode(batch: &RecordBatch) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut buffer = Vec::new();
+    let mut writer = ArrowWriter::try_new(&mut buffer, batch.schema(), Some(properties))?;
+    writer.write(batch)?;
+    writer.close()?;
+    Ok(buffer)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use futures::TryStreamExt;
+    use object_store::memory::InMemory;
+    use serde_avro_derive::BuildSchema;
+    use serde_json::json;
+
+    use super::*;
+    use crate::schema::arrow_schema;
+
+    fn invoice(id: i32, date: &str) -> Invoice {
+        serde_json::from_value(json!({
+            "id": id,
+            "date": date,
+            "client": {"id": 1, "name": "Client 1", "email": "", "address": ""},
+            "products": [],
+            "total_price": 0.0,
+            "size": 0,
+        }))
+        .unwrap()
+    }
+
+    async fn files(store: &Arc<dyn ObjectStore>) -> Vec<String> {
+        let objects: Vec<_> = store.list(None).try_collect().await.unwrap();
+        let mut files: Vec<String> = objects.into_iter().map(|object| object.location.to_string()).collect();
+        files.sort();
+        files
+    }
+
+    #[tokio::test]
+    async fn replayed_invoices_overwrite_their_files() {
+        let store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let schema = arrow_schema(Invoice::schema().unwrap().json()).unwrap();
+        let writer = LakeWriter::new(Arc::clone(&store), Path::from("invoices"), &schema);
+        let invoices = vec![invoice(1, "2025-01-01"), invoice(2, "2025-01-02"), invoice(3, "2025-01-01")];
+        let positions = vec![(0, 100), (0, 101), (1, 40)];
+
+        writer.write(&invoices[..2], &positions[..2]).await.unwrap();
+        // Consumed again after a crash, with one more invoice in the batch
+        writer.write(&invoices, &positions).await.unwrap();
+
+        assert_eq!(
+            files(&store).await,
+            [
+                "invoices/date=2025-01-01/part-0-100.parquet",
+                "invoices/date=2025-01-01/part-1-40.parquet",
+                "invoices/date=2025-01-02/part-0-101.parquet",
+            ]
+        );
+    }
+}
\ No newline at end of file
@@ -1,7 +1,135 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use common::client::Client;
+use common::command::Command;
 
-pub fn process_client(client: &Client, clients: &mut HashMap<i32, Client>) {
-    clients.insert(client.id, client.clone());
+/// Number of versions of a client kept to build the invoices of orders placed before an update.
+const MAX_VERSIONS: usize = 16;
+
+/// The known versions of each client, by client id then version.
+pub type ClientVersions = HashMap<i32, BTreeMap<i32, Client>>;
+
+/// Stores a version of a client. The latest version wins, whatever the order of arrival: a
+/// version older than the latest one only completes the history.
+pub fn process_client(client: &Client, clients: &mut ClientVersions) {
+    let versions = clients.entry(client.id).or_default();
+    if let Some((latest, _)) = versions.last_key_value() {
+        if client.version < *latest {
+            println!("Client {} version {} arrived after version {}", client.id, client.version, latest);
+        }
+    }
+    versions.insert(client.version, client.clone());
+    while versions.len() > MAX_VERSIONS {
+        versions.pop_first();
+    }
+}
+
+/// The client as it was at `version`, or the latest version if `version` is 0. Returns `None` if
+/// this version hasn't arrived yet.
+pub fn client_at(clients: &ClientVersions, id: i32, version: i32) -> Option<Client> {
+    let versions = clients.get(&id)?;
+    if version == 0 {
+        return versions.last_key_value().map(|(_, client)| client.clone());
+    }
+    match versions.get(&version) {
+        Some(client) => Some(client.clone()),
+        // Older than the kept history: the oldest kept version is the closest
+        None => versions
+            .first_key_value()
+            .filter(|(oldest, _)| **oldest > version && versions.len() == MAX_VERSIONS)
+            .map(|(_, client)| client.clone()),
+    }
+}
+
+/// The client of a command as it was when the order was placed: the snapshot the command carries,
+/// or for older commands without one, the version it names in the history of the `Client` topic.
+pub fn client_of(command: &Command, clients: &ClientVersions) -> Option<Client> {
+    match &command.client_snapshot {
+        Some(client) => Some(client.clone()),
+        None => client_at(clients, command.client_id, command.client_version),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(id: i32, version: i32) -> Client {
+        Client {
+            id,
+            name: format!("Client {}", id),
+            email: format!("client{}@example.com", id),
+            address: format!("{} rue de Rivoli", version),
+            version,
+            updated_at: String::new(),
+            currency: String::from("EUR"),
+        }
+    }
+
+    fn versions(clients: &ClientVersions, id: i32) -> Vec<i32> {
+        clients[&id].keys().copied().collect()
+    }
+
+    #[test]
+    fn late_versions_complete_the_history_without_replacing_the_latest() {
+        let mut clients = ClientVersions::new();
+        for version in [1, 3, 2] {
+            process_client(&client(7, version), &mut clients);
+        }
+        assert_eq!(versions(&clients, 7), [1, 2, 3]);
+        assert_eq!(client_at(&clients, 7, 0).unwrap().version, 3);
+        assert_eq!(client_at(&clients, 7, 2).unwrap().address, "2 rue de Rivoli");
+
+        // The same version again is an update of the row, not a new version
+        let mut replayed = client(7, 2);
+        replayed.name = String::from("Replayed");
+        process_client(&replayed, &mut clients);
+        assert_eq!(versions(&clients, 7), [1, 2, 3]);
+        assert_eq!(client_at(&clients, 7, 2).unwrap().name, "Replayed");
+    }
+
+    #[test]
+    fn missing_versions_are_not_found() {
+        let mut clients = ClientVersions::new();
+        process_client(&client(7, 2), &mut clients);
+        assert!(client_at(&clients, 8, 0).is_none());
+        // Not arrived yet
+        assert!(client_at(&clients, 7, 3).is_none());
+        // Older than the history, which isn't full: it may still arrive
+        assert!(client_at(&clients, 7, 1).is_none());
+    }
+
+    #[test]
+    fn only_the_last_versions_are_kept() {
+        let mut clients = ClientVersions::new();
+        for version in 1..=MAX_VERSIONS as i32 + 4 {
+            process_client(&client(7, version), &mut clients);
+        }
+        assert_eq!(clients[&7].len(), MAX_VERSIONS);
+        assert_eq!(*clients[&7].first_key_value().unwrap().0, 5);
+        // Older than the full history: the oldest kept version is the closest
+        assert_eq!(client_at(&clients, 7, 2).unwrap().version, 5);
+    }
+
+    #[test]
+    fn the_snapshot_of_a_command_wins_over_the_history() {
+        let mut clients = ClientVersions::new();
+        process_client(&client(7, 4), &mut clients);
+        let mut command = Command {
+            id: 42,
+            client_id: 7,
+            date: String::from("2025-01-20"),
+            size: 1,
+            client_version: 2,
+            client_snapshot: None,
+        };
+        // Without a snapshot, version 2 must be in the history
+        assert!(client_of(&command, &clients).is_none());
+
+        // A snapshot needs no history, after a restart or a compaction of the topic
+        command.client_snapshot = Some(client(7, 2));
+        assert_eq!(client_of(&command, &ClientVersions::new()).unwrap().version, 2);
+        assert_eq!(client_of(&command, &clients).unwrap().address, "2 rue de Rivoli");
+    }
 }
\ No newline at end of file
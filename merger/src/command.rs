@@ -1,24 +1,32 @@
-use std::{collections::HashMap, sync::Arc};
-use common::{client::Client, command::Command, invoice::Invoice};
+use std::sync::Arc;
+use std::time::Instant;
+use common::{command::Command, invoice::Invoice};
 use rdkafka::producer::FutureProducer;
+use sqlx::PgPool;
 use tokio::sync::Mutex;
 use backon::{ExponentialBuilder, Retryable};
+use crate::client::{client_of, ClientVersions};
+use crate::pending::PendingInvoices;
+use crate::product::settle;
 use crate::send_to_dlq;
 
 pub async fn process_command(
     producer: Arc<FutureProducer>,
+    pool: PgPool,
     command: Command,
-    invoices: Arc<Mutex<HashMap<i32, Invoice>>>,
-    clients: Arc<Mutex<HashMap<i32, Client>>>,
+    invoices: Arc<Mutex<PendingInvoices>>,
+    clients: Arc<Mutex<ClientVersions>>,
 ) {
     // Spawn a new task to process the command
     tokio::spawn(async move {
         let retry_result = (|| async {
             let clients_lock = clients.lock().await;
-            clients_lock.get(&command.client_id).cloned().ok_or_else(|| {
+            // The client as it was when the order was placed, not as updated since
+            client_of(&command, &clients_lock).ok_or_else(|| {
                 anyhow::anyhow!(
-                    "Client with ID {} not found",
-                    command.client_id
+                    "Client with ID {} version {} not found",
+                    command.client_id,
+                    command.client_version
                 )
             })
         })
@@ -34,16 +42,22 @@ pub async fn process_command(
 
         match retry_result {
             Ok(client) => {
-                // Client found, create the invoice and insert into shared map
+                // Client found, create the invoice with the products that came first
                 let mut invoice = Invoice::from(command.clone());
+                // Invoiced in the currency the client had when ordering
+                invoice.currency = client.currency.clone();
                 invoice.client = client;
 
-                let mut invoices_lock = invoices.lock().await;
-                invoices_lock.insert(invoice.id, invoice);
+                let progress = invoices.lock().await.command(invoice, Instant::now());
+                settle(producer, pool, progress).await;
             }
             Err(_) => {
-                // Client not found after retries, send the command to the DLQ
+                // Client not found after retries, send the command and its products to the DLQ
+                let products = invoices.lock().await.abandon(command.id);
                 send_to_dlq(&producer, command.id, command).await;
+                for product in products {
+                    send_to_dlq(&producer, product.command_id, product).await;
+                }
             }
         }
     });
@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use common::currency::{convert, rate_on};
+use common::invoice::Invoice;
+use common::vat::round_cents;
+use sqlx::PgPool;
+
+/// Rate from the base currency of each product of the invoice into its currency, valid on the
+/// order date.
+pub async fn rates(pool: &PgPool, invoice: &Invoice) -> anyhow::Result<HashMap<String, f64>> {
+    let mut rates = HashMap::new();
+    for product in &invoice.products {
+        if rates.contains_key(&product.currency) {
+            continue;
+        }
+        let rate = rate_on(pool, &product.currency, &invoice.currency, &invoice.date)
+            .await?
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "No exchange rate from {} to {} on {}",
+                    product.currency,
+                    invoice.currency,
+                    invoice.date
+                )
+            })?;
+        rates.insert(product.currency.clone(), rate);
+    }
+    Ok(rates)
+}
+
+/// Converts the price of each product into the invoice currency, records the rate it applied and
+/// totals the converted prices.
+pub fn apply(invoice: &mut Invoice, rates: &HashMap<String, f64>) {
+    for product in invoice.products.iter_mut() {
+        let rate = rates.get(&product.currency).copied().unwrap_or(1.0);
+        product.price = convert(product.price, rate);
+        product.exchange_rate = rate;
+    }
+    invoice.total_price = round_cents(invoice.products.iter().map(|product| product.price).sum());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::client::Client;
+    use common::product::Product;
+
+    fn unit(id: i32, price: f64, currency: &str) -> Product {
+        Product {
+            id,
+            name: format!("Product {}", id),
+            price,
+            command_id: 42,
+            backordered: false,
+            currency: String::from(currency),
+            exchange_rate: 1.0,
+            unit: None,
+        }
+    }
+
+    fn invoice(products: Vec<Product>) -> Invoice {
+        Invoice {
+            id: 42,
+            number: String::new(),
+            date: String::from("2026-03-02"),
+            client: Client::default(),
+            total_price: products.iter().map(|product| product.price).sum(),
+            size: products.len() as i32,
+            products,
+            currency: String::from("USD"),
+        }
+    }
+
+    #[test]
+    fn converts_each_unit_with_the_rate_of_its_currency() {
+        let mut invoice = invoice(vec![unit(1, 10.0, "EUR"), unit(2, 20.0, "GBP"), unit(1, 10.0, "EUR")]);
+        let rates = HashMap::from([(String::from("EUR"), 1.17), (String::from("GBP"), 1.34)]);
+        apply(&mut invoice, &rates);
+
+        let prices: Vec<f64> = invoice.products.iter().map(|product| product.price).collect();
+        assert_eq!(prices, vec![11.7, 26.8, 11.7]);
+        let applied: Vec<f64> = invoice.products.iter().map(|product| product.exchange_rate).collect();
+        assert_eq!(applied, vec![1.17, 1.34, 1.17]);
+        // The base currency of each unit is kept
+        assert_eq!(invoice.products[1].currency, "GBP");
+        assert_eq!(invoice.total_price, 50.2);
+    }
+
+    #[test]
+    fn totals_the_rounded_prices() {
+        // 3 × 9.99 / 1.07 is 28.009..., the rounded prices add up to 28.02
+        let mut invoice = invoice(vec![unit(1, 9.99, "EUR"), unit(1, 9.99, "EUR"), unit(1, 9.99, "EUR")]);
+        let rates = HashMap::from([(String::from("EUR"), 1.0 / 1.07)]);
+        apply(&mut invoice, &rates);
+
+        assert!(invoice.products.iter().all(|product| product.price == 9.34));
+        assert_eq!(invoice.total_price, 28.02);
+    }
+
+    #[test]
+    fn keeps_the_price_without_a_rate() {
+        let mut invoice = invoice(vec![unit(1, 12.5, "USD")]);
+        apply(&mut invoice, &HashMap::new());
+
+        assert_eq!(invoice.products[0].price, 12.5);
+        assert_eq!(invoice.products[0].exchange_rate, 1.0);
+        assert_eq!(invoice.total_price, 12.5);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,283 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use backon::{ExponentialBuilder, Retryable};
+use common::credit_note::CreditNote;
+use common::invoice::Invoice;
+use common::lifecycle::{Cancellation, Refund};
+use rdkafka::producer::FutureProducer;
+use serde::Serialize;
+use sqlx::{PgPool, Postgres, Transaction};
+
+use crate::{send_credit_note, send_to_dlq};
+
+/// How long an event waits for its invoice to be sent: longer than an incomplete invoice is kept
+/// and than the outbox takes to send an invoice again.
+fn until_sent() -> ExponentialBuilder {
+    ExponentialBuilder::default().with_max_delay(Duration::from_secs(60)).with_max_times(17)
+}
+
+/// A numbered invoice, with what was credited since, stored in `IssuedInvoice` by the outbox.
+#[derive(Debug)]
+pub struct Issued {
+    invoice: Invoice,
+    /// Number of credited units of each product
+    credited: HashMap<i32, usize>,
+    /// Ids of the events already credited, so that a replayed event isn't credited twice
+    events: HashSet<i32>,
+}
+
+impl Issued {
+    /// Builds the credit note of event `id` with `credit`, unless the event was credited before or
+    /// nothing is left to credit.
+    fn credit_once<F>(&mut self, id: i32, credit: F) -> Option<CreditNote>
+    where
+        F: Fn(&mut Issued) -> CreditNote,
+    {
+        if !self.events.insert(id) {
+            return None;
+        }
+        Some(credit(self)).filter(|credit_note| !credit_note.products.is_empty())
+    }
+
+    /// Credits up to `quantity` units of `product_id` not credited yet, or all of them without a
+    /// product, at their invoiced price.
+    fn credit(&mut self, credit_note: &mut CreditNote, product_id: Option<i32>, quantity: usize) {
+        let mut seen: HashMap<i32, usize> = HashMap::new();
+        let mut remaining = quantity;
+        for product in &self.invoice.products {
+            if remaining == 0 {
+                break;
+            }
+            if product_id.is_some_and(|id| id != product.id) {
+                continue;
+            }
+            // The first units of a product are the ones credited before
+            let rank = seen.entry(product.id).or_default();
+            *rank += 1;
+            let credited = self.credited.entry(product.id).or_default();
+            if *rank <= *credited {
+                continue;
+            }
+            *credited += 1;
+            credit_note.credit_product(product);
+            remaining -= 1;
+        }
+    }
+}
+
+/// Reads the issued invoice of `command_id` once sent, locked until the end of `tx`.
+async fn load(tx: &mut Transaction<'static, Postgres>, command_id: i32) -> anyhow::Result<Option<Issued>> {
+    let row: Option<(String, String, Vec<i32>)> = sqlx::query_as(
+        "SELECT invoice::text, credited::text, events FROM IssuedInvoice
+        WHERE commandId = $1 AND sentAt IS NOT NULL FOR UPDATE",
+    )
+    .bind(command_id)
+    .fetch_optional(&mut **tx)
+    .await?;
+    let Some((invoice, credited, events)) = row else {
+        return Ok(None);
+    };
+    Ok(Some(Issued {
+        invoice: serde_json::from_str(&invoice)?,
+        credited: serde_json::from_str(&credited)?,
+        events: events.into_iter().collect(),
+    }))
+}
+
+async fn save(tx: &mut Transaction<'static, Postgres>, issued: &Issued) -> anyhow::Result<()> {
+    sqlx::query("UPDATE IssuedInvoice SET credited = $2::jsonb, events = $3 WHERE commandId = $1")
+        .bind(issued.invoice.id)
+        .bind(serde_json::to_string(&issued.credited)?)
+        .bind(issued.events.iter().copied().collect::<Vec<i32>>())
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
+
+/// Emits a credit note cancelling what is left of the invoice of the cancelled command.
+pub async fn process_cancellation(
+    producer: Arc<FutureProducer>,
+    cancellation: Cancellation,
+    pool: PgPool,
+) {
+    let (id, command_id) = (cancellation.id, cancellation.command_id);
+    let event = cancellation.clone();
+    emit(producer, pool, id, command_id, event, move |issued| {
+        let mut credit_note = CreditNote::new(id, &issued.invoice, cancellation.date.clone(), cancellation.reason.clone());
+        issued.credit(&mut credit_note, None, usize::MAX);
+        credit_note
+    })
+    .await;
+}
+
+/// Emits a credit note for the refunded units of the invoice of the command.
+pub async fn process_refund(producer: Arc<FutureProducer>, refund: Refund, pool: PgPool) {
+    let (id, command_id) = (refund.id, refund.command_id);
+    let event = refund.clone();
+    emit(producer, pool, id, command_id, event, move |issued| {
+        let mut credit_note = CreditNote::new(id, &issued.invoice, refund.date.clone(), refund.reason.clone());
+        for line in &refund.lines {
+            issued.credit(&mut credit_note, Some(line.product_id), line.quantity.max(0) as usize);
+        }
+        credit_note
+    })
+    .await;
+}
+
+/// Waits for the invoice of the command to be sent, then sends the credit note built by
+/// `credit`, unless it credits nothing. The event goes to the dead letter queue if the invoice is
+/// never sent.
+///
+/// What is credited is saved once the credit note is sent, in the transaction holding the lock on
+/// the invoice: a crash in between sends the same credit note again, with the same id.
+async fn emit<T, F>(producer: Arc<FutureProducer>, pool: PgPool, id: i32, command_id: i32, event: T, credit: F)
+where
+    T: for<'de> serde::Deserialize<'de> + Serialize + Send + 'static,
+    F: Fn(&mut Issued) -> CreditNote + Send + Sync + 'static,
+{
+    // Spawn a new task, the invoice may still be waiting for its products
+    tokio::spawn(async move {
+        let retry_result = (|| async {
+            let mut tx = pool.begin().await?;
+            let mut issued = load(&mut tx, command_id)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("Invoice with command ID {} not sent", command_id))?;
+            let Some(credit_note) = issued.credit_once(id, &credit) else {
+                return Ok(None);
+            };
+            save(&mut tx, &issued).await?;
+            Ok(Some((tx, credit_note)))
+        })
+        .retry(until_sent())
+        .sleep(tokio::time::sleep)
+        .notify(|err: &anyhow::Error, dur| {
+            println!(
+                "Retrying to fetch invoice with command ID {} after {:?}: {}",
+                command_id, dur, err
+            );
+        })
+        .await;
+
+        match retry_result {
+            Ok(Some((tx, credit_note))) => {
+                send_credit_note(&producer, &credit_note).await;
+                if let Err(e) = tx.commit().await {
+                    eprintln!("Failed to save the credits of credit note {}: {}", id, e);
+                }
+            }
+            Ok(None) => println!("Credit note {} already sent or crediting nothing.", id),
+            Err(_) => send_to_dlq(&producer, command_id, event).await,
+        }
+    });
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::client::Client;
+    use common::product::Product;
+
+    fn unit(id: i32, price: f64) -> Product {
+        Product {
+            id,
+            name: format!("Product {}", id),
+            price,
+            command_id: 42,
+            backordered: false,
+            currency: String::from("EUR"),
+            exchange_rate: 1.0,
+            unit: None,
+        }
+    }
+
+    fn issued() -> Issued {
+        let products = vec![unit(1, 10.0), unit(2, 5.0), unit(1, 10.0), unit(1, 10.0)];
+        Issued {
+            invoice: Invoice {
+                id: 42,
+                number: String::from("OMS-2026-000001"),
+                date: String::from("2026-03-02"),
+                client: Client::default(),
+                total_price: products.iter().map(|product| product.price).sum(),
+                size: products.len() as i32,
+                products,
+                currency: String::from("EUR"),
+            },
+            credited: HashMap::new(),
+            events: HashSet::new(),
+        }
+    }
+
+    fn refund(issued: &mut Issued, id: i32, lines: &[(i32, usize)]) -> Option<CreditNote> {
+        issued.credit_once(id, |issued| {
+            let mut credit_note = CreditNote::new(id, &issued.invoice, String::from("2026-03-09"), String::new());
+            for &(product_id, quantity) in lines {
+                issued.credit(&mut credit_note, Some(product_id), quantity);
+            }
+            credit_note
+        })
+    }
+
+    fn cancellation(issued: &mut Issued, id: i32) -> Option<CreditNote> {
+        issued.credit_once(id, |issued| {
+            let mut credit_note = CreditNote::new(id, &issued.invoice, String::from("2026-03-10"), String::new());
+            issued.credit(&mut credit_note, None, usize::MAX);
+            credit_note
+        })
+    }
+
+    #[test]
+    fn partial_then_full_credit() {
+        let mut issued = issued();
+
+        let first = refund(&mut issued, 7, &[(1, 2)]).unwrap();
+        assert_eq!(first.size, 2);
+        assert_eq!(first.total_price, -20.0);
+        assert!(first.products.iter().all(|product| product.id == 1 && product.price == -10.0));
+
+        // More units than left: only the last unit of product 1 is credited
+        let second = refund(&mut issued, 8, &[(1, 5)]).unwrap();
+        assert_eq!(second.size, 1);
+
+        // The cancellation credits what is left, product 2
+        let rest = cancellation(&mut issued, 9).unwrap();
+        assert_eq!(rest.size, 1);
+        assert_eq!(rest.products[0].id, 2);
+        assert_eq!(rest.total_price, -5.0);
+
+        let total: f64 = [first, second, rest].iter().map(|credit_note| credit_note.total_price).sum();
+        assert_eq!(total, -issued.invoice.total_price);
+        // Nothing left to credit
+        assert!(cancellation(&mut issued, 10).is_none());
+    }
+
+    #[test]
+    fn refund_of_credited_units_credits_nothing() {
+        let mut issued = issued();
+        assert_eq!(refund(&mut issued, 7, &[(2, 1)]).unwrap().size, 1);
+        assert!(refund(&mut issued, 8, &[(2, 1)]).is_none());
+        assert!(refund(&mut issued, 9, &[(3, 1)]).is_none());
+        assert_eq!(issued.credited[&2], 1);
+    }
+
+    #[test]
+    fn replayed_events_are_credited_once() {
+        let mut issued = issued();
+        assert_eq!(refund(&mut issued, 7, &[(1, 1)]).unwrap().size, 1);
+        assert!(refund(&mut issued, 7, &[(1, 1)]).is_none());
+        assert!(cancellation(&mut issued, 7).is_none());
+        assert_eq!(issued.credited[&1], 1);
+    }
+
+    #[test]
+    fn credited_units_survive_their_json_column() {
+        let mut issued = issued();
+        refund(&mut issued, 7, &[(1, 2), (2, 1)]);
+        let json = serde_json::to_string(&issued.credited).unwrap();
+        let credited: HashMap<i32, usize> = serde_json::from_str(&json).unwrap();
+        assert_eq!(credited, issued.credited);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::health::{kafka_connected, subjects_registered, Readiness, PROBE_INTERVAL};
+use rdkafka::consumer::{Consumer, StreamConsumer};
+use rdkafka::Offset;
+use schema_registry_converter::async_impl::schema_registry::SrSettings;
+use sqlx::PgPool;
+
+pub const CHECKS: [&str; 5] = ["postgres", "schema_registry", "kafka", "partition_assignment", "state_restore"];
+
+/// High watermark of each assigned partition, by topic and partition, as it was when the partition
+/// was assigned.
+type Targets = HashMap<(String, i32), i64>;
+
+/// Periodically refreshes the readiness checks of the merger.
+pub async fn probe_loop(consumer: Arc<StreamConsumer>, pool: PgPool, sr_settings: SrSettings, readiness: Readiness) {
+    let mut targets = Targets::new();
+    loop {
+        // Invoice numbering, the outbox and the credit notes are kept in Postgres
+        let postgres = sqlx::query("SELECT 1").execute(&pool).await.is_ok();
+        readiness.set("postgres", postgres);
+
+        let registered = subjects_registered(&sr_settings, &["Invoice", "CreditNote"]).await;
+        readiness.set("schema_registry", registered);
+
+        let consumer_clone = Arc::clone(&consumer);
+        let (kafka, assigned, restored, tracked) = tokio::task::spawn_blocking(move || {
+            let kafka = kafka_connected(consumer_clone.client());
+            let assigned = track_assignment(&consumer_clone, &mut targets);
+            let restored = assigned && caught_up(&consumer_clone, &targets);
+            (kafka, assigned, restored, targets)
+        })
+        .await
+        .unwrap_or_else(|_| (false, false, false, Targets::new()));
+        targets = tracked;
+        readiness.set("kafka", kafka);
+        readiness.set("partition_assignment", assigned);
+
+        // The clients and pending invoices are rebuilt from the topics, so the state is restored
+        // once the consumer has reached, on every assigned partition, the end it had when the
+        // partition was assigned. The current end keeps moving while orders are produced.
+        if restored && !readiness.get("state_restore") {
+            println!("Merger state restored from assigned partitions");
+        }
+        readiness.set("state_restore", readiness.get("state_restore") || restored);
+
+        tokio::time::sleep(PROBE_INTERVAL).await;
+    }
+}
+
+/// Records the high watermark of the newly assigned partitions and forgets the revoked ones.
+/// Returns whether partitions are assigned.
+fn track_assignment(consumer: &StreamConsumer, targets: &mut Targets) -> bool {
+    let assignment = match consumer.assignment() {
+        Ok(assignment) => assignment,
+        Err(e) => {
+            eprintln!("Failed to fetch the partition assignment: {:?}", e);
+            return false;
+        }
+    };
+    let assigned: Vec<(String, i32)> = assignment
+        .elements()
+        .iter()
+        .map(|element| (element.topic().to_string(), element.partition()))
+        .collect();
+    targets.retain(|partition, _| assigned.contains(partition));
+    for (topic, partition) in assigned.iter() {
+        if targets.contains_key(&(topic.clone(), *partition)) {
+            continue;
+        }
+        // Retried on the next probe if the watermark can't be fetched
+        if let Ok((_, high)) = consumer.fetch_watermarks(topic, *partition, Duration::from_secs(1)) {
+            targets.insert((topic.clone(), *partition), high);
+        }
+    }
+    !assigned.is_empty()
+}
+
+fn caught_up(consumer: &StreamConsumer, targets: &Targets) -> bool {
+    let positions = match consumer.position() {
+        Ok(positions) => positions,
+        Err(e) => {
+            eprintln!("Failed to fetch consumer positions: {:?}", e);
+            return false;
+        }
+    };
+    positions.elements().iter().all(|element| {
+        let Some(high) = targets.get(&(element.topic().to_string(), element.partition())).copied() else {
+            return false;
+        };
+        match element.offset() {
+            Offset::Offset(offset) => offset >= high,
+            // Nothing consumed yet, which is only caught up for a partition that was empty.
+            _ => high == 0,
+        }
+    })
+}
\ No newline at end of file
@@ -0,0 +1,93 @@
+use chrono::{Datelike, NaiveDate};
+use common::invoice::Invoice;
+use sqlx::PgPool;
+
+use crate::outbox;
+
+/// Prefix of the legal invoice numbers.
+const PREFIX: &str = "OMS";
+
+/// Formats the legal number of the `sequence`th invoice of `fiscal_year`, such as
+/// `OMS-2026-000123`.
+pub fn legal_number(fiscal_year: i32, sequence: i32) -> String {
+    format!("{}-{}-{:06}", PREFIX, fiscal_year, sequence)
+}
+
+/// The fiscal year is the calendar year of the invoice date, `YYYY-MM-DD`. `None` if the date
+/// isn't one: the invoice can't be numbered.
+pub fn fiscal_year(date: &str) -> Option<i32> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok().map(|date| date.year())
+}
+
+/// Returns the legal number of the invoice, handing out the next number of `fiscal_year` the
+/// first time.
+///
+/// The counter of the year stays locked until the transaction ends, so the merger instances take
+/// turns and every number is used exactly once: when the invoice was already numbered, by a
+/// replay or another instance, the increment is rolled back. A new number is recorded with its
+/// invoice in the outbox, which sends it again if the merger stops before sending it.
+pub async fn assign(pool: &PgPool, invoice: &Invoice, fiscal_year: i32) -> Result<String, sqlx::Error> {
+    let command_id = invoice.id;
+    let mut tx = pool.begin().await?;
+
+    let sequence: i32 = sqlx::query_scalar(
+        "INSERT INTO InvoiceSequence (fiscalYear, lastNumber) VALUES ($1, 1)
+        ON CONFLICT (fiscalYear) DO UPDATE SET lastNumber = InvoiceSequence.lastNumber + 1
+        RETURNING lastNumber",
+    )
+    .bind(fiscal_year)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let assigned: Option<String> = sqlx::query_scalar("SELECT number FROM InvoiceNumber WHERE commandId = $1")
+        .bind(command_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    if let Some(number) = assigned {
+        tx.rollback().await?;
+        return Ok(number);
+    }
+
+    let number = legal_number(fiscal_year, sequence);
+    sqlx::query("INSERT INTO InvoiceNumber (commandId, fiscalYear, sequence, number) VALUES ($1, $2, $3, $4)")
+        .bind(command_id)
+        .bind(fiscal_year)
+        .bind(sequence)
+        .bind(&number)
+        .execute(&mut *tx)
+        .await?;
+    let numbered = Invoice {
+        number: number.clone(),
+        ..invoice.clone()
+    };
+    outbox::record(&mut tx, &numbered).await?;
+    tx.commit().await?;
+    Ok(number)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legal_numbers_are_padded() {
+        assert_eq!(legal_number(2026, 123), "OMS-2026-000123");
+        assert_eq!(legal_number(2026, 1), "OMS-2026-000001");
+        assert_eq!(legal_number(2027, 1_234_567), "OMS-2027-1234567");
+    }
+
+    #[test]
+    fn fiscal_year_of_the_invoice_date() {
+        assert_eq!(fiscal_year("2026-01-01"), Some(2026));
+        assert_eq!(fiscal_year("2025-12-31"), Some(2025));
+        assert_eq!(fiscal_year("2024-02-29"), Some(2024));
+    }
+
+    #[test]
+    fn invalid_dates_have_no_fiscal_year() {
+        for date in ["", "2026", "2026-13-01", "2025-02-29", "20260101", "2026-01-01T10:00:00Z", "abcd-01-01"] {
+            assert_eq!(fiscal_year(date), None, "{}", date);
+        }
+    }
+}
\ No newline at end of file
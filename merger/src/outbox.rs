@@ -0,0 +1,86 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::invoice::Invoice;
+use rdkafka::producer::FutureProducer;
+use sqlx::{PgConnection, PgPool};
+
+use crate::send_invoice;
+
+/// Time between two sweeps of the invoices numbered but not sent.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Seconds after its numbering an invoice not sent yet is sent again, so that the invoices being
+/// sent by their task aren't sent twice.
+const SEND_GRACE_SECS: f64 = 60.0;
+
+/// Records the numbered invoice to send, within the numbering transaction, so that a number is
+/// never handed out without its invoice. An invoice recorded before keeps what was credited since.
+pub async fn record(conn: &mut PgConnection, invoice: &Invoice) -> Result<(), sqlx::Error> {
+    let json = serde_json::to_string(invoice).map_err(|e| sqlx::Error::Encode(e.into()))?;
+    sqlx::query(
+        "INSERT INTO IssuedInvoice (commandId, invoice) VALUES ($1, $2::jsonb) ON CONFLICT (commandId) DO NOTHING",
+    )
+    .bind(invoice.id)
+    .bind(json)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Marks the invoice of `command_id` as sent to the `Invoice` topic.
+pub async fn mark_sent(pool: &PgPool, command_id: i32) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE IssuedInvoice SET sentAt = now() WHERE commandId = $1 AND sentAt IS NULL")
+        .bind(command_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// The invoices numbered more than `SEND_GRACE_SECS` ago and not sent, such as the ones numbered
+/// right before a crash.
+async fn unsent(pool: &PgPool) -> Result<Vec<Invoice>, anyhow::Error> {
+    let rows: Vec<String> = sqlx::query_scalar(
+        "SELECT invoice::text FROM IssuedInvoice
+        WHERE sentAt IS NULL AND issuedAt < now() - make_interval(secs => $1)
+        ORDER BY commandId",
+    )
+    .bind(SEND_GRACE_SECS)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.iter().map(|row| serde_json::from_str(row)).collect::<Result<_, _>>()?)
+}
+
+/// Sends the numbered invoices left unsent every `SWEEP_INTERVAL`, so that the sequence has no
+/// gap on the `Invoice` topic. A resent invoice keeps its number.
+pub async fn run(producer: Arc<FutureProducer>, pool: PgPool) {
+    loop {
+        match unsent(&pool).await {
+            Ok(invoices) => {
+                for invoice in invoices {
+                    println!("Sending unsent invoice {} ({}) again.", invoice.number, invoice.id);
+                    send_invoice(&producer, &invoice).await;
+                    if let Err(e) = mark_sent(&pool, invoice.id).await {
+                        eprintln!("Failed to mark invoice {} as sent: {}", invoice.number, e);
+                    }
+                }
+            }
+            Err(e) => eprintln!("Failed to fetch the unsent invoices: {}", e),
+        }
+        tokio::time::sleep(SWEEP_INTERVAL).await;
+    }
+}
+
+/// Units of the invoice issued for `command_id`, `None` if no invoice was issued for it.
+pub async fn issued_units(pool: &PgPool, command_id: i32) -> Result<Option<HashSet<i32>>, anyhow::Error> {
+    let row: Option<String> = sqlx::query_scalar("SELECT invoice::text FROM IssuedInvoice WHERE commandId = $1")
+        .bind(command_id)
+        .fetch_optional(pool)
+        .await?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    let invoice: Invoice = serde_json::from_str(&row)?;
+    Ok(Some(invoice.products.iter().filter_map(|product| product.unit).collect()))
+}
\ No newline at end of file
@@ -0,0 +1,347 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use common::invoice::Invoice;
+use common::product::Product;
+use rdkafka::producer::FutureProducer;
+use sqlx::PgPool;
+use tokio::sync::Mutex;
+
+use crate::{outbox, send_to_dlq};
+
+/// Time between two sweeps of the incomplete invoices.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Time after the first message of an order its invoice, still incomplete, is moved to the dead
+/// letter queue. Well above the delays of held back messages.
+const INCOMPLETE_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// The messages of an order received so far.
+struct Pending {
+    /// `None` until the command arrives, which may be after its products
+    invoice: Option<Invoice>,
+    /// Products received before the command
+    early: Vec<Product>,
+    /// Units already received, so that a product message sent twice is counted once
+    units: HashSet<i32>,
+    /// When the first message of the order arrived
+    since: Instant,
+}
+
+/// What a message did to the invoice of its order.
+#[derive(Debug)]
+pub enum Progress {
+    /// The invoice waits for more messages
+    Waiting,
+    /// The message was already received and is dropped
+    Duplicate,
+    /// Every product of the command arrived
+    Complete(Invoice),
+    /// A product doesn't fit in the size of the command, so the invoice can't be trusted
+    Inconsistent(Invoice),
+}
+
+/// The messages of an order whose invoice didn't complete in time.
+#[derive(Debug)]
+pub struct Expired {
+    pub command_id: i32,
+    /// The invoice with the products received, `None` if the command never arrived
+    pub invoice: Option<Invoice>,
+    /// The products received without their command
+    pub early: Vec<Product>,
+}
+
+/// The invoices waiting for their command or products, by command id.
+#[derive(Default)]
+pub struct PendingInvoices {
+    orders: HashMap<i32, Pending>,
+}
+
+impl PendingInvoices {
+    fn entry(&mut self, command_id: i32, now: Instant) -> &mut Pending {
+        self.orders.entry(command_id).or_insert_with(|| Pending {
+            invoice: None,
+            early: Vec::new(),
+            units: HashSet::new(),
+            since: now,
+        })
+    }
+
+    /// Starts the invoice of a command, with the products that came first. A command sent twice
+    /// keeps the invoice started by the first one.
+    pub fn command(&mut self, mut invoice: Invoice, now: Instant) -> Progress {
+        let command_id = invoice.id;
+        let pending = self.entry(command_id, now);
+        if pending.invoice.is_some() {
+            return Progress::Duplicate;
+        }
+        for product in std::mem::take(&mut pending.early) {
+            invoice.add_product(product);
+        }
+        pending.invoice = Some(invoice);
+        self.progress(command_id)
+    }
+
+    /// Adds a unit to the invoice of its command, or keeps it until the command arrives. A unit
+    /// sent twice is added once.
+    pub fn product(&mut self, product: Product, now: Instant) -> Progress {
+        let command_id = product.command_id;
+        let pending = self.entry(command_id, now);
+        if let Some(unit) = product.unit {
+            if !pending.units.insert(unit) {
+                return Progress::Duplicate;
+            }
+        }
+        match pending.invoice.as_mut() {
+            Some(invoice) => invoice.add_product(product),
+            None => {
+                pending.early.push(product);
+                return Progress::Waiting;
+            }
+        }
+        self.progress(command_id)
+    }
+
+    /// Forgets an order whose command was moved to the dead letter queue. Returns the products
+    /// received for it.
+    pub fn abandon(&mut self, command_id: i32) -> Vec<Product> {
+        self.orders.remove(&command_id).map(|pending| pending.early).unwrap_or_default()
+    }
+
+    /// Removes the invoice once complete, or once a product shows that its size is wrong.
+    fn progress(&mut self, command_id: i32) -> Progress {
+        let Some(Pending { invoice: Some(invoice), units, .. }) = self.orders.get(&command_id) else {
+            return Progress::Waiting;
+        };
+        let size = invoice.size.max(0) as usize;
+        let overflows = invoice.products.len() > size || units.iter().any(|unit| *unit >= invoice.size);
+        let complete = !invoice.products.is_empty() && invoice.products.len() == size;
+        if !overflows && !complete {
+            return Progress::Waiting;
+        }
+        let Some(invoice) = self.orders.remove(&command_id).and_then(|pending| pending.invoice) else {
+            return Progress::Waiting;
+        };
+        match overflows {
+            true => Progress::Inconsistent(invoice),
+            false => Progress::Complete(invoice),
+        }
+    }
+
+    /// Removes the orders whose first message arrived `timeout` or more before `now`.
+    pub fn expire(&mut self, now: Instant, timeout: Duration) -> Vec<Expired> {
+        let expired: Vec<i32> = self
+            .orders
+            .iter()
+            .filter(|(_, pending)| now.duration_since(pending.since) >= timeout)
+            .map(|(command_id, _)| *command_id)
+            .collect();
+        expired
+            .into_iter()
+            .filter_map(|command_id| {
+                let pending = self.orders.remove(&command_id)?;
+                Some(Expired { command_id, invoice: pending.invoice, early: pending.early })
+            })
+            .collect()
+    }
+}
+
+/// Moves the messages of an expired order to the dead letter queue. Messages sent twice for an
+/// order already invoiced, such as a command or a unit held back past the end of its order, are
+/// dropped instead.
+async fn dead_letter(producer: &FutureProducer, pool: &PgPool, expired: Expired) {
+    let issued = match outbox::issued_units(pool, expired.command_id).await {
+        Ok(issued) => issued,
+        Err(e) => {
+            eprintln!("Failed to fetch the invoice issued for command {}: {}", expired.command_id, e);
+            None
+        }
+    };
+    let Some(units) = issued else {
+        match expired.invoice {
+            Some(invoice) => {
+                eprintln!(
+                    "Invoice {} incomplete after {:?}: {} of {} products.",
+                    invoice.id,
+                    INCOMPLETE_TIMEOUT,
+                    invoice.products.len(),
+                    invoice.size
+                );
+                send_to_dlq(producer, invoice.id, invoice).await;
+            }
+            None => {
+                for product in expired.early {
+                    send_to_dlq(producer, product.command_id, product).await;
+                }
+            }
+        }
+        return;
+    };
+    if expired.invoice.is_some() {
+        println!("Command {} sent twice, already invoiced, dropped.", expired.command_id);
+    }
+    let products = expired.invoice.map(|invoice| invoice.products).unwrap_or(expired.early);
+    for product in products {
+        match product.unit.is_some_and(|unit| units.contains(&unit)) {
+            true => println!("Unit of command {} sent twice, already invoiced, dropped.", product.command_id),
+            false => send_to_dlq(producer, product.command_id, product).await,
+        }
+    }
+}
+
+/// Moves the invoices still incomplete `INCOMPLETE_TIMEOUT` after their first message to the dead
+/// letter queue every `SWEEP_INTERVAL`, such as the ones that lost a product.
+pub async fn run(producer: Arc<FutureProducer>, pool: PgPool, invoices: Arc<Mutex<PendingInvoices>>) {
+    loop {
+        tokio::time::sleep(SWEEP_INTERVAL).await;
+        let expired = invoices.lock().await.expire(Instant::now(), INCOMPLETE_TIMEOUT);
+        for expired in expired {
+            dead_letter(&producer, &pool, expired).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::client::Client;
+
+    const TIMEOUT: Duration = Duration::from_secs(600);
+
+    fn command(id: i32, size: i32) -> Invoice {
+        Invoice {
+            id,
+            number: String::new(),
+            date: String::from("2026-03-02"),
+            client: Client::default(),
+            products: vec![],
+            total_price: 0.0,
+            size,
+            currency: String::from("EUR"),
+        }
+    }
+
+    fn unit(command_id: i32, unit: i32) -> Product {
+        Product {
+            id: 3,
+            name: String::from("Tea"),
+            price: 4.5,
+            command_id,
+            backordered: false,
+            currency: String::from("EUR"),
+            exchange_rate: 1.0,
+            unit: Some(unit),
+        }
+    }
+
+    fn complete(progress: Progress) -> Invoice {
+        match progress {
+            Progress::Complete(invoice) => invoice,
+            progress => panic!("Expected a complete invoice, got {:?}", progress),
+        }
+    }
+
+    #[test]
+    fn in_order_messages_complete_the_invoice() {
+        let (mut pending, now) = (PendingInvoices::default(), Instant::now());
+        assert!(matches!(pending.command(command(1, 2), now), Progress::Waiting));
+        assert!(matches!(pending.product(unit(1, 0), now), Progress::Waiting));
+        let invoice = complete(pending.product(unit(1, 1), now));
+        assert_eq!((invoice.products.len(), invoice.total_price), (2, 9.0));
+        assert!(pending.expire(now + TIMEOUT, TIMEOUT).is_empty());
+    }
+
+    #[test]
+    fn reorder_keeps_the_products_until_the_command() {
+        let (mut pending, now) = (PendingInvoices::default(), Instant::now());
+        assert!(matches!(pending.product(unit(1, 0), now), Progress::Waiting));
+        assert!(matches!(pending.product(unit(1, 1), now), Progress::Waiting));
+        let invoice = complete(pending.command(command(1, 2), now));
+        assert_eq!(invoice.products.len(), 2);
+    }
+
+    #[test]
+    fn duplicate_messages_are_counted_once() {
+        let (mut pending, now) = (PendingInvoices::default(), Instant::now());
+        pending.command(command(1, 3), now);
+        pending.product(unit(1, 0), now);
+        assert!(matches!(pending.product(unit(1, 0), now), Progress::Duplicate));
+        // The command sent twice doesn't drop the units received
+        assert!(matches!(pending.command(command(1, 3), now), Progress::Duplicate));
+        pending.product(unit(1, 1), now);
+        let invoice = complete(pending.product(unit(1, 2), now));
+        assert_eq!(invoice.products.iter().map(|product| product.unit).collect::<Vec<_>>(), [Some(0), Some(1), Some(2)]);
+    }
+
+    #[test]
+    fn delayed_unit_completes_the_invoice_last() {
+        let (mut pending, now) = (PendingInvoices::default(), Instant::now());
+        pending.command(command(1, 3), now);
+        pending.product(unit(1, 0), now);
+        pending.product(unit(1, 2), now);
+        assert_eq!(complete(pending.product(unit(1, 1), now)).products.len(), 3);
+
+        // A unit held back past the end of its order waits alone, then expires
+        pending.product(unit(1, 2), now);
+        let expired = pending.expire(now + TIMEOUT, TIMEOUT);
+        assert_eq!(expired.len(), 1);
+        assert!(expired[0].invoice.is_none());
+        assert_eq!(expired[0].early.len(), 1);
+    }
+
+    #[test]
+    fn dropped_product_expires_the_invoice() {
+        let (mut pending, now) = (PendingInvoices::default(), Instant::now());
+        pending.command(command(1, 2), now);
+        pending.product(unit(1, 1), now);
+        assert!(pending.expire(now + TIMEOUT - Duration::from_secs(1), TIMEOUT).is_empty());
+
+        let expired = pending.expire(now + TIMEOUT, TIMEOUT);
+        assert_eq!(expired.len(), 1);
+        let invoice = expired[0].invoice.as_ref().unwrap();
+        assert_eq!((invoice.id, invoice.products.len(), invoice.size), (1, 1, 2));
+        // Expired orders are forgotten
+        assert!(pending.expire(now + TIMEOUT * 2, TIMEOUT).is_empty());
+    }
+
+    #[test]
+    fn size_mismatch_expires_or_rejects_the_invoice() {
+        // One more unit announced than sent
+        let (mut pending, now) = (PendingInvoices::default(), Instant::now());
+        pending.command(command(1, 3), now);
+        pending.product(unit(1, 0), now);
+        pending.product(unit(1, 1), now);
+        assert_eq!(pending.expire(now + TIMEOUT, TIMEOUT).len(), 1);
+
+        // One less unit announced than sent, the last unit arriving first
+        let mut pending = PendingInvoices::default();
+        pending.command(command(2, 1), now);
+        match pending.product(unit(2, 1), now) {
+            Progress::Inconsistent(invoice) => assert_eq!(invoice.id, 2),
+            progress => panic!("Expected an inconsistent invoice, got {:?}", progress),
+        }
+        assert!(pending.expire(now + TIMEOUT, TIMEOUT).is_empty());
+    }
+
+    #[test]
+    fn corrupt_command_leaves_its_products_to_expire() {
+        // The command can't be decoded, so it never arrives
+        let (mut pending, now) = (PendingInvoices::default(), Instant::now());
+        pending.product(unit(1, 0), now);
+        let expired = pending.expire(now + TIMEOUT, TIMEOUT);
+        assert_eq!((expired[0].command_id, expired[0].early.len()), (1, 1));
+        assert!(expired[0].invoice.is_none());
+    }
+
+    #[test]
+    fn unknown_client_abandons_the_products() {
+        // The command is dead lettered once its client can't be found
+        let (mut pending, now) = (PendingInvoices::default(), Instant::now());
+        pending.product(unit(1, 0), now);
+        pending.product(unit(1, 1), now);
+        assert_eq!(pending.abandon(1).len(), 2);
+        assert!(pending.abandon(1).is_empty());
+        assert!(pending.expire(now + TIMEOUT, TIMEOUT).is_empty());
+    }
+}
\ No newline at end of file
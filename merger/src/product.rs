@@ -1,53 +1,89 @@
-use std::{collections::HashMap, sync::Arc};
+use std::sync::Arc;
+use std::time::Instant;
 use common::{invoice::Invoice, product::Product};
 use rdkafka::producer::FutureProducer;
+use sqlx::PgPool;
 use tokio::sync::Mutex;
 use backon::{ExponentialBuilder, Retryable};
+use crate::pending::{PendingInvoices, Progress};
+use crate::{conversion, numbering, outbox};
 use crate::{send_invoice, send_to_dlq};
 
 pub async fn process_product(
     producer: Arc<FutureProducer>,
+    pool: PgPool,
     product: Product,
-    invoices: Arc<Mutex<HashMap<i32, Invoice>>>,
+    invoices: Arc<Mutex<PendingInvoices>>,
 ) {
-    // Spawn a separate task for this product
-    tokio::spawn(async move {
-        let retry_result = (|| async {
-            let mut invoices_lock = invoices.lock().await;
-            if let Some(invoice) = invoices_lock.get_mut(&product.command_id) {
-                invoice.add_product(product.clone());
-                Ok(invoice.clone())
-            } else {
-                Err(anyhow::anyhow!(
-                    "Invoice with command ID {} not found",
-                    product.command_id
-                ))
-            }
+    // Products arriving before their command wait for it
+    let progress = invoices.lock().await.product(product, Instant::now());
+    settle(producer, pool, progress).await;
+}
+
+/// Issues the invoice the message completed, or moves it to the dead letter queue if its
+/// products don't match its command.
+pub async fn settle(producer: Arc<FutureProducer>, pool: PgPool, progress: Progress) {
+    match progress {
+        Progress::Waiting => {}
+        Progress::Duplicate => println!("Message received twice, dropped."),
+        Progress::Complete(invoice) => {
+            // Spawn a separate task to number and send the invoice
+            tokio::spawn(issue(producer, pool, invoice));
+        }
+        Progress::Inconsistent(invoice) => {
+            eprintln!("Invoice {} has more products than its size {}.", invoice.id, invoice.size);
+            send_to_dlq(&producer, invoice.id, invoice).await;
+        }
+    }
+}
+
+/// Converts the prices of a complete invoice, numbers it, then sends it.
+async fn issue(producer: Arc<FutureProducer>, pool: PgPool, mut invoice: Invoice) {
+    let rates = (|| conversion::rates(&pool, &invoice))
+        .retry(ExponentialBuilder::default().with_max_times(5))
+        .sleep(tokio::time::sleep)
+        .notify(|err, dur| {
+            println!(
+                "Retrying to fetch the exchange rates of invoice with command ID {} after {:?}: {}",
+                invoice.id, dur, err
+            );
         })
+        .await;
+    let Ok(rates) = rates else {
+        // An invoice is never sent with prices in another currency than its own
+        send_to_dlq(&producer, invoice.id, invoice).await;
+        return;
+    };
+    conversion::apply(&mut invoice, &rates);
+
+    let Some(fiscal_year) = numbering::fiscal_year(&invoice.date) else {
+        // An invoice is never numbered in a year its date doesn't tell
+        send_to_dlq(&producer, invoice.id, invoice).await;
+        return;
+    };
+    let number = (|| numbering::assign(&pool, &invoice, fiscal_year))
         .retry(ExponentialBuilder::default().with_max_times(5))
         .sleep(tokio::time::sleep)
         .notify(|err, dur| {
             println!(
-                "Retrying to fetch invoice with command ID {} after {:?}: {}",
-                product.command_id, dur, err
+                "Retrying to number invoice with command ID {} after {:?}: {}",
+                invoice.id, dur, err
             );
         })
         .await;
 
-        match retry_result {
-            Ok(invoice) => {
-                // Check if the invoice is complete
-                if invoice.products.len() == invoice.size as usize {
-                    send_invoice(&producer, &invoice).await;
-                    // Remove the completed invoice from the shared map
-                    let mut invoices_lock = invoices.lock().await;
-                    invoices_lock.remove(&product.command_id);
-                }
-            }
-            Err(_) => {
-                // If invoice still not found after retries, send product to DLQ
-                send_to_dlq(&producer, product.command_id, product).await;
+    match number {
+        Ok(number) => {
+            invoice.number = number;
+            send_invoice(&producer, &invoice).await;
+            // Until then the outbox sends it again
+            if let Err(e) = outbox::mark_sent(&pool, invoice.id).await {
+                eprintln!("Failed to mark invoice {} as sent: {}", invoice.number, e);
             }
         }
-    });
-}
+        Err(_) => {
+            // An invoice is never sent without its legal number
+            send_to_dlq(&producer, invoice.id, invoice).await;
+        }
+    }
+}
\ No newline at end of file
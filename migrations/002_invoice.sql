@@ -0,0 +1,25 @@
+CREATE TABLE Invoice (
+  id INT PRIMARY KEY,
+  clientId INT NOT NULL,
+  clientName VARCHAR(255) NOT NULL,
+  clientEmail VARCHAR(255) NOT NULL,
+  clientAddress VARCHAR(255) NOT NULL,
+  date DATE NOT NULL,
+  totalPrice FLOAT NOT NULL,
+  size INT NOT NULL,
+  FOREIGN KEY (id) REFERENCES Command(id),
+  FOREIGN KEY (clientId) REFERENCES Client(id)
+);
+
+CREATE TABLE InvoiceLine (
+  invoiceId INT NOT NULL,
+  lineNumber INT NOT NULL,
+  productId INT NOT NULL,
+  name VARCHAR(255) NOT NULL,
+  price FLOAT NOT NULL,
+  PRIMARY KEY (invoiceId, lineNumber),
+  FOREIGN KEY (invoiceId) REFERENCES Invoice(id) ON DELETE CASCADE,
+  FOREIGN KEY (productId) REFERENCES Product(id)
+);
+
+CREATE INDEX invoice_client_date_idx ON Invoice (clientId, date);
\ No newline at end of file
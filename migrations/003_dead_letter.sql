@@ -0,0 +1,6 @@
+CREATE TABLE DeadLetter (
+  commandId INT NOT NULL,
+  payload TEXT NOT NULL,
+  receivedAt TIMESTAMP NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (commandId, payload)
+);
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE CommandProduct ADD COLUMN quantity INT NOT NULL DEFAULT 1 CHECK (quantity > 0);
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE Product ADD COLUMN category VARCHAR(255) NOT NULL DEFAULT 'misc';
\ No newline at end of file
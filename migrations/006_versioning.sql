@@ -0,0 +1,4 @@
+ALTER TABLE Client ADD COLUMN version INT NOT NULL DEFAULT 1;
+ALTER TABLE Client ADD COLUMN updatedAt TIMESTAMPTZ NOT NULL DEFAULT now();
+ALTER TABLE Product ADD COLUMN version INT NOT NULL DEFAULT 1;
+ALTER TABLE Product ADD COLUMN updatedAt TIMESTAMPTZ NOT NULL DEFAULT now();
\ No newline at end of file
@@ -0,0 +1,45 @@
+ALTER TABLE Command ADD COLUMN status VARCHAR(32) NOT NULL DEFAULT 'placed'
+  CHECK (status IN ('placed', 'paid', 'shipped', 'cancelled', 'partially_refunded'));
+
+CREATE TABLE OrderEvent (
+  id INT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  commandId INT NOT NULL,
+  status VARCHAR(32) NOT NULL,
+  reason VARCHAR(255) NOT NULL,
+  at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  FOREIGN KEY (commandId) REFERENCES Command(id)
+);
+
+CREATE TABLE RefundLine (
+  eventId INT NOT NULL,
+  productId INT NOT NULL,
+  quantity INT NOT NULL CHECK (quantity > 0),
+  PRIMARY KEY (eventId, productId),
+  FOREIGN KEY (eventId) REFERENCES OrderEvent(id) ON DELETE CASCADE,
+  FOREIGN KEY (productId) REFERENCES Product(id)
+);
+
+CREATE INDEX order_event_command_idx ON OrderEvent (commandId);
+
+-- No foreign key to Invoice: the sink may store a credit note before its invoice
+CREATE TABLE CreditNote (
+  id INT PRIMARY KEY,
+  invoiceId INT NOT NULL,
+  clientId INT NOT NULL,
+  date DATE NOT NULL,
+  reason VARCHAR(255) NOT NULL,
+  totalPrice FLOAT NOT NULL,
+  size INT NOT NULL
+);
+
+CREATE TABLE CreditNoteLine (
+  creditNoteId INT NOT NULL,
+  lineNumber INT NOT NULL,
+  productId INT NOT NULL,
+  name VARCHAR(255) NOT NULL,
+  price FLOAT NOT NULL,
+  PRIMARY KEY (creditNoteId, lineNumber),
+  FOREIGN KEY (creditNoteId) REFERENCES CreditNote(id) ON DELETE CASCADE
+);
+
+CREATE INDEX credit_note_invoice_idx ON CreditNote (invoiceId);
\ No newline at end of file
@@ -0,0 +1,23 @@
+CREATE TABLE Stock (
+  productId INT PRIMARY KEY,
+  available INT NOT NULL DEFAULT 100 CHECK (available >= 0),
+  backorder BOOLEAN NOT NULL DEFAULT false,
+  FOREIGN KEY (productId) REFERENCES Product(id) ON DELETE CASCADE
+);
+
+-- Grocery and clothing only sell what is in stock, the other categories can be backordered
+CREATE FUNCTION create_stock() RETURNS TRIGGER AS $$
+BEGIN
+  INSERT INTO Stock (productId, backorder) VALUES (NEW.id, NEW.category NOT IN ('grocery', 'clothing'));
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER product_stock AFTER INSERT ON Product FOR EACH ROW EXECUTE FUNCTION create_stock();
+
+INSERT INTO Stock (productId, backorder) SELECT id, category NOT IN ('grocery', 'clothing') FROM Product;
+
+ALTER TABLE CommandProduct ADD COLUMN backordered INT NOT NULL DEFAULT 0
+  CHECK (backordered >= 0 AND backordered <= quantity);
+
+ALTER TABLE InvoiceLine ADD COLUMN backordered BOOLEAN NOT NULL DEFAULT false;
\ No newline at end of file
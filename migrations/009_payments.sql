@@ -0,0 +1,16 @@
+-- Unit price when the order was placed, as the product price changes afterwards
+ALTER TABLE CommandProduct ADD COLUMN price FLOAT;
+UPDATE CommandProduct SET price = Product.price FROM Product WHERE Product.id = CommandProduct.productId;
+ALTER TABLE CommandProduct ALTER COLUMN price SET NOT NULL;
+
+CREATE TABLE Payment (
+  id INT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  commandId INT NOT NULL,
+  status VARCHAR(32) NOT NULL CHECK (status IN ('authorized', 'captured', 'failed')),
+  amount FLOAT NOT NULL,
+  method VARCHAR(32) NOT NULL,
+  at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  FOREIGN KEY (commandId) REFERENCES Command(id)
+);
+
+CREATE INDEX payment_command_idx ON Payment (commandId);
\ No newline at end of file
@@ -0,0 +1,18 @@
+-- Last legal invoice number handed out per fiscal year
+CREATE TABLE InvoiceSequence (
+  fiscalYear INT PRIMARY KEY,
+  lastNumber INT NOT NULL DEFAULT 0
+);
+
+-- Legal number of the invoice of each command, so that a replayed invoice keeps its number
+CREATE TABLE InvoiceNumber (
+  commandId INT PRIMARY KEY,
+  fiscalYear INT NOT NULL,
+  sequence INT NOT NULL,
+  number VARCHAR(32) NOT NULL UNIQUE,
+  assignedAt TIMESTAMPTZ NOT NULL DEFAULT now(),
+  UNIQUE (fiscalYear, sequence),
+  FOREIGN KEY (commandId) REFERENCES Command(id) ON DELETE CASCADE
+);
+
+ALTER TABLE Invoice ADD COLUMN number VARCHAR(32) NOT NULL DEFAULT ''
\ No newline at end of file
@@ -0,0 +1,19 @@
+-- Clients who don't want their invoices by email
+CREATE TABLE EmailOptOut (
+  clientId INT PRIMARY KEY,
+  at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  FOREIGN KEY (clientId) REFERENCES Client(id) ON DELETE CASCADE
+);
+
+-- Outcome of the invoice email of each invoice, so that no invoice is emailed twice
+CREATE TABLE InvoiceEmail (
+  invoiceId INT PRIMARY KEY,
+  clientId INT NOT NULL,
+  email VARCHAR(255) NOT NULL,
+  status VARCHAR(16) NOT NULL CHECK (status IN ('sending', 'sent', 'failed', 'opted_out')),
+  attempts INT NOT NULL DEFAULT 0,
+  error TEXT,
+  updatedAt TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX invoice_email_status_idx ON InvoiceEmail (status)
\ No newline at end of file
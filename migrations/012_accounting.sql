@@ -0,0 +1,45 @@
+-- Revenue account of each product category. Products of a category missing here are
+-- posted to the account of 'misc'.
+CREATE TABLE RevenueAccount (
+  category VARCHAR(255) PRIMARY KEY,
+  account VARCHAR(20) NOT NULL UNIQUE,
+  label VARCHAR(255) NOT NULL
+);
+
+INSERT INTO RevenueAccount (category, account, label) VALUES
+  ('misc', '707000', 'Ventes de marchandises'),
+  ('grocery', '707100', 'Ventes de marchandises - épicerie'),
+  ('books', '707200', 'Ventes de marchandises - livres'),
+  ('clothing', '707300', 'Ventes de marchandises - habillement'),
+  ('home', '707400', 'Ventes de marchandises - maison'),
+  ('electronics', '707500', 'Ventes de marchandises - électronique');
+
+-- One balanced entry per invoice or credit note, numbered without gaps in the order it is posted
+CREATE TABLE JournalEntry (
+  id INT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  number INT NOT NULL UNIQUE,
+  journal VARCHAR(8) NOT NULL,
+  sourceType VARCHAR(16) NOT NULL CHECK (sourceType IN ('invoice', 'credit_note')),
+  sourceId INT NOT NULL,
+  pieceRef VARCHAR(32) NOT NULL,
+  pieceDate DATE NOT NULL,
+  label VARCHAR(255) NOT NULL,
+  postedAt TIMESTAMPTZ NOT NULL DEFAULT now(),
+  UNIQUE (sourceType, sourceId)
+);
+
+-- Amounts are in cents, so that the debits and credits of an entry add up exactly
+CREATE TABLE JournalLine (
+  entryId INT NOT NULL,
+  lineNumber INT NOT NULL,
+  account VARCHAR(20) NOT NULL,
+  accountLabel VARCHAR(255) NOT NULL,
+  auxAccount VARCHAR(20),
+  auxLabel VARCHAR(255),
+  debit BIGINT NOT NULL DEFAULT 0 CHECK (debit >= 0),
+  credit BIGINT NOT NULL DEFAULT 0 CHECK (credit >= 0),
+  PRIMARY KEY (entryId, lineNumber),
+  FOREIGN KEY (entryId) REFERENCES JournalEntry(id) ON DELETE CASCADE
+);
+
+CREATE INDEX journal_entry_piece_date_idx ON JournalEntry (pieceDate);
\ No newline at end of file
@@ -0,0 +1,37 @@
+ALTER TABLE Client ADD COLUMN currency VARCHAR(3) NOT NULL DEFAULT 'EUR';
+ALTER TABLE Product ADD COLUMN currency VARCHAR(3) NOT NULL DEFAULT 'EUR';
+
+-- Rate converting an amount in baseCurrency into quoteCurrency, from validFrom until the next
+-- rate of the pair. The reverse pair uses the inverse rate unless it has its own.
+CREATE TABLE ExchangeRate (
+  baseCurrency VARCHAR(3) NOT NULL,
+  quoteCurrency VARCHAR(3) NOT NULL,
+  validFrom DATE NOT NULL,
+  rate FLOAT NOT NULL CHECK (rate > 0),
+  PRIMARY KEY (baseCurrency, quoteCurrency, validFrom)
+);
+
+INSERT INTO ExchangeRate (baseCurrency, quoteCurrency, validFrom, rate) VALUES
+  ('EUR', 'USD', '2020-01-01', 1.12),
+  ('EUR', 'USD', '2023-01-01', 1.07),
+  ('EUR', 'USD', '2025-01-01', 1.04),
+  ('EUR', 'USD', '2026-01-01', 1.17),
+  ('EUR', 'GBP', '2020-01-01', 0.85),
+  ('EUR', 'GBP', '2023-01-01', 0.88),
+  ('EUR', 'GBP', '2025-01-01', 0.83),
+  ('EUR', 'GBP', '2026-01-01', 0.87),
+  ('GBP', 'USD', '2020-01-01', 1.32),
+  ('GBP', 'USD', '2023-01-01', 1.22),
+  ('GBP', 'USD', '2025-01-01', 1.25),
+  ('GBP', 'USD', '2026-01-01', 1.34);
+
+ALTER TABLE Invoice ADD COLUMN currency VARCHAR(3) NOT NULL DEFAULT 'EUR';
+-- Base currency of the product and the rate its price was converted with into the invoice currency
+ALTER TABLE InvoiceLine ADD COLUMN currency VARCHAR(3) NOT NULL DEFAULT 'EUR';
+ALTER TABLE InvoiceLine ADD COLUMN exchangeRate FLOAT NOT NULL DEFAULT 1;
+ALTER TABLE CreditNote ADD COLUMN currency VARCHAR(3) NOT NULL DEFAULT 'EUR';
+
+-- Entries of invoices in another currency are posted in EUR, converted on the piece date
+ALTER TABLE JournalEntry ADD COLUMN currency VARCHAR(3) NOT NULL DEFAULT 'EUR';
+ALTER TABLE JournalEntry ADD COLUMN exchangeRate FLOAT NOT NULL DEFAULT 1;
+ALTER TABLE JournalLine ADD COLUMN currencyAmount BIGINT;
\ No newline at end of file
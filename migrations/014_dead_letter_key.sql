@@ -0,0 +1,6 @@
+-- A payload can outgrow what a btree key holds: dead letters get a surrogate key and are
+-- deduplicated on a hash of their payload instead
+ALTER TABLE DeadLetter DROP CONSTRAINT deadletter_pkey;
+ALTER TABLE DeadLetter ADD COLUMN id INT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY;
+ALTER TABLE DeadLetter ADD COLUMN payloadHash TEXT GENERATED ALWAYS AS (md5(payload)) STORED;
+CREATE UNIQUE INDEX dead_letter_payload_idx ON DeadLetter (commandId, payloadHash);
\ No newline at end of file
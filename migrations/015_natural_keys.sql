@@ -0,0 +1,4 @@
+-- Imported rows without an id are matched on their natural key: the email of a client and the
+-- name of a product, both case-insensitive
+CREATE INDEX client_email_idx ON Client (lower(email));
+CREATE INDEX product_name_idx ON Product (lower(name));
\ No newline at end of file
@@ -0,0 +1,13 @@
+-- Invoices sent by the merger with what was credited since, so that the cancellations and refunds
+-- of an order are credited once and against its invoice, even after a restart of the merger
+CREATE TABLE IssuedInvoice (
+  commandId INT PRIMARY KEY,
+  -- The invoice as sent to the Invoice topic
+  invoice JSONB NOT NULL,
+  -- Number of credited units by product id
+  credited JSONB NOT NULL DEFAULT '{}',
+  -- Ids of the credited cancellation and refund events
+  events INT[] NOT NULL DEFAULT '{}',
+  issuedAt TIMESTAMPTZ NOT NULL DEFAULT now(),
+  FOREIGN KEY (commandId) REFERENCES Command(id) ON DELETE CASCADE
+);
\ No newline at end of file
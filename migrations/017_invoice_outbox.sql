@@ -0,0 +1,8 @@
+-- Numbered invoices are recorded with their number and sent afterwards: an invoice not sent yet
+-- has no sentAt, and the merger sends it again
+ALTER TABLE IssuedInvoice ADD COLUMN sentAt TIMESTAMPTZ;
+
+-- The invoices recorded so far were recorded once sent
+UPDATE IssuedInvoice SET sentAt = issuedAt;
+
+CREATE INDEX issued_invoice_unsent_idx ON IssuedInvoice (issuedAt) WHERE sentAt IS NULL;
\ No newline at end of file
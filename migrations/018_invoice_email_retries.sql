@@ -0,0 +1,6 @@
+-- The invoice to email, so that failed emails and emails interrupted by a crash are sent again
+-- without waiting for a replay of the Invoice topic
+ALTER TABLE InvoiceEmail ADD COLUMN invoice JSONB;
+
+-- A failure that sending again won't fix, such as an invalid address or a rejected recipient
+ALTER TABLE InvoiceEmail ADD COLUMN permanent BOOLEAN NOT NULL DEFAULT false;
\ No newline at end of file
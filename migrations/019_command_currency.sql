@@ -0,0 +1,7 @@
+-- Currency of the client and base currency of each product when the order was placed, which its
+-- total is computed in even if the client or the product changes currency later
+ALTER TABLE Command ADD COLUMN currency VARCHAR(3) NOT NULL DEFAULT 'EUR';
+UPDATE Command SET currency = Client.currency FROM Client WHERE Client.id = Command.clientId;
+
+ALTER TABLE CommandProduct ADD COLUMN currency VARCHAR(3) NOT NULL DEFAULT 'EUR';
+UPDATE CommandProduct SET currency = Product.currency FROM Product WHERE Product.id = CommandProduct.productId;
\ No newline at end of file
@@ -0,0 +1,4 @@
+-- Refunds and cancellations of captured orders pay the money back, as a negative amount
+ALTER TABLE Payment DROP CONSTRAINT payment_status_check;
+ALTER TABLE Payment ADD CONSTRAINT payment_status_check
+  CHECK (status IN ('authorized', 'captured', 'failed', 'refunded'));
\ No newline at end of file
@@ -0,0 +1,42 @@
+use common::invoice::Invoice;
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, Mailbox, MultiPart};
+use lettre::Message;
+use renderer::{html, pdf, InvoiceView, Locale, RenderOptions};
+
+/// Builds the email of an invoice: a short text, the rendered invoice as its HTML alternative,
+/// and the PDF attached.
+pub fn build(invoice: &Invoice, options: &RenderOptions, from: &Mailbox) -> Result<Message, Box<dyn std::error::Error>> {
+    let view = InvoiceView::new(invoice, options);
+    let to = Mailbox::new(Some(invoice.client.name.clone()), invoice.client.email.parse()?);
+
+    let (subject, text) = match options.locale {
+        Locale::FrFr => (
+            format!("Votre facture {}", view.number),
+            format!(
+                "Bonjour {},\n\nVeuillez trouver ci-joint votre facture {} du {}, d'un montant de {}.\n\nMerci pour votre commande,\n{}\n",
+                view.client.name, view.number, view.date, view.total, view.seller.name
+            ),
+        ),
+        Locale::EnUs => (
+            format!("Your invoice {}", view.number),
+            format!(
+                "Hello {},\n\nPlease find attached your invoice {} of {}, for a total of {}.\n\nThank you for your order,\n{}\n",
+                view.client.name, view.number, view.date, view.total, view.seller.name
+            ),
+        ),
+    };
+
+    let attachment = Attachment::new(format!("{}.pdf", view.number))
+        .body(pdf::render(&view), ContentType::parse("application/pdf")?);
+    let message = Message::builder()
+        .from(from.clone())
+        .to(to)
+        .subject(subject)
+        .multipart(
+            MultiPart::mixed()
+                .multipart(MultiPart::alternative_plain_html(text, html::render(&view)?))
+                .singlepart(attachment),
+        )?;
+    Ok(message)
+}
\ No newline at end of file
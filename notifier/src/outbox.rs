@@ -0,0 +1,108 @@
+use common::invoice::Invoice;
+use sqlx::PgPool;
+
+/// Largest number of emails retried at once.
+const RETRY_BATCH: i64 = 100;
+
+/// Claims the email of an invoice before it is sent, with the invoice to send it again. Returns
+/// `false` if it was already sent, is being sent or the client opted out, so that nobody gets the
+/// same invoice twice. A failed email can be claimed again, and so can an email `sending` for more
+/// than `lease_secs`, interrupted by a crash.
+pub async fn claim(pool: &PgPool, invoice: &Invoice, lease_secs: f64) -> Result<bool, sqlx::Error> {
+    let json = serde_json::to_string(invoice).map_err(|e| sqlx::Error::Encode(e.into()))?;
+    let claimed = sqlx::query(
+        "INSERT INTO InvoiceEmail (invoiceId, clientId, email, status, invoice)
+        VALUES ($1, $2, $3, 'sending', $4::jsonb)
+        ON CONFLICT (invoiceId) DO UPDATE SET status = 'sending', email = EXCLUDED.email, invoice = EXCLUDED.invoice,
+            error = NULL, permanent = false, updatedAt = now()
+        WHERE InvoiceEmail.status = 'failed'
+            OR (InvoiceEmail.status = 'sending' AND InvoiceEmail.updatedAt < now() - make_interval(secs => $5))",
+    )
+    .bind(invoice.id)
+    .bind(invoice.client.id)
+    .bind(&invoice.client.email)
+    .bind(json)
+    .bind(lease_secs)
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(claimed == 1)
+}
+
+/// Claims again the emails to retry: the ones that failed for a temporary reason more than
+/// `retry_after_secs` ago with less than `max_attempts` attempts, and the ones `sending` for more
+/// than `lease_secs`. Returns their invoices.
+pub async fn reclaim(
+    pool: &PgPool,
+    retry_after_secs: f64,
+    max_attempts: i32,
+    lease_secs: f64,
+) -> Result<Vec<Invoice>, Box<dyn std::error::Error>> {
+    let rows: Vec<String> = sqlx::query_scalar(
+        "UPDATE InvoiceEmail SET status = 'sending', error = NULL, updatedAt = now()
+        WHERE invoiceId IN (
+            SELECT invoiceId FROM InvoiceEmail
+            WHERE invoice IS NOT NULL AND (
+                (status = 'failed' AND NOT permanent AND attempts < $2
+                    AND updatedAt < now() - make_interval(secs => $1))
+                OR (status = 'sending' AND updatedAt < now() - make_interval(secs => $3))
+            )
+            ORDER BY invoiceId LIMIT $4 FOR UPDATE SKIP LOCKED
+        )
+        RETURNING invoice::text",
+    )
+    .bind(retry_after_secs)
+    .bind(max_attempts)
+    .bind(lease_secs)
+    .bind(RETRY_BATCH)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.iter().map(|row| serde_json::from_str(row)).collect::<Result<_, _>>()?)
+}
+
+pub async fn is_opted_out(pool: &PgPool, client_id: i32) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM EmailOptOut WHERE clientId = $1)")
+        .bind(client_id)
+        .fetch_one(pool)
+        .await
+}
+
+/// Records how the claimed email ended: `sent`, `failed` with the error and whether it is
+/// `permanent`, or `opted_out`.
+pub async fn record(
+    pool: &PgPool,
+    invoice_id: i32,
+    status: &str,
+    attempts: i32,
+    error: Option<&str>,
+    permanent: bool,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE InvoiceEmail SET status = $2, attempts = attempts + $3, error = $4, permanent = $5, updatedAt = now()
+        WHERE invoiceId = $1",
+    )
+    .bind(invoice_id)
+    .bind(status)
+    .bind(attempts)
+    .bind(error)
+    .bind(permanent)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn opt_out(pool: &PgPool, client_id: i32) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT INTO EmailOptOut (clientId) VALUES ($1) ON CONFLICT (clientId) DO NOTHING")
+        .bind(client_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn opt_in(pool: &PgPool, client_id: i32) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM EmailOptOut WHERE clientId = $1")
+        .bind(client_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
\ No newline at end of file
@@ -0,0 +1,87 @@
+use std::path::PathBuf;
+
+use clap::{Args, ValueEnum};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncFileTransport, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum TransportKind {
+    /// Sends the emails through an SMTP server
+    Smtp,
+    /// Writes each email to an `.eml` file in `--drop-dir`, for local testing
+    File,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct TransportOptions {
+    #[arg(long, value_enum, default_value_t = TransportKind::Smtp)]
+    pub transport: TransportKind,
+
+    /// SMTP server, the Mailpit of the docker compose by default
+    #[arg(long, default_value = "localhost")]
+    pub smtp_host: String,
+
+    #[arg(long, default_value_t = 1025)]
+    pub smtp_port: u16,
+
+    #[arg(long, requires = "smtp_password")]
+    pub smtp_username: Option<String>,
+
+    #[arg(long, requires = "smtp_username")]
+    pub smtp_password: Option<String>,
+
+    /// If provided, upgrades the SMTP connection to TLS with STARTTLS
+    #[arg(long)]
+    pub smtp_starttls: bool,
+
+    /// Directory the file transport writes the emails to
+    #[arg(long, default_value = "emails")]
+    pub drop_dir: PathBuf,
+}
+
+/// Why an email wasn't sent.
+pub struct SendError {
+    pub message: String,
+    /// Sending it again won't help, such as when the server rejects the recipient
+    pub permanent: bool,
+}
+
+pub enum Transport {
+    Smtp(AsyncSmtpTransport<Tokio1Executor>),
+    File(AsyncFileTransport<Tokio1Executor>),
+}
+
+impl Transport {
+    pub fn new(options: &TransportOptions) -> Result<Self, Box<dyn std::error::Error>> {
+        match options.transport {
+            TransportKind::Smtp => {
+                let mut builder = match options.smtp_starttls {
+                    true => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&options.smtp_host)?,
+                    false => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&options.smtp_host),
+                }
+                .port(options.smtp_port);
+                if let (Some(username), Some(password)) = (&options.smtp_username, &options.smtp_password) {
+                    builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+                }
+                Ok(Transport::Smtp(builder.build()))
+            }
+            TransportKind::File => {
+                std::fs::create_dir_all(&options.drop_dir)?;
+                Ok(Transport::File(AsyncFileTransport::new(&options.drop_dir)))
+            }
+        }
+    }
+
+    pub async fn send(&self, message: Message) -> Result<(), SendError> {
+        match self {
+            Transport::Smtp(transport) => transport.send(message).await.map(|_| ()).map_err(|e| SendError {
+                permanent: e.is_permanent(),
+                message: e.to_string(),
+            }),
+            Transport::File(transport) => transport.send(message).await.map(|_| ()).map_err(|e| SendError {
+                permanent: false,
+                message: e.to_string(),
+            }),
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use chrono::{DateTime, Months, NaiveDate, Utc};
+use clap::{Args, ValueEnum};
+use common::client::{Client, CLIENT_COLUMNS};
+use common::command::Command;
+use common::delivery::Sender;
+use common::product::ProductFromDb;
+use common::rng::SharedRng;
+use futures::future::join_all;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rand_distr::{Distribution, Poisson};
+use schema_registry_converter::async_impl::schema_registry::SrSettings;
+use sqlx::PgPool;
+
+use crate::catalog::Catalog;
+use crate::command::Publisher;
+
+/// How the number of orders per day evolves from the start to the end of the backfill.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum GrowthCurve {
+    /// The start number every day
+    Flat,
+    /// The same increase every day
+    Linear,
+    /// The same relative increase every day
+    Exponential,
+    /// Slow, then fast in the middle, then slow again
+    Logistic,
+}
+
+impl GrowthCurve {
+    /// Number of orders per day once `progress` (0 to 1) of the backfill has elapsed.
+    fn daily_orders(&self, start: f64, end: f64, progress: f64) -> f64 {
+        match self {
+            GrowthCurve::Flat => start,
+            GrowthCurve::Linear => start + (end - start) * progress,
+            GrowthCurve::Exponential => start * (end / start).powf(progress),
+            GrowthCurve::Logistic => start + (end - start) / (1.0 + (-10.0 * (progress - 0.5)).exp()),
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct BackfillOptions {
+    /// If provided, generates this many months of past orders, up to yesterday, then exits
+    #[arg(long = "backfill", value_name = "MONTHS")]
+    pub months: Option<u32>,
+
+    /// Average number of orders per day at the start of the backfill
+    #[arg(long, default_value_t = 50.0)]
+    backfill_start_daily_orders: f64,
+
+    /// Average number of orders per day at the end of the backfill
+    #[arg(long, default_value_t = 500.0)]
+    backfill_end_daily_orders: f64,
+
+    #[arg(long, value_enum, default_value_t = GrowthCurve::Exponential)]
+    backfill_growth: GrowthCurve,
+
+    /// Number of orders inserted in each transaction
+    #[arg(long, default_value_t = 1000)]
+    backfill_batch_size: usize,
+}
+
+/// An order of the backfill, before it is inserted.
+struct PastOrder {
+    client_id: i32,
+    lines: Vec<(i32, i32)>,
+    at: DateTime<Utc>,
+}
+
+/// Generates the orders of the past months following the growth curve and the seasonality,
+/// inserts them in batches and publishes them with Kafka timestamps matching the order times.
+pub async fn run(
+    options: &BackfillOptions,
+    pool: &PgPool,
+    sender: &Sender,
+    sr_settings: &SrSettings,
+    rng: &SharedRng,
+    catalog: &Catalog,
+) -> Result<(), sqlx::Error> {
+    let publisher = Publisher::new(sender, sr_settings, false)?;
+    let mut rng = StdRng::seed_from_u64(rng.lock().gen());
+    let started = Instant::now();
+
+    let today = Utc::now().date_naive();
+    let first_day = today.checked_sub_months(Months::new(options.months.unwrap_or(0))).unwrap_or(today);
+    let days = (today - first_day).num_days().max(1) as f64;
+    println!("Backfilling orders from {} to {}", first_day, today.pred_opt().unwrap_or(today));
+
+    let mut total = 0;
+    let mut batch: Vec<PastOrder> = Vec::new();
+    for day in first_day.iter_days().take_while(|day| *day < today) {
+        let progress = (day - first_day).num_days() as f64 / days;
+        let expected = options.backfill_growth.daily_orders(
+            options.backfill_start_daily_orders,
+            options.backfill_end_daily_orders,
+            progress,
+        ) * catalog.options().day_weight(day);
+        let count = match Poisson::new(expected) {
+            Ok(poisson) => poisson.sample(&mut rng) as usize,
+            Err(_) => 0,
+        };
+
+        let mut orders = Vec::with_capacity(count);
+        for _ in 0..count {
+            let (client_id, lines) = catalog.pick(&mut rng).ok_or(sqlx::Error::RowNotFound)?;
+            let at = catalog.options().order_time(&mut rng, day);
+            orders.push(PastOrder { client_id, lines, at });
+        }
+        orders.sort_by_key(|order| order.at);
+        batch.extend(orders);
+
+        while batch.len() >= options.backfill_batch_size.max(1) {
+            let rest = batch.split_off(options.backfill_batch_size.max(1));
+            total += insert_and_publish(pool, &publisher, std::mem::replace(&mut batch, rest)).await?;
+            println!("Backfilled {} orders up to {} in {:.2?}", total, day, started.elapsed());
+        }
+    }
+    total += insert_and_publish(pool, &publisher, batch).await?;
+
+    println!("Backfilled {} orders in {:.2?}", total, started.elapsed());
+    Ok(())
+}
+
+/// Inserts the orders in a single transaction, then publishes them. Returns the number of orders.
+///
+/// Unlike live orders, past orders don't go through `inventory::reserve`: they were shipped long
+/// ago, and taking them out of today's stock would only empty it and backorder the live orders.
+async fn insert_and_publish(pool: &PgPool, publisher: &Publisher<'_>, orders: Vec<PastOrder>) -> Result<usize, sqlx::Error> {
+    if orders.is_empty() {
+        return Ok(0);
+    }
+
+    let mut tx = pool.begin().await?;
+
+    // Reserve the ids first, to insert the commands and their products with single statements
+    let ids: Vec<i32> =
+        sqlx::query_scalar("SELECT nextval(pg_get_serial_sequence('command', 'id'))::INT FROM generate_series(1, $1)")
+            .bind(orders.len() as i32)
+            .fetch_all(&mut *tx)
+            .await?;
+    let client_ids: Vec<i32> = orders.iter().map(|order| order.client_id).collect();
+    let dates: Vec<NaiveDate> = orders.iter().map(|order| order.at.date_naive()).collect();
+    sqlx::query(
+        "INSERT INTO Command (id, clientId, date, currency)
+        SELECT command.id, command.clientId, command.date, Client.currency
+        FROM UNNEST($1::INT[], $2::INT[], $3::DATE[]) AS command(id, clientId, date)
+        JOIN Client ON Client.id = command.clientId",
+    )
+    .bind(&ids)
+    .bind(&client_ids)
+    .bind(&dates)
+    .execute(&mut *tx)
+    .await?;
+
+    let (mut command_ids, mut product_ids, mut quantities) = (Vec::new(), Vec::new(), Vec::new());
+    for (id, order) in ids.iter().zip(&orders) {
+        for (product_id, quantity) in &order.lines {
+            command_ids.push(*id);
+            product_ids.push(*product_id);
+            quantities.push(*quantity);
+        }
+    }
+    sqlx::query(
+        "INSERT INTO CommandProduct (commandId, productId, quantity, price, currency)
+        SELECT line.commandId, line.productId, line.quantity, Product.price, Product.currency
+        FROM UNNEST($1::INT[], $2::INT[], $3::INT[]) AS line(commandId, productId, quantity)
+        JOIN Product ON Product.id = line.productId",
+    )
+    .bind(&command_ids)
+    .bind(&product_ids)
+    .bind(&quantities)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    let clients: HashMap<i32, Client> =
+        sqlx::query_as::<_, Client>(&format!("SELECT {} FROM Client WHERE id = ANY($1)", CLIENT_COLUMNS))
+            .bind(&client_ids)
+            .fetch_all(pool)
+            .await?
+            .into_iter()
+            .map(|client| (client.id, client))
+            .collect();
+    let products: HashMap<i32, ProductFromDb> =
+        sqlx::query_as::<_, ProductFromDb>("SELECT id, name, price, currency FROM Product WHERE id = ANY($1)")
+            .bind(&product_ids)
+            .fetch_all(pool)
+            .await?
+            .into_iter()
+            .map(|product| (product.id, product))
+            .collect();
+
+    let count = orders.len();
+    // The whole batch is in flight at once, so that the broker round trip isn't paid per order
+    let mut publications = Vec::with_capacity(count);
+    for (id, order) in ids.into_iter().zip(orders) {
+        let client = clients.get(&order.client_id).cloned().ok_or(sqlx::Error::RowNotFound)?;
+        let lines: Vec<(ProductFromDb, i32)> = order
+            .lines
+            .iter()
+            .filter_map(|(product_id, quantity)| Some((products.get(product_id)?.clone(), *quantity)))
+            .collect();
+        let command = Command {
+            id,
+            client_id: order.client_id,
+            date: order.at.format("%Y-%m-%d").to_string(),
+            size: lines.iter().map(|(_, quantity)| quantity).sum(),
+            client_version: client.version,
+            client_snapshot: Some(client.clone()),
+        };
+        let timestamp = Some(order.at.timestamp_millis());
+        publications.push(async move { publisher.publish(client, command, lines, &HashMap::new(), None, timestamp).await });
+    }
+    join_all(publications).await;
+    Ok(count)
+}
\ No newline at end of file
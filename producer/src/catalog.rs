@@ -0,0 +1,154 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use rand::distributions::WeightedIndex;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand_distr::{Distribution, Pareto, Zipf};
+use sqlx::PgPool;
+use tokio::sync::RwLock;
+
+use crate::distribution::DistributionOptions;
+
+/// Interval between two reloads of the client and product ids.
+pub const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The ids of the clients and products that random orders pick from, with their popularity.
+///
+/// The ids are cached instead of picking rows with `ORDER BY RANDOM()`, which scans the whole
+/// tables on every order.
+pub struct Catalog {
+    options: DistributionOptions,
+    clients: Vec<i32>,
+    client_weights: Option<WeightedIndex<f64>>,
+    /// Product ids from the most to the least popular
+    products: Vec<i32>,
+    product_popularity: Option<Zipf<f64>>,
+}
+
+pub type SharedCatalog = Arc<RwLock<Catalog>>;
+
+impl Catalog {
+    /// Loads the ids. The popularity of each client and product is drawn from `seed` and its id,
+    /// so that it stays the same across reloads, even when clients and products were added or
+    /// removed in between.
+    pub async fn load(pool: &PgPool, options: DistributionOptions, seed: u64) -> Result<Self, sqlx::Error> {
+        let clients: Vec<i32> = sqlx::query_scalar("SELECT id FROM Client ORDER BY id")
+            .fetch_all(pool)
+            .await?;
+        let products: Vec<i32> = sqlx::query_scalar("SELECT id FROM Product ORDER BY id")
+            .fetch_all(pool)
+            .await?;
+
+        // Heavy buyers get a weight drawn from the tail of the Pareto distribution
+        let client_weights = Pareto::new(1.0, options.client_pareto_shape).ok().and_then(|pareto| {
+            let weights = clients
+                .iter()
+                .map(|id| pareto.sample(&mut StdRng::seed_from_u64(seeded_hash(seed, *id))));
+            WeightedIndex::new(weights).ok()
+        });
+        let products = by_popularity(products, seed);
+        let product_popularity = Zipf::new(products.len() as u64, options.product_zipf_exponent).ok();
+
+        Ok(Catalog {
+            options,
+            clients,
+            client_weights,
+            products,
+            product_popularity,
+        })
+    }
+
+    pub fn options(&self) -> &DistributionOptions {
+        &self.options
+    }
+
+    /// Picks the client and the products of an order, with the quantity of each product.
+    /// Returns `None` while there are no clients or products.
+    pub fn pick(&self, rng: &mut StdRng) -> Option<(i32, Vec<(i32, i32)>)> {
+        let client = self.clients[self.client_weights.as_ref()?.sample(rng)];
+        let popularity = self.product_popularity.as_ref()?;
+
+        // A product drawn twice is ordered twice
+        let mut quantities: BTreeMap<usize, i32> = BTreeMap::new();
+        for _ in 0..self.options.basket_size(rng) {
+            let rank = popularity.sample(rng) as usize - 1;
+            *quantities.entry(rank).or_default() += 1;
+        }
+        let lines = quantities
+            .into_iter()
+            .map(|(rank, quantity)| (self.products[rank], quantity))
+            .collect();
+        Some((client, lines))
+    }
+
+    /// Picks any client, whatever its popularity.
+    pub fn random_client(&self, rng: &mut StdRng) -> Option<i32> {
+        self.clients.choose(rng).copied()
+    }
+
+    /// Picks any product, whatever its popularity.
+    pub fn random_product(&self, rng: &mut StdRng) -> Option<i32> {
+        self.products.choose(rng).copied()
+    }
+}
+
+/// Mixes `id` with `seed` (SplitMix64 finalizer), to draw the popularity of a client or product
+/// from its id alone.
+fn seeded_hash(seed: u64, id: i32) -> u64 {
+    let mut hash = seed ^ (id as u32 as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    hash ^ (hash >> 31)
+}
+
+/// Sorts the product ids from the most to the least popular. The best sellers are spread over
+/// the catalog rather than being the first ids, and two products keep their relative ranks when
+/// others are added or removed.
+fn by_popularity(mut products: Vec<i32>, seed: u64) -> Vec<i32> {
+    // Not the hash of the clients, so that client and product with the same id are unrelated
+    products.sort_by_key(|id| (seeded_hash(!seed, *id), *id));
+    products
+}
+
+/// Reloads the catalog every `REFRESH_INTERVAL`, to pick up new clients and products.
+pub async fn refresh_loop(pool: PgPool, catalog: SharedCatalog, seed: u64) {
+    loop {
+        tokio::time::sleep(REFRESH_INTERVAL).await;
+        let options = catalog.read().await.options.clone();
+        match Catalog::load(&pool, options, seed).await {
+            Ok(loaded) => *catalog.write().await = loaded,
+            Err(e) => eprintln!("Failed to reload the catalog: {:?}", e),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn popularity_ranks_survive_catalog_changes() {
+        let ranked = by_popularity((1..=100).collect(), 42);
+        assert_ne!(ranked, (1..=100).collect::<Vec<_>>());
+
+        // Removing products and adding new ones keeps the order of the remaining ones
+        let changed: Vec<i32> = (1..=100).filter(|id| id % 7 != 0).chain(101..=120).collect();
+        let reranked = by_popularity(changed, 42);
+        let kept: Vec<i32> = reranked.iter().copied().filter(|id| *id <= 100).collect();
+        let expected: Vec<i32> = ranked.iter().copied().filter(|id| id % 7 != 0).collect();
+        assert_eq!(kept, expected);
+
+        assert_ne!(by_popularity((1..=100).collect(), 43), ranked);
+    }
+
+    #[test]
+    fn seeded_hash_depends_on_the_seed_and_the_id() {
+        assert_eq!(seeded_hash(42, 7), seeded_hash(42, 7));
+        assert_ne!(seeded_hash(42, 7), seeded_hash(42, 8));
+        assert_ne!(seeded_hash(42, 7), seeded_hash(43, 7));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,400 @@
+use std::fs::{File, OpenOptions};
+use std::future::Future;
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use clap::Args;
+use common::client::Client;
+use common::command::Command;
+use common::product::Product;
+use rand::rngs::StdRng;
+use rand::Rng;
+use serde::Serialize;
+use tokio::task::JoinHandle;
+
+#[derive(Args, Clone, Debug)]
+pub struct ChaosOptions {
+    /// Probability that the products of an order are sent before its command
+    #[arg(long, default_value_t = 0.0)]
+    chaos_reorder: f64,
+
+    /// Probability that one message of an order is sent twice
+    #[arg(long, default_value_t = 0.0)]
+    chaos_duplicate: f64,
+
+    /// Probability that one message of an order is held back and sent after the others
+    #[arg(long, default_value_t = 0.0)]
+    chaos_delay: f64,
+
+    /// Longest delay of a held back message, in milliseconds
+    #[arg(long, default_value_t = 5000)]
+    chaos_max_delay_ms: u64,
+
+    /// Probability that one product of an order is never sent
+    #[arg(long, default_value_t = 0.0)]
+    chaos_drop_product: f64,
+
+    /// Probability that the published size of a command doesn't match its number of products
+    #[arg(long, default_value_t = 0.0)]
+    chaos_size_mismatch: f64,
+
+    /// Probability that one message of an order is sent with a payload that can't be decoded
+    #[arg(long, default_value_t = 0.0)]
+    chaos_corrupt: f64,
+
+    /// Probability that a command references a client that was never published
+    #[arg(long, default_value_t = 0.0)]
+    chaos_unknown_client: f64,
+
+    /// File the injected faults are appended to, one JSON object per line
+    #[arg(long, default_value = "chaos-manifest.jsonl")]
+    chaos_manifest: String,
+}
+
+impl ChaosOptions {
+    /// Whether any fault has a chance to be injected.
+    pub fn enabled(&self) -> bool {
+        [
+            self.chaos_reorder,
+            self.chaos_duplicate,
+            self.chaos_delay,
+            self.chaos_drop_product,
+            self.chaos_size_mismatch,
+            self.chaos_corrupt,
+            self.chaos_unknown_client,
+        ]
+        .iter()
+        .any(|probability| *probability > 0.0)
+    }
+}
+
+/// A message of an order, before it is encoded.
+#[derive(Clone, Debug)]
+pub enum Message {
+    Client(Client),
+    Command(Command),
+    Product(Product),
+}
+
+impl Message {
+    pub fn topic(&self) -> &'static str {
+        match self {
+            Message::Client(_) => "Client",
+            Message::Command(_) => "Command",
+            Message::Product(_) => "Product",
+        }
+    }
+}
+
+/// A message to publish, with the faults to apply when sending it.
+#[derive(Clone, Debug)]
+pub struct Outgoing {
+    pub message: Message,
+    /// The payload is replaced by bytes that aren't a valid Avro message
+    pub corrupt: bool,
+    /// The message is sent after the other messages of the order, once this delay has elapsed
+    pub delay: Option<Duration>,
+}
+
+impl From<Message> for Outgoing {
+    fn from(message: Message) -> Self {
+        Outgoing {
+            message,
+            corrupt: false,
+            delay: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Fault {
+    Reorder,
+    Duplicate,
+    Delay,
+    DropProduct,
+    SizeMismatch,
+    Corrupt,
+    UnknownClient,
+}
+
+#[derive(Debug, Serialize)]
+struct ManifestEntry<'a> {
+    at: String,
+    command_id: i32,
+    fault: Fault,
+    topic: Option<&'a str>,
+    detail: String,
+}
+
+/// Injects delivery faults in the messages of random orders and records each of them to the
+/// manifest.
+#[derive(Debug)]
+pub struct Chaos {
+    options: ChaosOptions,
+    manifest: Mutex<File>,
+    /// Tasks sending the held back messages
+    delayed: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl Chaos {
+    pub fn new(options: ChaosOptions) -> std::io::Result<Self> {
+        let manifest = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&options.chaos_manifest)?;
+        println!("Chaos mode enabled, injected faults are recorded to {}", options.chaos_manifest);
+        Ok(Chaos {
+            options,
+            manifest: Mutex::new(manifest),
+            delayed: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Runs `send` on its own task once `delay` has elapsed, so that holding a message back
+    /// doesn't hold back the orders published after it.
+    pub fn schedule(&self, delay: Duration, send: impl Future<Output = ()> + Send + 'static) {
+        let task = tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            send.await;
+        });
+        let mut delayed = self.delayed.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        delayed.retain(|task| !task.is_finished());
+        delayed.push(task);
+    }
+
+    /// Waits for the held back messages still to be sent.
+    pub async fn wait_delayed(&self) {
+        let tasks = std::mem::take(&mut *self.delayed.lock().unwrap_or_else(|poisoned| poisoned.into_inner()));
+        if !tasks.is_empty() {
+            println!("Waiting for {} held back messages", tasks.len());
+        }
+        for task in tasks {
+            if let Err(e) = task.await {
+                eprintln!("Failed to send a held back message: {}", e);
+            }
+        }
+    }
+
+    /// Applies the faults drawn with `rng` to the messages of an order, which start with the
+    /// client, then the command, then the products.
+    pub fn inject(&self, rng: &mut StdRng, command_id: i32, messages: &mut Vec<Outgoing>) {
+        let options = &self.options;
+
+        if rng.gen_bool(options.chaos_unknown_client.clamp(0.0, 1.0)) {
+            // Client ids are positive, so a negative id is never known to the merger
+            let unknown_id = -rng.gen_range(1..i32::MAX);
+            messages.retain(|outgoing| !matches!(outgoing.message, Message::Client(_)));
+            if let Some(command) = command_mut(messages) {
+                command.client_id = unknown_id;
+            }
+            self.record(command_id, Fault::UnknownClient, Some("Command"), format!("client_id set to {}", unknown_id));
+        }
+
+        if rng.gen_bool(options.chaos_size_mismatch.clamp(0.0, 1.0)) {
+            if let Some(command) = command_mut(messages) {
+                let size = command.size;
+                command.size = if size > 1 && rng.gen_bool(0.5) { size - 1 } else { size + 1 };
+                let detail = format!("size {} published as {}", size, command.size);
+                self.record(command_id, Fault::SizeMismatch, Some("Command"), detail);
+            }
+        }
+
+        if rng.gen_bool(options.chaos_drop_product.clamp(0.0, 1.0)) {
+            let products: Vec<usize> = product_indices(messages);
+            if !products.is_empty() {
+                let dropped = messages.remove(products[rng.gen_range(0..products.len())]);
+                if let Message::Product(product) = dropped.message {
+                    self.record(command_id, Fault::DropProduct, Some("Product"), format!("product {} not sent", product.id));
+                }
+            }
+        }
+
+        if rng.gen_bool(options.chaos_reorder.clamp(0.0, 1.0)) {
+            if let Some(index) = messages.iter().position(|outgoing| matches!(outgoing.message, Message::Command(_))) {
+                let command = messages.remove(index);
+                messages.push(command);
+                self.record(command_id, Fault::Reorder, Some("Command"), String::from("command sent after its products"));
+            }
+        }
+
+        if !messages.is_empty() && rng.gen_bool(options.chaos_duplicate.clamp(0.0, 1.0)) {
+            let index = rng.gen_range(0..messages.len());
+            let duplicate = messages[index].clone();
+            let topic = duplicate.message.topic();
+            messages.insert(index + 1, duplicate);
+            self.record(command_id, Fault::Duplicate, Some(topic), format!("message {} of the order sent twice", index));
+        }
+
+        if !messages.is_empty() && rng.gen_bool(options.chaos_delay.clamp(0.0, 1.0)) {
+            let index = rng.gen_range(0..messages.len());
+            let delay = Duration::from_millis(rng.gen_range(0..=options.chaos_max_delay_ms));
+            messages[index].delay = Some(delay);
+            let topic = messages[index].message.topic();
+            self.record(command_id, Fault::Delay, Some(topic), format!("message {} of the order delayed by {:?}", index, delay));
+        }
+
+        if !messages.is_empty() && rng.gen_bool(options.chaos_corrupt.clamp(0.0, 1.0)) {
+            let index = rng.gen_range(0..messages.len());
+            messages[index].corrupt = true;
+            let topic = messages[index].message.topic();
+            self.record(command_id, Fault::Corrupt, Some(topic), format!("message {} of the order corrupted", index));
+        }
+    }
+
+    fn record(&self, command_id: i32, fault: Fault, topic: Option<&str>, detail: String) {
+        let entry = ManifestEntry {
+            at: chrono::Utc::now().to_rfc3339(),
+            command_id,
+            fault,
+            topic,
+            detail,
+        };
+        let line = serde_json::to_string(&entry).unwrap();
+        println!("Injected fault: {}", line);
+        let mut manifest = self.manifest.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Err(e) = writeln!(manifest, "{}", line) {
+            eprintln!("Failed to record fault to the manifest: {}", e);
+        }
+    }
+}
+
+/// Replaces a payload by bytes without the schema registry magic byte, which no consumer can decode.
+pub fn corrupt(payload: &mut Vec<u8>) {
+    payload.truncate(payload.len() / 2);
+    payload.insert(0, 0xFF);
+}
+
+fn command_mut(messages: &mut [Outgoing]) -> Option<&mut Command> {
+    messages.iter_mut().find_map(|outgoing| match &mut outgoing.message {
+        Message::Command(command) => Some(command),
+        _ => None,
+    })
+}
+
+fn product_indices(messages: &[Outgoing]) -> Vec<usize> {
+    messages
+        .iter()
+        .enumerate()
+        .filter(|(_, outgoing)| matches!(outgoing.message, Message::Product(_)))
+        .map(|(index, _)| index)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use clap::Parser;
+    use rand::SeedableRng;
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        chaos: ChaosOptions,
+    }
+
+    fn chaos(name: &str, faults: &[&str]) -> (Chaos, std::path::PathBuf) {
+        let manifest = std::env::temp_dir().join(format!("{}-{}.jsonl", name, std::process::id()));
+        let _ = std::fs::remove_file(&manifest);
+        let mut args = vec![String::from("producer"), format!("--chaos-manifest={}", manifest.display())];
+        args.extend(faults.iter().map(|fault| format!("--chaos-{}=1", fault)));
+        (Chaos::new(Cli::parse_from(args).chaos).unwrap(), manifest)
+    }
+
+    fn order(command_id: i32, units: i32) -> Vec<Outgoing> {
+        let client = json!({"id": 7, "name": "Ada", "email": "ada@example.com", "address": "Paris"});
+        let command = json!({"id": command_id, "client_id": 7, "date": "2025-01-20", "size": units});
+        let product = json!({"id": 3, "name": "Tea", "price": 4.5, "command_id": command_id});
+        let mut messages = vec![
+            Message::Client(serde_json::from_value(client).unwrap()).into(),
+            Message::Command(serde_json::from_value(command).unwrap()).into(),
+        ];
+        for _ in 0..units {
+            messages.push(Message::Product(serde_json::from_value(product.clone()).unwrap()).into());
+        }
+        messages
+    }
+
+    fn manifest_lines(path: &std::path::Path) -> Vec<Value> {
+        let lines: Vec<Value> = std::fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        std::fs::remove_file(path).unwrap();
+        lines
+    }
+
+    #[test]
+    fn every_fault_is_recorded_to_the_manifest() {
+        let faults = ["unknown-client", "size-mismatch", "drop-product", "reorder", "duplicate", "delay", "corrupt"];
+        let (chaos, path) = chaos("chaos-all", &faults);
+        let mut messages = order(42, 3);
+        chaos.inject(&mut StdRng::seed_from_u64(1), 42, &mut messages);
+
+        let lines = manifest_lines(&path);
+        let recorded: Vec<&str> = lines.iter().map(|line| line["fault"].as_str().unwrap()).collect();
+        let expected = ["unknown_client", "size_mismatch", "drop_product", "reorder", "duplicate", "delay", "corrupt"];
+        assert_eq!(recorded, expected);
+        for line in &lines {
+            let mut keys: Vec<&str> = line.as_object().unwrap().keys().map(String::as_str).collect();
+            keys.sort_unstable();
+            assert_eq!(keys, ["at", "command_id", "detail", "fault", "topic"]);
+            assert_eq!(line["command_id"], 42);
+            assert!(chrono::DateTime::parse_from_rfc3339(line["at"].as_str().unwrap()).is_ok());
+            assert!(["Client", "Command", "Product"].contains(&line["topic"].as_str().unwrap()));
+            assert!(!line["detail"].as_str().unwrap().is_empty());
+        }
+
+        // The client is gone, one of the three products is dropped and a message is duplicated
+        assert!(!messages.iter().any(|outgoing| matches!(outgoing.message, Message::Client(_))));
+        assert_eq!(messages.len(), 1 + 2 + 1);
+        let command = messages.iter().rev().find_map(|outgoing| match &outgoing.message {
+            Message::Command(command) => Some(command),
+            _ => None,
+        });
+        assert!(command.unwrap().client_id < 0);
+        assert_eq!(messages.iter().filter(|outgoing| outgoing.delay.is_some()).count(), 1);
+        assert_eq!(messages.iter().filter(|outgoing| outgoing.corrupt).count(), 1);
+    }
+
+    #[test]
+    fn reorder_sends_the_command_last() {
+        let (chaos, path) = chaos("chaos-reorder", &["reorder"]);
+        let mut messages = order(43, 2);
+        chaos.inject(&mut StdRng::seed_from_u64(1), 43, &mut messages);
+
+        let topics: Vec<&str> = messages.iter().map(|outgoing| outgoing.message.topic()).collect();
+        assert_eq!(topics, ["Client", "Product", "Product", "Command"]);
+        let lines = manifest_lines(&path);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["fault"], "reorder");
+        assert_eq!(lines[0]["topic"], "Command");
+        assert_eq!(lines[0]["detail"], "command sent after its products");
+    }
+
+    #[tokio::test]
+    async fn held_back_messages_are_sent_by_their_own_task() {
+        let (chaos, path) = chaos("chaos-schedule", &[]);
+        let sent = Arc::new(AtomicUsize::new(0));
+        for delay in [30, 10] {
+            let sent = Arc::clone(&sent);
+            chaos.schedule(Duration::from_millis(delay), async move {
+                sent.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+        // Scheduling doesn't wait for the delays
+        assert_eq!(sent.load(Ordering::SeqCst), 0);
+
+        chaos.wait_delayed().await;
+        assert_eq!(sent.load(Ordering::SeqCst), 2);
+        assert!(chaos.delayed.lock().unwrap().is_empty());
+        std::fs::remove_file(path).unwrap();
+    }
+}
\ No newline at end of file
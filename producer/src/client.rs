@@ -1,37 +1,49 @@
 use async_trait::async_trait;
 use common::client::ClientInterface;
 use fake::{faker::name::en::Name, faker::internet::en::SafeEmail, faker::address::en::SecondaryAddress, Fake};
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
 use sqlx::PgPool;
 
+/// Currencies the clients are invoiced in, with their share of the clients.
+const CURRENCIES: [(&str, u32); 3] = [("EUR", 70), ("USD", 20), ("GBP", 10)];
+
 #[allow(dead_code)]
 pub struct MyClient {
     pub id: i32,
     pub name: String,
     pub email: String,
     pub address: String,
+    pub currency: String,
 }
 
 #[async_trait]
 impl ClientInterface for MyClient {
-    fn generate_random() -> Self {
+    fn generate_random(rng: &mut StdRng) -> Self {
+        let name = Name().fake_with_rng(rng);
+        let email = SafeEmail().fake_with_rng(rng);
+        let address = SecondaryAddress().fake_with_rng(rng);
+        let currency = WeightedIndex::new(CURRENCIES.iter().map(|(_, share)| share))
+            .map(|shares| CURRENCIES[shares.sample(rng)].0)
+            .unwrap_or("EUR");
 
         MyClient {
             id: 0, // This will be set by the database
-            name: Name().fake(),
-            email: SafeEmail().fake(),
-            address: SecondaryAddress().fake(),
+            name,
+            email,
+            address,
+            currency: currency.to_string(),
         }
     }
 
     async fn insert_into_db(&self, pool: &PgPool) -> Result<(), sqlx::Error> {
-        sqlx::query!(
-            "INSERT INTO Client (name, email, address) VALUES ($1, $2, $3)",
-            self.name,
-            self.email,
-            self.address
-        )
-        .execute(pool)
-        .await?;
+        sqlx::query("INSERT INTO Client (name, email, address, currency) VALUES ($1, $2, $3, $4)")
+            .bind(&self.name)
+            .bind(&self.email)
+            .bind(&self.address)
+            .bind(&self.currency)
+            .execute(pool)
+            .await?;
         Ok(())
     }
 }
\ No newline at end of file
@@ -1,8 +1,12 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
 use async_trait::async_trait;
-use chrono::DateTime;
-use common::client::Client;
-use rand::Rng;
-use rdkafka::producer::{BaseProducer, BaseRecord};
+use chrono::{DateTime, Utc};
+use futures::future::join_all;
+use common::client::{Client, CLIENT_COLUMNS};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use schema_registry_converter::async_impl::avro::AvroEncoder;
 use schema_registry_converter::async_impl::schema_registry::SrSettings;
 use schema_registry_converter::schema_registry_common::SubjectNameStrategy;
@@ -11,35 +15,193 @@ use serde_avro_fast::Schema;
 use sqlx::PgPool;
 
 use common::command::{Command, CommandFromDb, CommandInterface};
+use common::delivery::Sender;
+use common::inventory::StockChange;
 use common::product::{Product, ProductFromDb};
 
+use crate::catalog::Catalog;
+use crate::chaos::{self, Chaos, Message, Outgoing};
+use crate::inventory::{self, OutOfStock};
+
 #[derive(Debug)]
 #[allow(dead_code)]
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct MyCommand {
     client_id: i32,
     date: DateTime<chrono::Utc>,
+    /// Product ids and quantities
+    lines: Vec<(i32, i32)>,
+    /// Seed of the client and product picks, drawn from the shared generator so that the picks
+    /// don't depend on the order in which concurrent workers use the catalog
+    pick_seed: u64,
+    #[serde(skip)]
+    chaos: Option<Arc<Chaos>>,
+}
+
+impl MyCommand {
+    /// Injects delivery faults when publishing the command.
+    pub fn with_chaos(mut self, chaos: Arc<Chaos>) -> Self {
+        self.chaos = Some(chaos);
+        self
+    }
+
+    /// Picks the client and the products of the command from the catalog, and dates it with the
+    /// clock of the random orders.
+    pub fn pick(&mut self, catalog: &Catalog) -> Result<(), sqlx::Error> {
+        self.date = catalog.options().now();
+        let mut rng = StdRng::seed_from_u64(self.pick_seed);
+        let (client_id, lines) = catalog.pick(&mut rng).ok_or(sqlx::Error::RowNotFound)?;
+        self.client_id = client_id;
+        self.lines = lines;
+        Ok(())
+    }
 }
 
 #[async_trait]
 impl CommandInterface for MyCommand {
-    fn generate_random() -> Self {
+    fn generate_random(rng: &mut StdRng) -> Self {
         MyCommand {
-            client_id: 0, //This will be set randomly
+            client_id: 0, //This will be set by `pick`
             date: chrono::Utc::now(),
+            lines: Vec::new(),
+            pick_seed: rng.gen(),
+            chaos: None,
         }
     }
 
     async fn process_command(
         &self,
         pool: &PgPool,
-        producer: &BaseProducer,
+        sender: &Sender,
         sr_settings: &SrSettings,
     ) -> Result<(), sqlx::Error> {
-        let encoder = AvroEncoder::new((*sr_settings).clone());
+        let client_object = sqlx::query_as::<_, Client>(&format!("SELECT {} FROM Client WHERE id = $1", CLIENT_COLUMNS))
+            .bind(self.client_id)
+            .fetch_one(pool)
+            .await?;
+
+        let product_ids: Vec<i32> = self.lines.iter().map(|(id, _)| *id).collect();
+        let mut products_from_db: HashMap<i32, ProductFromDb> =
+            sqlx::query_as::<_, ProductFromDb>("SELECT id, name, price, currency FROM Product WHERE id = ANY($1)")
+                .bind(&product_ids)
+                .fetch_all(pool)
+                .await?
+                .into_iter()
+                .map(|product| (product.id, product))
+                .collect();
+
+        let lines = self
+            .lines
+            .iter()
+            .filter_map(|(id, quantity)| Some((products_from_db.remove(id)?, *quantity)))
+            .collect();
+        // The faults are drawn from the seed of the picks, to be reproducible as well
+        let mut chaos_rng = StdRng::seed_from_u64(self.pick_seed.wrapping_add(1));
+        let chaos = self.chaos.as_deref().map(|chaos| (chaos, &mut chaos_rng));
+        let placed = place_order(pool, sender, sr_settings, client_object, self.date, lines, chaos).await?;
+        if let Err(out_of_stock) = placed {
+            println!("Order of client {} rejected. {}", self.client_id, out_of_stock);
+        }
+
+        Ok(())
+    }
+}
+
+/// Inserts an order for `client` with the given products and quantities, then publishes the
+/// client, the command and one product message per ordered unit.
+///
+/// The order is dated from `time`, which is also the Kafka timestamp of the published messages.
+///
+/// The size of the published command is the total number of units, which is the number of
+/// product messages the merger waits for before emitting the invoice.
+///
+/// With `chaos`, delivery faults drawn from the given generator are injected in the published
+/// messages. The database always holds the order as placed.
+///
+/// The units are reserved in the stock within the same transaction. The order is rejected with
+/// `OutOfStock` if a product is short and can't be backordered, and nothing is inserted.
+pub async fn place_order(
+    pool: &PgPool,
+    sender: &Sender,
+    sr_settings: &SrSettings,
+    client_object: Client,
+    time: DateTime<Utc>,
+    lines: Vec<(ProductFromDb, i32)>,
+    chaos: Option<(&Chaos, &mut StdRng)>,
+) -> Result<Result<Command, OutOfStock>, sqlx::Error> {
+    let date = time.date_naive();
+    let publisher = Publisher::new(sender, sr_settings, true)?;
+    let size = lines
+        .iter()
+        .try_fold(0i32, |size, (_, quantity)| size.checked_add(*quantity))
+        .ok_or_else(|| sqlx::Error::Protocol(String::from("Order size overflows")))?;
+
+    let mut tx = pool.begin().await?;
 
-        //retrieve random products from database
-        let product_limit: i32 = rand::thread_rng().gen_range(1..=10);
+    let command_from_db = sqlx::query_as!(
+        CommandFromDb,
+        r#"
+            INSERT INTO Command (clientId, date, currency)
+            VALUES ($1, $2, $3)
+            RETURNING id, clientId AS "client_id", COALESCE(TO_CHAR(date, 'YYYY-MM-DD'), '') AS "date!"
+            "#,
+        client_object.id,
+        date,
+        client_object.currency
+    )
+        .fetch_one(&mut *tx)
+        .await?;
+
+    let reservation = match inventory::reserve(&mut tx, &lines).await? {
+        Ok(reservation) => reservation,
+        // Dropping the transaction rolls the command back
+        Err(out_of_stock) => return Ok(Err(out_of_stock)),
+    };
+
+    for (product, quantity) in &lines {
+        sqlx::query(
+            "INSERT INTO CommandProduct (commandId, productId, quantity, backordered, price, currency)
+            VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+        .bind(command_from_db.id)
+        .bind(product.id)
+        .bind(quantity)
+        .bind(reservation.backordered.get(&product.id).copied().unwrap_or_default())
+        .bind(product.price)
+        .bind(&product.currency)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    let mut command = Command::from((command_from_db, size));
+    command.client_version = client_object.version;
+    command.client_snapshot = Some(client_object.clone());
+    let timestamp = Some(time.timestamp_millis());
+    publisher.publish(client_object, command.clone(), lines, &reservation.backordered, chaos, timestamp).await;
+    publisher.publish_stock(reservation.changes).await;
+
+    Ok(Ok(command))
+}
+
+/// Schemas used to print the produced messages.
+struct Schemas {
+    client: Schema,
+    command: Schema,
+    product: Schema,
+}
+
+/// Encodes and sends the messages of orders.
+pub struct Publisher<'a> {
+    sender: &'a Sender,
+    encoder: AvroEncoder<'a>,
+    /// Only set when every produced message is printed
+    schemas: Option<Schemas>,
+}
+
+impl<'a> Publisher<'a> {
+    pub fn new(sender: &'a Sender, sr_settings: &SrSettings, verbose: bool) -> Result<Self, sqlx::Error> {
         let client_schema: Schema = match Client::schema() {
             Ok(schema) => schema,
             Err(e) => {
@@ -62,113 +224,141 @@ impl CommandInterface for MyCommand {
             }
         };
 
-        //retrieve a random client from the database
-        let client_object = sqlx::query_as!(
-            Client,
-            "SELECT * FROM Client ORDER BY RANDOM() LIMIT 1"
-        )
-            .fetch_one(pool)
-            .await?;
+        Ok(Publisher {
+            sender,
+            encoder: AvroEncoder::new(sr_settings.clone()),
+            schemas: verbose.then_some(Schemas {
+                client: client_schema,
+                command: command_schema,
+                product: product_schema,
+            }),
+        })
+    }
 
-        let command_from_db = sqlx::query_as!(
-            CommandFromDb,
-            r#"
-            INSERT INTO Command (clientId, date)
-            VALUES ($1, $2)
-            RETURNING id, clientId AS "client_id", COALESCE(TO_CHAR(date, 'YYYY-MM-DD'), '') AS "date!"
-            "#,
-            client_object.id,
-            self.date.naive_utc().date()
-        )
-            .fetch_one(pool)
-            .await?;
+    /// Publishes the client, the command and one product message per ordered unit, with the
+    /// given Kafka timestamp in milliseconds, or the current time. The last units of each product
+    /// counted in `backordered` are marked as backordered.
+    pub async fn publish(
+        &self,
+        client_object: Client,
+        command: Command,
+        lines: Vec<(ProductFromDb, i32)>,
+        backordered: &HashMap<i32, i32>,
+        chaos: Option<(&Chaos, &mut StdRng)>,
+        timestamp: Option<i64>,
+    ) {
+        let command_id = command.id;
+        let mut messages: Vec<Outgoing> = vec![
+            Message::Client(client_object).into(),
+            Message::Command(command).into(),
+        ];
+        for (product, quantity) in lines {
+            let backordered = backordered.get(&product.id).copied().unwrap_or_default().clamp(0, quantity);
+            let product = Product::from((product, command_id));
+            let backordered_product = Product { backordered: true, ..product.clone() };
+            messages.extend(std::iter::repeat_n(Message::Product(product).into(), (quantity - backordered) as usize));
+            messages.extend(std::iter::repeat_n(Message::Product(backordered_product).into(), backordered as usize));
+        }
+        // Numbered before the faults are injected, so that the merger drops a duplicated unit
+        let products = messages.iter_mut().filter_map(|outgoing| match &mut outgoing.message {
+            Message::Product(product) => Some(product),
+            _ => None,
+        });
+        for (unit, product) in products.enumerate() {
+            product.unit = Some(unit as i32);
+        }
 
-        let command = Command::from((command_from_db, product_limit));
+        let chaos = chaos.map(|(chaos, rng)| {
+            chaos.inject(rng, command_id, &mut messages);
+            chaos
+        });
 
-        let products_from_db = sqlx::query_as!(
-            ProductFromDb,
-            r#"SELECT id, name, price
-            FROM Product
-            ORDER BY RANDOM()
-            LIMIT $1"#,
-            product_limit as i64
-        )
-            .fetch_all(pool)
-            .await?;
+        // The messages are enqueued in order and acknowledged concurrently. Held back messages
+        // are encoded now and sent by their own task once their delay has elapsed, so that the
+        // worker goes on with the next orders
+        let (delayed, messages): (Vec<Outgoing>, Vec<Outgoing>) =
+            messages.into_iter().partition(|outgoing| outgoing.delay.is_some());
+        join_all(messages.into_iter().map(|outgoing| self.send_message(outgoing, timestamp))).await;
+        for outgoing in delayed {
+            let delay = outgoing.delay.unwrap_or_default();
+            let encoded = self.encode(outgoing).await;
+            let sender = self.sender.clone();
+            if let Some(chaos) = chaos {
+                chaos.schedule(delay, async move { encoded.deliver(&sender, timestamp).await });
+            }
+        }
+    }
 
-        let products: Vec<Product> = products_from_db
-            .into_iter()
-            .map(|product| Product::from((product, command.id)))
-            .collect();
+    /// Publishes the stock changes of an order to the `StockLevel` topic.
+    pub async fn publish_stock(&self, changes: Vec<StockChange>) {
+        inventory::publish(self.sender, &self.encoder, changes).await;
+    }
 
-        let client_payload = encoder
-            .encode_struct(
-                &client_object,
-                &SubjectNameStrategy::TopicNameStrategy("Client".to_string(), false),
-            )
-            .await
-            .expect("Failed to encode client object");
-
-        let command_payload = encoder
-            .encode_struct(
-                &command,
-                &SubjectNameStrategy::TopicNameStrategy("Command".to_string(), false),
-            )
-            .await
-            .expect("Failed to encode command object");
-
-
-
-        if let Err(e) = producer.send(
-            BaseRecord::to("Client")
-                .payload(&client_payload)
-                .key(&client_object.id.to_string()),
-        ) {
-            eprintln!("Failed to send message: {:?}", e);
-        } else {
-            let message = serde_avro_fast::from_datum_slice::<Client>(&client_payload, &client_schema).unwrap();
-
-            println!("Message produced in Client: {}", serde_json::to_string(&message).unwrap());
-        }
+    async fn send_message(&self, outgoing: Outgoing, timestamp: Option<i64>) {
+        self.encode(outgoing).await.deliver(self.sender, timestamp).await;
+    }
 
-        if let Err(e) = producer.send(
-            BaseRecord::to("Command")
-                .payload(&command_payload)
-                .key(&command.id.to_string()),
-        ) {
-            eprintln!("Failed to send message: {:?}", e);
-        } else {
-            let message = serde_avro_fast::from_datum_slice::<Command>(&command_payload, &command_schema).unwrap();
-            println!("Message produced in Command: {}", serde_json::to_string(&message).unwrap());
+    /// Encodes the message, applying its corruption, along with the line printed once delivered.
+    async fn encode(&self, outgoing: Outgoing) -> Encoded {
+        let topic = outgoing.message.topic();
+        let strategy = SubjectNameStrategy::TopicNameStrategy(topic.to_string(), false);
+        let (key, payload) = match &outgoing.message {
+            Message::Client(client) => (client.id, self.encoder.encode_struct(client, &strategy).await),
+            Message::Command(command) => (command.id, self.encoder.encode_struct(command, &strategy).await),
+            Message::Product(product) => (product.id, self.encoder.encode_struct(product, &strategy).await),
+        };
+        let mut payload = match payload {
+            Ok(payload) => payload,
+            Err(e) => panic!("Failed to encode {} object: {:?}", topic.to_lowercase(), e),
+        };
+        if outgoing.corrupt {
+            chaos::corrupt(&mut payload);
         }
 
-        for product in products {
-            sqlx::query!(
-                "INSERT INTO CommandProduct (commandId, productId) VALUES ($1, $2)",
-                command.id,
-                product.id
-            )
-                .execute(pool)
-                .await?;
-            let product_payload = encoder
-                .encode_struct(
-                    &product,
-                    &SubjectNameStrategy::TopicNameStrategy("Product".to_string(), false),
-                )
-                .await
-                .expect("Failed to encode product object");
-            if let Err(e) = producer.send(
-                BaseRecord::to("Product")
-                    .payload(&product_payload)
-                    .key(&product.id.to_string()),
-            ) {
-                eprintln!("Failed to send message: {:?}", e);
-            } else {
-                let message = serde_avro_fast::from_datum_slice::<Product>(&product_payload, &product_schema).unwrap();
-                println!("Message produced in Product: {}", serde_json::to_string(&message).unwrap());
+        let log = match (&self.schemas, outgoing.corrupt) {
+            (None, _) => None,
+            (Some(_), true) => Some(format!("Corrupted message produced in {}", topic)),
+            (Some(schemas), false) => {
+                let message = match outgoing.message {
+                    Message::Client(_) => serde_json::to_string(
+                        &serde_avro_fast::from_datum_slice::<Client>(&payload, &schemas.client).unwrap(),
+                    ),
+                    Message::Command(_) => serde_json::to_string(
+                        &serde_avro_fast::from_datum_slice::<Command>(&payload, &schemas.command).unwrap(),
+                    ),
+                    Message::Product(_) => serde_json::to_string(
+                        &serde_avro_fast::from_datum_slice::<Product>(&payload, &schemas.product).unwrap(),
+                    ),
+                };
+                Some(format!("Message produced in {}: {}", topic, message.unwrap()))
             }
+        };
+        Encoded {
+            topic,
+            key: key.to_string(),
+            payload,
+            log,
         }
-
-        Ok(())
     }
 }
+
+/// A message ready to be sent.
+struct Encoded {
+    topic: &'static str,
+    key: String,
+    payload: Vec<u8>,
+    /// Printed once the message is delivered, when every produced message is printed
+    log: Option<String>,
+}
+
+impl Encoded {
+    async fn deliver(self, sender: &Sender, timestamp: Option<i64>) {
+        if let Err(e) = sender.send(self.topic, &self.key, &self.payload, timestamp).await {
+            eprintln!("Failed to deliver message to {}: {:?}", self.topic, e);
+            return;
+        }
+        if let Some(log) = self.log {
+            println!("{}", log);
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,128 @@
+use chrono::{DateTime, Datelike, NaiveDate, NaiveTime, TimeDelta, Timelike, Utc};
+use clap::{Args, ValueEnum};
+use rand::distributions::WeightedIndex;
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand_distr::{Distribution, Geometric, Poisson};
+
+/// Largest number of units in a random order.
+pub const MAX_BASKET_SIZE: usize = 20;
+
+/// Relative number of orders for each hour of the day (UTC): quiet nights, a lunch peak and an
+/// evening peak.
+const HOURLY_WEIGHTS: [f64; 24] = [
+    0.15, 0.08, 0.05, 0.04, 0.04, 0.06, 0.15, 0.30, 0.45, 0.55, 0.60, 0.70, //
+    0.85, 0.80, 0.65, 0.60, 0.62, 0.70, 0.85, 0.95, 1.00, 0.90, 0.60, 0.30,
+];
+
+/// Relative number of orders for each day of the week, from Monday to Sunday.
+const WEEKDAY_WEIGHTS: [f64; 7] = [0.85, 0.80, 0.80, 0.85, 0.95, 1.00, 0.90];
+
+/// Parses a finite number above zero, such as a rate or a time scale.
+pub fn positive(value: &str) -> Result<f64, String> {
+    let number: f64 = value.parse().map_err(|e| format!("{}", e))?;
+    if number.is_finite() && number > 0.0 {
+        Ok(number)
+    } else {
+        Err(String::from("must be a number greater than 0"))
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum BasketDistribution {
+    /// Between 1 and 10 units, all equally likely
+    Uniform,
+    /// Mostly small orders, with a long tail of large ones
+    Geometric,
+    /// Orders concentrated around the mean
+    Poisson,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct DistributionOptions {
+    /// Exponent of the Zipf distribution of product popularity. The higher, the more the orders
+    /// concentrate on a few best sellers
+    #[arg(long, default_value_t = 1.1)]
+    pub product_zipf_exponent: f64,
+
+    /// Shape of the Pareto distribution of client purchase frequency. The lower, the more the
+    /// orders come from a few heavy buyers
+    #[arg(long, default_value_t = 1.2)]
+    pub client_pareto_shape: f64,
+
+    /// Distribution of the number of units in an order
+    #[arg(long, value_enum, default_value_t = BasketDistribution::Geometric)]
+    pub basket: BasketDistribution,
+
+    /// Mean number of units in an order, for the geometric and Poisson basket distributions
+    #[arg(long, default_value_t = 3.0)]
+    pub basket_mean: f64,
+
+    /// If provided, backfilled orders and the rate of `--seasonal-pacing` are the same at any time
+    /// instead of following the daily and weekly seasonality
+    #[arg(long)]
+    pub no_seasonality: bool,
+
+    /// Speed of the clock of the random orders compared to the wall clock. With 1440, a day of
+    /// orders, with its peaks, goes by in a minute and the orders are dated accordingly
+    #[arg(long, default_value_t = 1.0, value_parser = positive)]
+    pub time_scale: f64,
+
+    /// Wall clock time at which the clock of the random orders starts
+    #[arg(skip = Utc::now())]
+    pub started_at: DateTime<Utc>,
+}
+
+impl DistributionOptions {
+    /// Draws the number of units of an order, between 1 and `MAX_BASKET_SIZE`.
+    pub fn basket_size(&self, rng: &mut StdRng) -> usize {
+        let mean = self.basket_mean.max(1.0);
+        let size = match self.basket {
+            BasketDistribution::Uniform => rng.gen_range(1..=10),
+            // Number of failures before a success, shifted to start at 1
+            BasketDistribution::Geometric => match Geometric::new(1.0 / mean) {
+                Ok(geometric) => geometric.sample(rng) as usize + 1,
+                Err(_) => 1,
+            },
+            BasketDistribution::Poisson => match Poisson::new(mean) {
+                Ok(poisson) => poisson.sample(rng) as usize,
+                Err(_) => 1,
+            },
+        };
+        size.clamp(1, MAX_BASKET_SIZE)
+    }
+
+    /// Current time of the random orders, which runs `time_scale` times faster than the wall clock.
+    pub fn now(&self) -> DateTime<Utc> {
+        let elapsed = (Utc::now() - self.started_at).as_seconds_f64() * self.time_scale;
+        self.started_at + TimeDelta::milliseconds((elapsed * 1000.0) as i64)
+    }
+
+    /// Fraction of the peak order rate at `date`, or 1 without seasonality.
+    pub fn seasonality(&self, date: DateTime<Utc>) -> f64 {
+        if self.no_seasonality {
+            return 1.0;
+        }
+        HOURLY_WEIGHTS[date.hour() as usize] * WEEKDAY_WEIGHTS[date.weekday().num_days_from_monday() as usize]
+    }
+
+    /// Relative number of orders on `date` compared to an average day, or 1 without seasonality.
+    pub fn day_weight(&self, date: NaiveDate) -> f64 {
+        if self.no_seasonality {
+            return 1.0;
+        }
+        let average = WEEKDAY_WEIGHTS.iter().sum::<f64>() / WEEKDAY_WEIGHTS.len() as f64;
+        WEEKDAY_WEIGHTS[date.weekday().num_days_from_monday() as usize] / average
+    }
+
+    /// Draws the time of an order placed on `date`, following the daily seasonality.
+    pub fn order_time(&self, rng: &mut StdRng, date: NaiveDate) -> DateTime<Utc> {
+        let hour = match self.no_seasonality {
+            true => rng.gen_range(0..24),
+            false => WeightedIndex::new(HOURLY_WEIGHTS).map(|hours| hours.sample(rng)).unwrap_or(12),
+        };
+        let seconds = hour as u32 * 3600 + rng.gen_range(0..3600);
+        let time = NaiveTime::from_num_seconds_from_midnight_opt(seconds, 0).unwrap_or_default();
+        date.and_time(time).and_utc()
+    }
+}
\ No newline at end of file
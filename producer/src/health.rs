@@ -0,0 +1,31 @@
+use common::health::{kafka_connected, subjects_registered, Readiness, PROBE_INTERVAL};
+use common::delivery::Sender;
+use rdkafka::producer::Producer;
+use schema_registry_converter::async_impl::schema_registry::SrSettings;
+use sqlx::PgPool;
+
+pub const CHECKS: [&str; 3] = ["postgres", "schema_registry", "kafka"];
+
+/// Periodically refreshes the readiness checks of the producer.
+pub async fn probe_loop(
+    pool: PgPool,
+    sender: Sender,
+    sr_settings: SrSettings,
+    readiness: Readiness,
+) {
+    loop {
+        let postgres = sqlx::query("SELECT 1").execute(&pool).await.is_ok();
+        readiness.set("postgres", postgres);
+
+        let registered = subjects_registered(&sr_settings, &["Client", "Product", "Command"]).await;
+        readiness.set("schema_registry", registered);
+
+        let sender_clone = sender.clone();
+        let kafka = tokio::task::spawn_blocking(move || kafka_connected(sender_clone.producer().client()))
+            .await
+            .unwrap_or(false);
+        readiness.set("kafka", kafka);
+
+        tokio::time::sleep(PROBE_INTERVAL).await;
+    }
+}
@@ -0,0 +1,453 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use clap::{Args, ValueEnum};
+use common::client::{Client, CLIENT_COLUMNS};
+use common::currency::{BASE_CURRENCY, CURRENCIES};
+use common::delivery::Sender;
+use common::product::{CatalogProduct, CATALOG_PRODUCT_COLUMNS};
+use schema_registry_converter::async_impl::avro::AvroEncoder;
+use schema_registry_converter::async_impl::schema_registry::SrSettings;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use sqlx::{PgConnection, PgPool};
+
+use crate::seed::publish_all;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ImportKind {
+    Clients,
+    Products,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum FileFormat {
+    Csv,
+    Jsonl,
+}
+
+#[derive(Args, Debug)]
+pub struct ImportArgs {
+    /// What the file holds
+    kind: ImportKind,
+
+    /// CSV file with a header row, or JSON Lines file
+    file: PathBuf,
+
+    /// Format of the file, guessed from its extension if not provided
+    #[arg(long, value_enum)]
+    format: Option<FileFormat>,
+}
+
+/// A client row. Rows with an id update the client with this id, or create it. Rows without one
+/// update the client with the same email, or create it, and are rejected if several clients have
+/// this email.
+#[derive(Debug, Deserialize)]
+struct ClientRecord {
+    id: Option<i32>,
+    name: String,
+    email: String,
+    address: String,
+    currency: Option<String>,
+}
+
+/// A product row. Rows with an id update the product with this id, or create it. Rows without
+/// one update the product with the same name, or create it, and are rejected if several products
+/// have this name.
+#[derive(Debug, Deserialize)]
+struct ProductRecord {
+    id: Option<i32>,
+    name: String,
+    price: f64,
+    category: Option<String>,
+    currency: Option<String>,
+}
+
+/// The currency of a row, the base currency if it has none.
+fn currency(currency: &Option<String>) -> String {
+    currency.as_deref().unwrap_or(BASE_CURRENCY).trim().to_uppercase()
+}
+
+fn validate_currency(value: &Option<String>) -> Result<(), String> {
+    if !CURRENCIES.contains(&currency(value).as_str()) {
+        return Err(format!("currency {:?} is not one of {}", value.as_deref().unwrap_or_default(), CURRENCIES.join(", ")));
+    }
+    Ok(())
+}
+
+/// Line number of a rejected row, and the reason.
+type Reject = (u64, String);
+
+/// A valid row and its line number.
+type Row<T> = (u64, T);
+
+trait Record {
+    fn id(&self) -> Option<i32>;
+    /// The natural key, matched case-insensitively, with its name for the rejection reason.
+    fn key(&self) -> (&'static str, String);
+    fn validate(&self) -> Result<(), String>;
+}
+
+impl Record for ClientRecord {
+    fn id(&self) -> Option<i32> {
+        self.id
+    }
+
+    fn key(&self) -> (&'static str, String) {
+        ("email", self.email.trim().to_lowercase())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err(String::from("name is empty"));
+        }
+        if !self.email.contains('@') {
+            return Err(format!("email {:?} is not valid", self.email));
+        }
+        if self.address.trim().is_empty() {
+            return Err(String::from("address is empty"));
+        }
+        validate_currency(&self.currency)
+    }
+}
+
+impl Record for ProductRecord {
+    fn id(&self) -> Option<i32> {
+        self.id
+    }
+
+    fn key(&self) -> (&'static str, String) {
+        ("name", self.name.trim().to_lowercase())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err(String::from("name is empty"));
+        }
+        if !self.price.is_finite() || self.price <= 0.0 {
+            return Err(format!("price {} is not positive", self.price));
+        }
+        if self.category.as_ref().is_some_and(|category| category.trim().is_empty()) {
+            return Err(String::from("category is empty"));
+        }
+        validate_currency(&self.currency)
+    }
+}
+
+/// Loads clients or products from a file, upserts the valid rows and publishes them to the
+/// compacted `Client` or `ProductCatalog` topic. Rejected rows are reported with their line number.
+pub async fn run(
+    args: &ImportArgs,
+    pool: &PgPool,
+    sender: &Sender,
+    sr_settings: &SrSettings,
+) -> Result<(), sqlx::Error> {
+    let format = match args.format {
+        Some(format) => format,
+        None => match args.file.extension().and_then(|extension| extension.to_str()) {
+            Some("csv") => FileFormat::Csv,
+            _ => FileFormat::Jsonl,
+        },
+    };
+    let encoder = AvroEncoder::new(sr_settings.clone());
+
+    let (imported, mut rejects) = match args.kind {
+        ImportKind::Clients => {
+            let (records, mut rejects) = read_records::<ClientRecord>(&args.file, format)?;
+            let (clients, ambiguous) = upsert_clients(pool, records).await?;
+            rejects.extend(ambiguous);
+            let clients = futures::stream::iter(clients.into_iter().map(Ok));
+            let published = publish_all(sender, &encoder, "Client", clients, |client| client.id).await?;
+            (published, rejects)
+        }
+        ImportKind::Products => {
+            let (records, mut rejects) = read_records::<ProductRecord>(&args.file, format)?;
+            let (products, ambiguous) = upsert_products(pool, records).await?;
+            rejects.extend(ambiguous);
+            let products = futures::stream::iter(products.into_iter().map(Ok));
+            let published = publish_all(sender, &encoder, "ProductCatalog", products, |product| product.id).await?;
+            (published, rejects)
+        }
+    };
+
+    rejects.sort_by_key(|(line, _)| *line);
+    for (line, reason) in &rejects {
+        eprintln!("Rejected line {}: {}", line, reason);
+    }
+    println!("{} rows imported from {}, {} rejected", imported, args.file.display(), rejects.len());
+    Ok(())
+}
+
+/// Reads and validates the rows of the file. Returns the valid rows with their line number and the
+/// rejected line numbers with the reason.
+fn read_records<T: Record + DeserializeOwned>(
+    path: &Path,
+    format: FileFormat,
+) -> Result<(Vec<Row<T>>, Vec<Reject>), sqlx::Error> {
+    let mut rows: Vec<(u64, Result<T, String>)> = Vec::new();
+    match format {
+        FileFormat::Csv => {
+            let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::All).from_reader(File::open(path)?);
+            let headers = reader.headers().map_err(|e| sqlx::Error::Decode(Box::new(e)))?.clone();
+            for record in reader.records() {
+                match record {
+                    Ok(record) => {
+                        let line = record.position().map(|position| position.line()).unwrap_or_default();
+                        rows.push((line, record.deserialize(Some(&headers)).map_err(|e| e.to_string())));
+                    }
+                    Err(e) => {
+                        let line = e.position().map(|position| position.line()).unwrap_or_default();
+                        rows.push((line, Err(e.to_string())));
+                    }
+                }
+            }
+        }
+        FileFormat::Jsonl => {
+            let reader = BufReader::new(File::open(path)?);
+            for (index, line) in reader.lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                rows.push((index as u64 + 1, serde_json::from_str(&line).map_err(|e| e.to_string())));
+            }
+        }
+    }
+
+    let mut records = Vec::new();
+    let mut rejects = Vec::new();
+    // An id, or the natural key of a row without one, present twice would be upserted twice by
+    // the same statement
+    let mut seen: HashMap<i32, u64> = HashMap::new();
+    let mut seen_keys: HashMap<String, u64> = HashMap::new();
+    for (line, row) in rows {
+        let result = row.and_then(|record| {
+            record.validate()?;
+            match record.id() {
+                Some(id) => {
+                    if let Some(first) = seen.insert(id, line) {
+                        return Err(format!("id {} already on line {}", id, first));
+                    }
+                }
+                None => {
+                    let (name, key) = record.key();
+                    if let Some(first) = seen_keys.insert(key.clone(), line) {
+                        return Err(format!("{} {:?} already on line {}", name, key, first));
+                    }
+                }
+            }
+            Ok(record)
+        });
+        match result {
+            Ok(record) => records.push((line, record)),
+            Err(reason) => rejects.push((line, reason)),
+        }
+    }
+    Ok((records, rejects))
+}
+
+/// Rejects the rows without an id whose natural key, the lowercased `column` of `table`, matches
+/// more than one row: updating all of them from a single row would merge distinct records.
+async fn reject_ambiguous<T: Record>(
+    conn: &mut PgConnection,
+    table: &str,
+    column: &str,
+    records: Vec<Row<T>>,
+) -> Result<(Vec<T>, Vec<Reject>), sqlx::Error> {
+    let keys: Vec<String> = records.iter().map(|(_, record)| record.key().1).collect();
+    let ambiguous: HashSet<String> = sqlx::query_scalar::<_, String>(&format!(
+        "SELECT lower({column}) FROM {table} WHERE lower({column}) = ANY($1)
+        GROUP BY lower({column}) HAVING COUNT(*) > 1"
+    ))
+    .bind(&keys)
+    .fetch_all(conn)
+    .await?
+    .into_iter()
+    .collect();
+
+    let mut kept = Vec::new();
+    let mut rejects = Vec::new();
+    for (line, record) in records {
+        let (name, key) = record.key();
+        if ambiguous.contains(&key) {
+            let reason = format!("{} {:?} matches several rows of {}, import it with an id", name, key, table);
+            rejects.push((line, reason));
+        } else {
+            kept.push(record);
+        }
+    }
+    Ok((kept, rejects))
+}
+
+async fn upsert_clients(
+    pool: &PgPool,
+    records: Vec<Row<ClientRecord>>,
+) -> Result<(Vec<Client>, Vec<Reject>), sqlx::Error> {
+    let (with_id, without_id): (Vec<_>, Vec<_>) = records.into_iter().partition(|(_, record)| record.id.is_some());
+    let with_id: Vec<ClientRecord> = with_id.into_iter().map(|(_, record)| record).collect();
+    let mut tx = pool.begin().await?;
+    let (without_id, rejects) = reject_ambiguous(&mut tx, "Client", "email", without_id).await?;
+
+    let query = format!(
+        "INSERT INTO Client (id, name, email, address, currency)
+        SELECT * FROM UNNEST($1::INT[], $2::VARCHAR[], $3::VARCHAR[], $4::VARCHAR[], $5::VARCHAR[])
+        ON CONFLICT (id) DO UPDATE SET name = EXCLUDED.name, email = EXCLUDED.email, address = EXCLUDED.address,
+            currency = EXCLUDED.currency, version = Client.version + 1, updatedAt = now()
+        RETURNING {}",
+        CLIENT_COLUMNS
+    );
+    let mut clients = sqlx::query_as::<_, Client>(&query)
+        .bind(with_id.iter().map(|record| record.id.unwrap_or_default()).collect::<Vec<i32>>())
+        .bind(with_id.iter().map(|record| record.name.trim()).collect::<Vec<&str>>())
+        .bind(with_id.iter().map(|record| record.email.trim()).collect::<Vec<&str>>())
+        .bind(with_id.iter().map(|record| record.address.trim()).collect::<Vec<&str>>())
+        .bind(with_id.iter().map(|record| currency(&record.currency)).collect::<Vec<String>>())
+        .fetch_all(&mut *tx)
+        .await?;
+
+    // Explicit ids don't advance the identity, which would then hand them out again
+    sqlx::query("SELECT setval(pg_get_serial_sequence('client', 'id'), GREATEST((SELECT MAX(id) FROM Client), 1))")
+        .execute(&mut *tx)
+        .await?;
+
+    // Rows without an id update the single client with the same email, so importing a file twice
+    // doesn't duplicate them
+    let query = format!(
+        "WITH input AS (
+            SELECT * FROM UNNEST($1::VARCHAR[], $2::VARCHAR[], $3::VARCHAR[], $4::VARCHAR[])
+                AS input(new_name, new_email, new_address, new_currency)
+        ), updated AS (
+            UPDATE Client SET name = new_name, address = new_address, currency = new_currency,
+                version = Client.version + 1, updatedAt = now()
+            FROM input WHERE lower(email) = lower(new_email)
+            RETURNING {columns}
+        ), inserted AS (
+            INSERT INTO Client (name, email, address, currency)
+            SELECT new_name, new_email, new_address, new_currency FROM input
+            WHERE NOT EXISTS (SELECT 1 FROM Client WHERE lower(email) = lower(new_email))
+            RETURNING {columns}
+        )
+        SELECT * FROM updated UNION ALL SELECT * FROM inserted",
+        columns = CLIENT_COLUMNS
+    );
+    clients.extend(
+        sqlx::query_as::<_, Client>(&query)
+            .bind(without_id.iter().map(|record| record.name.trim()).collect::<Vec<&str>>())
+            .bind(without_id.iter().map(|record| record.email.trim()).collect::<Vec<&str>>())
+            .bind(without_id.iter().map(|record| record.address.trim()).collect::<Vec<&str>>())
+            .bind(without_id.iter().map(|record| currency(&record.currency)).collect::<Vec<String>>())
+            .fetch_all(&mut *tx)
+            .await?,
+    );
+
+    tx.commit().await?;
+    Ok((clients, rejects))
+}
+
+async fn upsert_products(
+    pool: &PgPool,
+    records: Vec<Row<ProductRecord>>,
+) -> Result<(Vec<CatalogProduct>, Vec<Reject>), sqlx::Error> {
+    let (with_id, without_id): (Vec<_>, Vec<_>) = records.into_iter().partition(|(_, record)| record.id.is_some());
+    let with_id: Vec<ProductRecord> = with_id.into_iter().map(|(_, record)| record).collect();
+    let category = |record: &ProductRecord| record.category.as_deref().unwrap_or("misc").trim().to_string();
+    let mut tx = pool.begin().await?;
+    let (without_id, rejects) = reject_ambiguous(&mut tx, "Product", "name", without_id).await?;
+
+    let query = format!(
+        "INSERT INTO Product (id, name, price, category, currency)
+        SELECT * FROM UNNEST($1::INT[], $2::VARCHAR[], $3::FLOAT[], $4::VARCHAR[], $5::VARCHAR[])
+        ON CONFLICT (id) DO UPDATE SET name = EXCLUDED.name, price = EXCLUDED.price, category = EXCLUDED.category,
+            currency = EXCLUDED.currency, version = Product.version + 1, updatedAt = now()
+        RETURNING {}",
+        CATALOG_PRODUCT_COLUMNS
+    );
+    let mut products = sqlx::query_as::<_, CatalogProduct>(&query)
+        .bind(with_id.iter().map(|record| record.id.unwrap_or_default()).collect::<Vec<i32>>())
+        .bind(with_id.iter().map(|record| record.name.trim()).collect::<Vec<&str>>())
+        .bind(with_id.iter().map(|record| record.price).collect::<Vec<f64>>())
+        .bind(with_id.iter().map(category).collect::<Vec<String>>())
+        .bind(with_id.iter().map(|record| currency(&record.currency)).collect::<Vec<String>>())
+        .fetch_all(&mut *tx)
+        .await?;
+
+    // Explicit ids don't advance the identity, which would then hand them out again
+    sqlx::query("SELECT setval(pg_get_serial_sequence('product', 'id'), GREATEST((SELECT MAX(id) FROM Product), 1))")
+        .execute(&mut *tx)
+        .await?;
+
+    // Rows without an id update the single product with the same name, so importing a file twice
+    // doesn't duplicate them
+    let query = format!(
+        "WITH input AS (
+            SELECT * FROM UNNEST($1::VARCHAR[], $2::FLOAT[], $3::VARCHAR[], $4::VARCHAR[])
+                AS input(new_name, new_price, new_category, new_currency)
+        ), updated AS (
+            UPDATE Product SET price = new_price, category = new_category, currency = new_currency,
+                version = Product.version + 1, updatedAt = now()
+            FROM input WHERE lower(name) = lower(new_name)
+            RETURNING {columns}
+        ), inserted AS (
+            INSERT INTO Product (name, price, category, currency)
+            SELECT new_name, new_price, new_category, new_currency FROM input
+            WHERE NOT EXISTS (SELECT 1 FROM Product WHERE lower(name) = lower(new_name))
+            RETURNING {columns}
+        )
+        SELECT * FROM updated UNION ALL SELECT * FROM inserted",
+        columns = CATALOG_PRODUCT_COLUMNS
+    );
+    products.extend(
+        sqlx::query_as::<_, CatalogProduct>(&query)
+            .bind(without_id.iter().map(|record| record.name.trim()).collect::<Vec<&str>>())
+            .bind(without_id.iter().map(|record| record.price).collect::<Vec<f64>>())
+            .bind(without_id.iter().map(category).collect::<Vec<String>>())
+            .bind(without_id.iter().map(|record| currency(&record.currency)).collect::<Vec<String>>())
+            .fetch_all(&mut *tx)
+            .await?,
+    );
+
+    tx.commit().await?;
+    Ok((products, rejects))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_products(name: &str, content: &str) -> (Vec<Row<ProductRecord>>, Vec<Reject>) {
+        let path = std::env::temp_dir().join(format!("import-{}-{}.csv", name, std::process::id()));
+        std::fs::write(&path, content).unwrap();
+        let read = read_records::<ProductRecord>(&path, FileFormat::Csv).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        read
+    }
+
+    #[test]
+    fn distinct_ids_may_share_a_name() {
+        let (records, rejects) = read_products("ids", "id,name,price\n1,Lorem,10\n2,lorem,12\n");
+        assert!(rejects.is_empty());
+        assert_eq!(records.iter().map(|(line, _)| *line).collect::<Vec<u64>>(), vec![2, 3]);
+    }
+
+    #[test]
+    fn rows_without_an_id_have_distinct_names() {
+        let (records, rejects) = read_products("names", "id,name,price\n,Lorem,10\n1,Lorem,11\n, LOREM ,12\n");
+        assert_eq!(records.len(), 2);
+        assert_eq!(rejects, vec![(4, String::from("name \"lorem\" already on line 2"))]);
+    }
+
+    #[test]
+    fn rejects_a_repeated_id_or_an_invalid_row() {
+        let (records, rejects) = read_products("invalid", "id,name,price\n1,Lorem,10\n1,Ipsum,12\n,Dolor,-1\n");
+        assert_eq!(records.len(), 1);
+        assert_eq!(
+            rejects,
+            vec![
+                (3, String::from("id 1 already on line 2")),
+                (4, String::from("price -1 is not positive")),
+            ]
+        );
+    }
+}
\ No newline at end of file
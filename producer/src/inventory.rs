@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+use clap::Args;
+use common::delivery::Sender;
+use common::inventory::StockChange;
+use common::product::ProductFromDb;
+use schema_registry_converter::async_impl::avro::AvroEncoder;
+use schema_registry_converter::async_impl::schema_registry::SrSettings;
+use sqlx::{PgConnection, PgPool};
+
+use crate::seed::publish_all;
+
+#[derive(Args, Clone, Debug)]
+pub struct InventoryOptions {
+    /// Seconds between two restocks
+    #[arg(long, default_value_t = 30)]
+    pub restock_interval_secs: u64,
+
+    /// Products with this many units or less in stock are restocked
+    #[arg(long, default_value_t = 10)]
+    pub restock_threshold: i32,
+
+    /// Units added to the stock of a restocked product
+    #[arg(long, default_value_t = 100)]
+    pub restock_quantity: i32,
+
+    /// Largest number of products restocked at once
+    #[arg(long, default_value_t = 50)]
+    pub restock_batch: i64,
+}
+
+/// The products of an order short of stock that can't be backordered.
+#[derive(Debug)]
+pub struct OutOfStock(pub Vec<i32>);
+
+impl fmt::Display for OutOfStock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Products out of stock: {:?}", self.0)
+    }
+}
+
+/// The units of an order taken out of the stock.
+pub struct Reservation {
+    /// Number of units of each product ordered beyond the stock
+    pub backordered: HashMap<i32, i32>,
+    pub changes: Vec<StockChange>,
+}
+
+/// Takes the units of the order out of the stock, within the order transaction. Units beyond the
+/// stock are backordered if the product allows it, otherwise the order is rejected and the caller
+/// rolls the transaction back. Products without a stock row are not tracked.
+///
+/// Backordered units stay pending in `CommandProduct.backordered` until a restock fills them.
+pub async fn reserve(
+    conn: &mut PgConnection,
+    lines: &[(ProductFromDb, i32)],
+) -> Result<Result<Reservation, OutOfStock>, sqlx::Error> {
+    let product_ids: Vec<i32> = lines.iter().map(|(product, _)| product.id).collect();
+    // Locked in the same order by every order, so that concurrent orders don't deadlock
+    let stock: HashMap<i32, (i32, bool)> = sqlx::query_as::<_, (i32, i32, bool)>(
+        "SELECT productId, available, backorder FROM Stock WHERE productId = ANY($1) ORDER BY productId FOR UPDATE",
+    )
+    .bind(&product_ids)
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .map(|(product_id, available, backorder)| (product_id, (available, backorder)))
+    .collect();
+
+    let mut backordered = HashMap::new();
+    let mut rejected = Vec::new();
+    let (mut reserved_ids, mut reserved) = (Vec::new(), Vec::new());
+    for (product, quantity) in lines {
+        let Some((available, backorder)) = stock.get(&product.id) else {
+            continue;
+        };
+        let taken = (*quantity).min(*available);
+        if taken < *quantity {
+            match backorder {
+                true => {
+                    backordered.insert(product.id, quantity - taken);
+                }
+                false => rejected.push(product.id),
+            }
+        }
+        if taken > 0 {
+            reserved_ids.push(product.id);
+            reserved.push(taken);
+        }
+    }
+    if !rejected.is_empty() {
+        rejected.sort_unstable();
+        return Ok(Err(OutOfStock(rejected)));
+    }
+
+    let rows: Vec<(i32, i32, i32)> = sqlx::query_as(
+        "UPDATE Stock SET available = Stock.available - taken.quantity
+        FROM UNNEST($1::INT[], $2::INT[]) AS taken(productId, quantity)
+        WHERE Stock.productId = taken.productId
+        RETURNING Stock.productId, Stock.available, taken.quantity",
+    )
+    .bind(&reserved_ids)
+    .bind(&reserved)
+    .fetch_all(&mut *conn)
+    .await?;
+    let changes = to_changes(rows, -1, "order");
+    Ok(Ok(Reservation { backordered, changes }))
+}
+
+/// Puts the reserved units of a cancelled order back in stock, within the cancellation transaction.
+pub async fn release(conn: &mut PgConnection, command_id: i32) -> Result<Vec<StockChange>, sqlx::Error> {
+    let rows: Vec<(i32, i32, i32)> = sqlx::query_as(
+        "UPDATE Stock SET available = Stock.available + CommandProduct.quantity - CommandProduct.backordered
+        FROM CommandProduct
+        WHERE CommandProduct.commandId = $1
+            AND Stock.productId = CommandProduct.productId
+            AND CommandProduct.quantity > CommandProduct.backordered
+        RETURNING Stock.productId, Stock.available, CommandProduct.quantity - CommandProduct.backordered",
+    )
+    .bind(command_id)
+    .fetch_all(&mut *conn)
+    .await?;
+    Ok(to_changes(rows, 1, "cancellation"))
+}
+
+/// Restocks the products running low every `--restock-interval-secs`, then fills the pending
+/// backorders with the units in stock, until the producer stops.
+pub async fn run(options: InventoryOptions, pool: PgPool, sender: Sender, sr_settings: SrSettings) {
+    let encoder = AvroEncoder::new(sr_settings);
+    loop {
+        tokio::time::sleep(Duration::from_secs(options.restock_interval_secs.max(1))).await;
+        match restock(&pool, &options).await {
+            Ok((restocked, filled)) => {
+                if !restocked.is_empty() || !filled.is_empty() {
+                    println!("{} products restocked, {} backorders filled", restocked.len(), filled.len());
+                }
+                publish(&sender, &encoder, restocked).await;
+                publish(&sender, &encoder, filled).await;
+            }
+            Err(e) => eprintln!("Failed to restock: {:?}", e),
+        }
+    }
+}
+
+/// Restocks the products running low and fills their backorders in one transaction.
+async fn restock(
+    pool: &PgPool,
+    options: &InventoryOptions,
+) -> Result<(Vec<StockChange>, Vec<StockChange>), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let restocked = sqlx::query_as::<_, (i32, i32, i32)>(
+        "UPDATE Stock SET available = available + $2
+        WHERE productId IN (
+            SELECT productId FROM Stock WHERE available <= $1
+            ORDER BY available, productId LIMIT $3 FOR UPDATE SKIP LOCKED
+        )
+        RETURNING productId, available, $2",
+    )
+    .bind(options.restock_threshold)
+    .bind(options.restock_quantity)
+    .bind(options.restock_batch)
+    .fetch_all(&mut *tx)
+    .await?;
+    let filled = fill_backorders(&mut tx).await?;
+    tx.commit().await?;
+    Ok((to_changes(restocked, 1, "restock"), filled))
+}
+
+/// Takes the pending backorders out of the stock, oldest order first, within the restock
+/// transaction. A backorder is filled partly when the stock runs out, the rest waits for the next
+/// restock. The backorders of cancelled orders are dropped, their units were never taken.
+pub async fn fill_backorders(conn: &mut PgConnection) -> Result<Vec<StockChange>, sqlx::Error> {
+    // Locked like orders lock them, so that no order takes the units being allocated
+    sqlx::query(
+        "SELECT productId FROM Stock
+        WHERE available > 0 AND productId IN (SELECT productId FROM CommandProduct WHERE backordered > 0)
+        ORDER BY productId FOR UPDATE",
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let rows: Vec<(i32, i32, i32)> = sqlx::query_as(
+        "WITH pending AS (
+            SELECT CommandProduct.commandId, CommandProduct.productId, CommandProduct.backordered, Stock.available,
+                SUM(CommandProduct.backordered) OVER (
+                    PARTITION BY CommandProduct.productId ORDER BY CommandProduct.commandId
+                ) - CommandProduct.backordered AS ahead
+            FROM CommandProduct
+            JOIN Command ON Command.id = CommandProduct.commandId
+            JOIN Stock ON Stock.productId = CommandProduct.productId
+            WHERE CommandProduct.backordered > 0 AND Command.status <> 'cancelled' AND Stock.available > 0
+        ),
+        allocated AS (
+            SELECT commandId, productId, LEAST(backordered, available - ahead)::INT AS units
+            FROM pending WHERE available > ahead
+        ),
+        filled AS (
+            UPDATE CommandProduct SET backordered = CommandProduct.backordered - allocated.units
+            FROM allocated
+            WHERE CommandProduct.commandId = allocated.commandId AND CommandProduct.productId = allocated.productId
+            RETURNING CommandProduct.productId, allocated.units
+        )
+        UPDATE Stock SET available = Stock.available - taken.units
+        FROM (SELECT productId, SUM(units)::INT AS units FROM filled GROUP BY productId) AS taken
+        WHERE Stock.productId = taken.productId
+        RETURNING Stock.productId, Stock.available, taken.units",
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    Ok(to_changes(rows, -1, "backorder"))
+}
+
+/// Publishes the stock changes to the `StockLevel` topic.
+pub async fn publish(sender: &Sender, encoder: &AvroEncoder<'_>, changes: Vec<StockChange>) {
+    if changes.is_empty() {
+        return;
+    }
+    let changes = futures::stream::iter(changes.into_iter().map(Ok));
+    if let Err(e) = publish_all(sender, encoder, "StockLevel", changes, |change| change.product_id).await {
+        eprintln!("Failed to publish stock changes: {:?}", e);
+    }
+}
+
+/// Turns `(product id, available, units)` rows into stock changes of `sign * units`.
+fn to_changes(rows: Vec<(i32, i32, i32)>, sign: i32, reason: &str) -> Vec<StockChange> {
+    let at = chrono::Utc::now().to_rfc3339();
+    rows.into_iter()
+        .map(|(product_id, available, units)| StockChange {
+            product_id,
+            available,
+            change: sign * units,
+            reason: reason.to_string(),
+            at: at.clone(),
+        })
+        .collect()
+}
\ No newline at end of file
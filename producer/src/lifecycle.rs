@@ -0,0 +1,220 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use clap::Args;
+use common::delivery::Sender;
+use common::lifecycle::{Cancellation, LifecycleStatus, Refund, RefundLine};
+use rand::distributions::WeightedIndex;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rand_distr::{Distribution, Exp};
+use schema_registry_converter::async_impl::avro::AvroEncoder;
+use schema_registry_converter::async_impl::schema_registry::SrSettings;
+use sqlx::PgPool;
+
+use crate::inventory;
+use crate::payment::{self, PaymentOptions};
+use crate::seed::publish_all;
+
+/// Number of latest orders the status changes are drawn among.
+const RECENT_ORDERS: i64 = 1000;
+
+const CANCELLATION_REASONS: [&str; 3] = ["changed_mind", "payment_failed", "out_of_stock"];
+const REFUND_REASONS: [&str; 3] = ["damaged", "wrong_item", "not_as_described"];
+
+#[derive(Args, Clone, Debug)]
+pub struct LifecycleOptions {
+    /// Average number of order status changes per minute: payments, shipments, cancellations and
+    /// refunds
+    #[arg(long, default_value_t = 30.0)]
+    pub lifecycle_events_per_minute: f64,
+
+    #[command(flatten)]
+    pub payments: PaymentOptions,
+}
+
+/// Relative likelihood of a status change, among the ones allowed from the current status.
+fn weight(status: LifecycleStatus) -> f64 {
+    match status {
+        LifecycleStatus::Placed => 0.0,
+        LifecycleStatus::Paid => 10.0,
+        LifecycleStatus::Shipped => 8.0,
+        LifecycleStatus::Cancelled => 1.0,
+        LifecycleStatus::PartiallyRefunded => 1.0,
+    }
+}
+
+/// Changes the status of random recent orders at the given rate, with exponentially distributed
+/// intervals, until the producer stops. Every draw comes from `rng`, seeded from the shared
+/// generator before the load starts.
+pub async fn run(options: LifecycleOptions, pool: PgPool, sender: Sender, sr_settings: SrSettings, mut rng: StdRng) {
+    let per_second = options.lifecycle_events_per_minute / 60.0;
+    if per_second <= 0.0 {
+        return;
+    }
+    let Ok(interval) = Exp::new(per_second) else {
+        return;
+    };
+    let encoder = AvroEncoder::new(sr_settings);
+    loop {
+        let wait = interval.sample(&mut rng);
+        tokio::time::sleep(Duration::from_secs_f64(wait)).await;
+        if let Err(e) = advance(&options, &pool, &sender, &encoder, &mut rng).await {
+            eprintln!("Failed to change the status of an order: {:?}", e);
+        }
+    }
+}
+
+/// Moves a random recent order to one of the statuses it can become, records the event, then
+/// publishes it to the `Cancellation` or `Refund` topic for these statuses. Paying an order
+/// publishes its payment events to the `Payment` topic.
+async fn advance(
+    options: &LifecycleOptions,
+    pool: &PgPool,
+    sender: &Sender,
+    encoder: &AvroEncoder<'_>,
+    rng: &mut StdRng,
+) -> Result<(), sqlx::Error> {
+    let orders: Vec<(i32, String)> =
+        sqlx::query_as("SELECT id, status FROM Command WHERE status <> 'cancelled' ORDER BY id DESC LIMIT $1")
+            .bind(RECENT_ORDERS)
+            .fetch_all(pool)
+            .await?;
+
+    let Some((command_id, status)) = orders.choose(rng) else {
+        return Ok(());
+    };
+    let command_id = *command_id;
+    let status: LifecycleStatus = status.parse().map_err(|e: String| sqlx::Error::Decode(e.into()))?;
+    let next = status.next();
+    let Ok(weights) = WeightedIndex::new(next.iter().map(|status| weight(*status))) else {
+        return Ok(());
+    };
+    let next = next[weights.sample(rng)];
+
+    let reason = match next {
+        LifecycleStatus::Cancelled => CANCELLATION_REASONS.choose(rng).copied().unwrap_or_default(),
+        LifecycleStatus::PartiallyRefunded => REFUND_REASONS.choose(rng).copied().unwrap_or_default(),
+        _ => "",
+    };
+    let lines = match next {
+        LifecycleStatus::PartiallyRefunded => match refund_lines(pool, command_id, rng).await? {
+            Some(lines) => lines,
+            None => return Ok(()),
+        },
+        _ => Vec::new(),
+    };
+
+    // The payment is drawn when the order is paid. A failed payment leaves the order placed. A
+    // captured order that is cancelled or refunded gets its money back
+    let attempts = match next {
+        LifecycleStatus::Paid => options.payments.draw(rng, payment::order_total(pool, command_id).await?),
+        LifecycleStatus::Cancelled => payment::refund(pool, command_id, None).await?,
+        LifecycleStatus::PartiallyRefunded => payment::refund(pool, command_id, Some(&lines)).await?,
+        _ => Vec::new(),
+    };
+
+    let mut tx = pool.begin().await?;
+    let payments = payment::insert(&mut tx, command_id, &attempts).await?;
+    if attempts.iter().any(|attempt| attempt.failed()) {
+        tx.commit().await?;
+        println!("Payment of order {} failed", command_id);
+        payment::publish(sender, encoder, payments).await;
+        return Ok(());
+    }
+
+    // Another change of the order in the meantime wins, the transaction is rolled back
+    let updated = sqlx::query("UPDATE Command SET status = $2 WHERE id = $1 AND status = $3")
+        .bind(command_id)
+        .bind(next.as_str())
+        .bind(status.as_str())
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    if updated == 0 {
+        return Ok(());
+    }
+    let (event_id, date): (i32, String) = sqlx::query_as(
+        "INSERT INTO OrderEvent (commandId, status, reason) VALUES ($1, $2, $3)
+        RETURNING id, TO_CHAR(at AT TIME ZONE 'UTC', 'YYYY-MM-DD')",
+    )
+    .bind(command_id)
+    .bind(next.as_str())
+    .bind(reason)
+    .fetch_one(&mut *tx)
+    .await?;
+    sqlx::query(
+        "INSERT INTO RefundLine (eventId, productId, quantity)
+        SELECT $1, * FROM UNNEST($2::INT[], $3::INT[])",
+    )
+    .bind(event_id)
+    .bind(lines.iter().map(|line| line.product_id).collect::<Vec<i32>>())
+    .bind(lines.iter().map(|line| line.quantity).collect::<Vec<i32>>())
+    .execute(&mut *tx)
+    .await?;
+    // The reserved units of a cancelled order are back for sale
+    let stock_changes = match next {
+        LifecycleStatus::Cancelled => inventory::release(&mut tx, command_id).await?,
+        _ => Vec::new(),
+    };
+    tx.commit().await?;
+    println!("Order {} is now {}", command_id, next);
+    payment::publish(sender, encoder, payments).await;
+    inventory::publish(sender, encoder, stock_changes).await;
+
+    let reason = reason.to_string();
+    match next {
+        LifecycleStatus::Cancelled => {
+            let cancellation = Cancellation { id: event_id, command_id, date, reason };
+            let events = futures::stream::iter([Ok(cancellation)]);
+            publish_all(sender, encoder, "Cancellation", events, |cancellation| cancellation.command_id).await?;
+        }
+        LifecycleStatus::PartiallyRefunded => {
+            let refund = Refund { id: event_id, command_id, date, reason, lines };
+            let events = futures::stream::iter([Ok(refund)]);
+            publish_all(sender, encoder, "Refund", events, |refund| refund.command_id).await?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Draws the units of a refund among the units of the order not refunded yet, leaving at least
+/// one. Returns `None` if less than two units are left.
+async fn refund_lines(pool: &PgPool, command_id: i32, rng: &mut StdRng) -> Result<Option<Vec<RefundLine>>, sqlx::Error> {
+    let remaining: Vec<(i32, i32)> = sqlx::query_as(
+        "SELECT CommandProduct.productId,
+            (CommandProduct.quantity - COALESCE(SUM(RefundLine.quantity), 0))::INT
+        FROM CommandProduct
+        LEFT JOIN OrderEvent ON OrderEvent.commandId = CommandProduct.commandId
+        LEFT JOIN RefundLine ON RefundLine.eventId = OrderEvent.id AND RefundLine.productId = CommandProduct.productId
+        WHERE CommandProduct.commandId = $1
+        GROUP BY CommandProduct.productId, CommandProduct.quantity
+        ORDER BY CommandProduct.productId",
+    )
+    .bind(command_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut units: Vec<i32> = remaining
+        .into_iter()
+        .flat_map(|(product_id, quantity)| std::iter::repeat_n(product_id, quantity.max(0) as usize))
+        .collect();
+    if units.len() < 2 {
+        return Ok(None);
+    }
+    let count = rng.gen_range(1..units.len());
+    units.shuffle(rng);
+
+    let mut quantities: BTreeMap<i32, i32> = BTreeMap::new();
+    for product_id in &units[..count] {
+        *quantities.entry(*product_id).or_default() += 1;
+    }
+    Ok(Some(
+        quantities
+            .into_iter()
+            .map(|(product_id, quantity)| RefundLine { product_id, quantity })
+            .collect(),
+    ))
+}
\ No newline at end of file
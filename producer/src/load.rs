@@ -0,0 +1,244 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use clap::{Args, ValueEnum};
+use common::delivery::Sender;
+use common::rng::SharedRng;
+use schema_registry_converter::async_impl::schema_registry::SrSettings;
+use sqlx::PgPool;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+use crate::catalog::SharedCatalog;
+use crate::chaos::Chaos;
+use crate::distribution::positive;
+use crate::produce_command;
+
+/// How the rate grows from nearly zero to `--rate` during the ramp-up.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum RampProfile {
+    /// The rate grows proportionally to the elapsed time
+    Linear,
+    /// The rate goes through 25%, 50%, 75% and 100%
+    Step,
+    /// The rate doubles every fifth of the ramp-up, starting at 1/32
+    Exponential,
+}
+
+impl RampProfile {
+    /// Fraction of the target rate once `progress` (0 to 1) of the ramp-up has elapsed.
+    fn factor(&self, progress: f64) -> f64 {
+        let progress = progress.clamp(0.0, 1.0);
+        let factor = match self {
+            RampProfile::Linear => progress,
+            RampProfile::Step => ((progress * 4.0).floor() + 1.0).min(4.0) / 4.0,
+            RampProfile::Exponential => 2f64.powf(5.0 * (progress - 1.0)),
+        };
+        // Never let the rate reach zero, which would stall the workers
+        factor.max(0.01)
+    }
+}
+
+/// Lowest rate the pacer waits for, in commands per second, whatever the ramp-up and the
+/// seasonality.
+const MIN_RATE: f64 = 0.01;
+
+#[derive(Args, Debug)]
+pub struct LoadOptions {
+    /// Target number of commands per second, across all workers, at the peak of the seasonality
+    /// with `--seasonal-pacing`. Unlimited if not provided
+    #[arg(long, value_parser = positive)]
+    rate: Option<f64>,
+
+    /// Stop after producing this many commands
+    #[arg(long)]
+    count: Option<u64>,
+
+    /// Stop after this many seconds
+    #[arg(long)]
+    duration: Option<u64>,
+
+    /// Number of concurrent workers sharing the database pool and the Kafka producer
+    #[arg(long, default_value_t = 1)]
+    workers: usize,
+
+    /// Number of seconds to reach the target rate
+    #[arg(long, requires = "rate")]
+    ramp_up: Option<u64>,
+
+    #[arg(long, value_enum, default_value_t = RampProfile::Linear)]
+    ramp_profile: RampProfile,
+
+    /// If provided, the rate follows the daily and weekly seasonality of the orders, and without
+    /// `--rate` the workers rest so that the unlimited rate is only reached at the peak
+    #[arg(long)]
+    seasonal_pacing: bool,
+}
+
+/// Hands out the instants at which the workers may produce, to hold the target rate.
+struct Pacer {
+    rate: Option<f64>,
+    catalog: SharedCatalog,
+    ramp: Option<(Duration, RampProfile)>,
+    seasonal: bool,
+    start: Instant,
+    next: Mutex<Instant>,
+}
+
+impl Pacer {
+    /// Fraction of the peak order rate at the current time of the orders, or 1 without seasonal
+    /// pacing.
+    async fn seasonality(&self) -> f64 {
+        if !self.seasonal {
+            return 1.0;
+        }
+        let catalog = self.catalog.read().await;
+        let options = catalog.options();
+        options.seasonality(options.now())
+    }
+
+    async fn wait(&self) {
+        let rate = match self.rate {
+            Some(rate) => rate,
+            None => return,
+        };
+        // With seasonal pacing, the rate follows the daily and weekly seasonality of the orders
+        let seasonality = self.seasonality().await;
+        let slot = {
+            let mut next = self.next.lock().await;
+            let slot = (*next).max(Instant::now());
+            let factor = match self.ramp {
+                Some((ramp_up, profile)) => profile.factor((slot - self.start).as_secs_f64() / ramp_up.as_secs_f64()),
+                None => 1.0,
+            };
+            // A seasonality of zero, or not a number, would make the interval infinite
+            let rate = (rate * factor * seasonality).max(MIN_RATE);
+            *next = slot + Duration::from_secs_f64(1.0 / rate);
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+    }
+
+    /// With seasonal pacing and without a target rate, holds a worker back after a command that
+    /// took `elapsed`, so that the unlimited rate follows the seasonality as well: at a fraction
+    /// `s` of the peak, the worker rests `elapsed * (1 / s - 1)`.
+    async fn rest(&self, elapsed: Duration) {
+        if self.rate.is_some() || !self.seasonal {
+            return;
+        }
+        let seasonality = self.seasonality().await.max(MIN_RATE);
+        tokio::time::sleep(elapsed.mul_f64(1.0 / seasonality - 1.0)).await;
+    }
+}
+
+pub struct LoadSummary {
+    produced: usize,
+    failed: u64,
+    elapsed: Duration,
+    /// Time to place each order, from its insertion in the database to the publication of its
+    /// messages
+    latencies: Vec<Duration>,
+}
+
+impl LoadSummary {
+    fn percentile(&self, percentile: f64) -> Duration {
+        if self.latencies.is_empty() {
+            return Duration::ZERO;
+        }
+        let rank = ((percentile / 100.0) * self.latencies.len() as f64).ceil() as usize;
+        self.latencies[rank.clamp(1, self.latencies.len()) - 1]
+    }
+
+    pub fn print(&self) {
+        println!("Produced {} commands in {:.2?} ({} failed)", self.produced, self.elapsed, self.failed);
+        println!(
+            "Throughput: {:.2} commands/s",
+            self.produced as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+        );
+        println!(
+            "Order latency (insert and publish): p50 {:.2?}, p90 {:.2?}, p99 {:.2?}, max {:.2?}",
+            self.percentile(50.0),
+            self.percentile(90.0),
+            self.percentile(99.0),
+            self.latencies.last().copied().unwrap_or_default()
+        );
+    }
+}
+
+/// Produces random commands with the given workers until the count or the duration is reached,
+/// or until Ctrl+C is pressed.
+pub async fn run(
+    options: &LoadOptions,
+    pool: PgPool,
+    sender: Sender,
+    sr_settings: SrSettings,
+    rng: SharedRng,
+    catalog: SharedCatalog,
+    chaos: Option<Arc<Chaos>>,
+) -> LoadSummary {
+    let start = Instant::now();
+    let pacer = Arc::new(Pacer {
+        rate: options.rate,
+        catalog: Arc::clone(&catalog),
+        ramp: options.ramp_up.map(|secs| (Duration::from_secs(secs), options.ramp_profile)),
+        seasonal: options.seasonal_pacing,
+        start,
+        next: Mutex::new(start),
+    });
+    let stop = Arc::new(AtomicBool::new(false));
+    let issued = Arc::new(AtomicU64::new(0));
+    let failed = Arc::new(AtomicU64::new(0));
+    let deadline = options.duration.map(|secs| start + Duration::from_secs(secs));
+
+    let stop_on_signal = Arc::clone(&stop);
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            println!("Stopping the load generation");
+            stop_on_signal.store(true, Ordering::Relaxed);
+        }
+    });
+
+    let mut workers = Vec::new();
+    for _ in 0..options.workers.max(1) {
+        let (pool, sender, sr_settings) = (pool.clone(), sender.clone(), sr_settings.clone());
+        let (rng, catalog, chaos) = (rng.clone(), Arc::clone(&catalog), chaos.clone());
+        let (pacer, stop, issued, failed) = (Arc::clone(&pacer), Arc::clone(&stop), Arc::clone(&issued), Arc::clone(&failed));
+        let count = options.count;
+        workers.push(tokio::spawn(async move {
+            let mut latencies = Vec::new();
+            loop {
+                if stop.load(Ordering::Relaxed) || deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    break;
+                }
+                if count.is_some_and(|count| issued.fetch_add(1, Ordering::Relaxed) >= count) {
+                    break;
+                }
+                pacer.wait().await;
+                let sent = Instant::now();
+                match produce_command(&pool, &sender, &sr_settings, &rng, &catalog, chaos.as_ref()).await {
+                    Ok(()) => latencies.push(sent.elapsed()),
+                    Err(e) => {
+                        eprintln!("Failed to produce command: {:?}", e);
+                        failed.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+                pacer.rest(sent.elapsed()).await;
+            }
+            latencies
+        }));
+    }
+
+    let mut latencies = Vec::new();
+    for worker in workers {
+        latencies.extend(worker.await.unwrap_or_default());
+    }
+    latencies.sort_unstable();
+
+    LoadSummary {
+        produced: latencies.len(),
+        failed: failed.load(Ordering::Relaxed),
+        elapsed: start.elapsed(),
+        latencies,
+    }
+}
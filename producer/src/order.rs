@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use common::client::{Client, CLIENT_COLUMNS};
+use common::delivery::Sender;
+use common::product::ProductFromDb;
+use schema_registry_converter::async_impl::schema_registry::SrSettings;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::command::place_order;
+use crate::inventory::OutOfStock;
+
+/// Maximum quantity of a product in an order, duplicate items summed.
+pub const MAX_LINE_QUANTITY: i32 = 100;
+/// Maximum number of units in an order. Each unit is published as its own product message.
+pub const MAX_ORDER_UNITS: i32 = 1000;
+
+#[derive(Clone)]
+pub struct OrderState {
+    pub pool: PgPool,
+    pub sender: Sender,
+    pub sr_settings: SrSettings,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OrderRequest {
+    client_id: i32,
+    items: Vec<OrderItem>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OrderItem {
+    product_id: i32,
+    quantity: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrderResponse {
+    command_id: i32,
+}
+
+pub enum OrderError {
+    Invalid(String),
+    OutOfStock(OutOfStock),
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for OrderError {
+    fn from(error: sqlx::Error) -> Self {
+        OrderError::Database(error)
+    }
+}
+
+impl IntoResponse for OrderError {
+    fn into_response(self) -> Response {
+        let (status, error) = match self {
+            OrderError::Invalid(message) => (StatusCode::UNPROCESSABLE_ENTITY, message),
+            OrderError::OutOfStock(out_of_stock) => (StatusCode::CONFLICT, out_of_stock.to_string()),
+            OrderError::Database(e) => {
+                eprintln!("Failed to place order: {:?}", e);
+                (StatusCode::INTERNAL_SERVER_ERROR, String::from("Internal server error"))
+            }
+        };
+        (status, Json(serde_json::json!({ "error": error }))).into_response()
+    }
+}
+
+pub fn router(state: OrderState) -> Router {
+    Router::new()
+        .route("/orders", post(create_order))
+        .with_state(state)
+}
+
+/// Places an order for existing client and products and publishes it like a random one.
+async fn create_order(
+    State(state): State<OrderState>,
+    Json(request): Json<OrderRequest>,
+) -> Result<(StatusCode, Json<OrderResponse>), OrderError> {
+    if request.items.is_empty() {
+        return Err(OrderError::Invalid(String::from("An order needs at least one item")));
+    }
+
+    // The same product listed twice is ordered once with the summed quantity
+    let mut quantities: HashMap<i32, i32> = HashMap::new();
+    for item in &request.items {
+        if item.quantity < 1 {
+            return Err(OrderError::Invalid(format!(
+                "Quantity of product {} must be at least 1",
+                item.product_id
+            )));
+        }
+        let quantity = quantities.entry(item.product_id).or_default();
+        *quantity = quantity
+            .checked_add(item.quantity)
+            .filter(|quantity| *quantity <= MAX_LINE_QUANTITY)
+            .ok_or_else(|| {
+                OrderError::Invalid(format!(
+                    "Quantity of product {} must be at most {}",
+                    item.product_id, MAX_LINE_QUANTITY
+                ))
+            })?;
+    }
+    // Each line is bounded, but their count isn't
+    quantities
+        .values()
+        .try_fold(0i32, |units, quantity| units.checked_add(*quantity))
+        .filter(|units| *units <= MAX_ORDER_UNITS)
+        .ok_or_else(|| OrderError::Invalid(format!("An order holds at most {} units", MAX_ORDER_UNITS)))?;
+
+    let client = sqlx::query_as::<_, Client>(&format!("SELECT {} FROM Client WHERE id = $1", CLIENT_COLUMNS))
+        .bind(request.client_id)
+        .fetch_optional(&state.pool)
+        .await?
+        .ok_or_else(|| OrderError::Invalid(format!("Client with ID {} not found", request.client_id)))?;
+
+    let product_ids: Vec<i32> = quantities.keys().copied().collect();
+    let products = sqlx::query_as::<_, ProductFromDb>("SELECT id, name, price, currency FROM Product WHERE id = ANY($1)")
+        .bind(&product_ids)
+        .fetch_all(&state.pool)
+        .await?;
+
+    if products.len() != product_ids.len() {
+        let mut missing: Vec<i32> = product_ids
+            .into_iter()
+            .filter(|id| !products.iter().any(|product| product.id == *id))
+            .collect();
+        missing.sort_unstable();
+        return Err(OrderError::Invalid(format!("Products not found: {:?}", missing)));
+    }
+
+    let lines = products
+        .into_iter()
+        .map(|product| {
+            let quantity = quantities[&product.id];
+            (product, quantity)
+        })
+        .collect();
+
+    let command = place_order(
+        &state.pool,
+        &state.sender,
+        &state.sr_settings,
+        client,
+        chrono::Utc::now(),
+        lines,
+        None,
+    )
+    .await?
+    .map_err(OrderError::OutOfStock)?;
+
+    Ok((StatusCode::CREATED, Json(OrderResponse { command_id: command.id })))
+}
@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+
+use clap::Args;
+use common::currency::{convert, rate_on};
+use common::delivery::Sender;
+use common::lifecycle::RefundLine;
+use common::payment::Payment;
+use common::vat::round_cents;
+use rand::distributions::WeightedIndex;
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand_distr::Distribution;
+use schema_registry_converter::async_impl::avro::AvroEncoder;
+use sqlx::{PgConnection, PgPool};
+
+use crate::seed::publish_all;
+
+/// Payment methods, with their share of the payments.
+const METHODS: [(&str, u32); 3] = [("card", 70), ("paypal", 20), ("bank_transfer", 10)];
+
+#[derive(Args, Clone, Debug)]
+pub struct PaymentOptions {
+    /// Probability that the payment of an order fails. The order then stays placed
+    #[arg(long, default_value_t = 0.05)]
+    pub payment_failure_rate: f64,
+
+    /// Probability that less than the order total is captured
+    #[arg(long, default_value_t = 0.02)]
+    pub payment_underpay_rate: f64,
+
+    /// Probability that more than the order total is captured
+    #[arg(long, default_value_t = 0.01)]
+    pub payment_overpay_rate: f64,
+}
+
+/// A payment event, before it is inserted.
+pub struct Attempt {
+    status: &'static str,
+    amount: f64,
+    method: &'static str,
+}
+
+impl Attempt {
+    pub fn failed(&self) -> bool {
+        self.status == "failed"
+    }
+}
+
+impl PaymentOptions {
+    /// Draws the payment events of an order of `total`: a failure, or an authorization followed
+    /// by a capture, usually of the total.
+    pub fn draw(&self, rng: &mut StdRng, total: f64) -> Vec<Attempt> {
+        let method = WeightedIndex::new(METHODS.iter().map(|(_, share)| share))
+            .map(|shares| METHODS[shares.sample(rng)].0)
+            .unwrap_or("card");
+        if rng.gen_bool(self.payment_failure_rate.clamp(0.0, 1.0)) {
+            return vec![Attempt { status: "failed", amount: total, method }];
+        }
+        let roll: f64 = rng.gen();
+        let captured = if roll < self.payment_underpay_rate {
+            total * rng.gen_range(0.5..0.99)
+        } else if roll < self.payment_underpay_rate + self.payment_overpay_rate {
+            total * rng.gen_range(1.01..1.2)
+        } else {
+            total
+        };
+        vec![
+            Attempt { status: "authorized", amount: total, method },
+            Attempt { status: "captured", amount: (captured * 100.0).round() / 100.0, method },
+        ]
+    }
+}
+
+/// The refund of a captured order: the value of the refunded units, or everything captured and not
+/// refunded yet when the order is cancelled, paid back as a negative `refunded` payment with the
+/// method of the capture. Nothing if no money is left to pay back.
+pub async fn refund(pool: &PgPool, command_id: i32, lines: Option<&[RefundLine]>) -> Result<Vec<Attempt>, sqlx::Error> {
+    let (balance, method): (f64, Option<String>) = sqlx::query_as(
+        "SELECT COALESCE(SUM(amount) FILTER (WHERE status IN ('captured', 'refunded')), 0),
+            MAX(method) FILTER (WHERE status = 'captured')
+        FROM Payment WHERE commandId = $1",
+    )
+    .bind(command_id)
+    .fetch_one(pool)
+    .await?;
+    let Some(method) = method else {
+        return Ok(Vec::new());
+    };
+    let amount = match lines {
+        Some(lines) => {
+            let ordered = self::lines(pool, command_id).await?;
+            let value: f64 = lines
+                .iter()
+                .map(|line| ordered.get(&line.product_id).map_or(0.0, |(price, _)| price * line.quantity as f64))
+                .sum();
+            // An underpaid order gets back at most what was paid
+            value.min(balance)
+        }
+        None => balance,
+    };
+    let amount = round_cents(amount);
+    if amount <= 0.0 {
+        return Ok(Vec::new());
+    }
+    let method = METHODS.iter().map(|(method, _)| *method).find(|known| *known == method).unwrap_or("card");
+    Ok(vec![Attempt { status: "refunded", amount: -amount, method }])
+}
+
+/// Unit price and quantity of each product of the order, the price in the currency of the order,
+/// converted with the rate of the order date as the merger invoices it.
+async fn lines(pool: &PgPool, command_id: i32) -> Result<HashMap<i32, (f64, i32)>, sqlx::Error> {
+    let (currency, date): (String, String) = sqlx::query_as(
+        "SELECT currency, TO_CHAR(date, 'YYYY-MM-DD') FROM Command WHERE id = $1",
+    )
+    .bind(command_id)
+    .fetch_one(pool)
+    .await?;
+    let rows: Vec<(i32, f64, i32, String)> = sqlx::query_as(
+        "SELECT productId, price, quantity, currency FROM CommandProduct WHERE commandId = $1",
+    )
+    .bind(command_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut lines = HashMap::new();
+    for (product_id, price, quantity, from) in rows {
+        // Without a rate the invoice is dead-lettered, whatever is paid
+        let rate = rate_on(pool, &from, &currency, &date).await?.unwrap_or(1.0);
+        lines.insert(product_id, (convert(price, rate), quantity));
+    }
+    Ok(lines)
+}
+
+/// Total of the order at the prices and in the currencies it was placed with.
+pub async fn order_total(pool: &PgPool, command_id: i32) -> Result<f64, sqlx::Error> {
+    let lines = lines(pool, command_id).await?;
+    Ok(round_cents(lines.values().map(|(price, quantity)| price * *quantity as f64).sum()))
+}
+
+/// Inserts the payment events of the order, within the lifecycle transaction.
+pub async fn insert(conn: &mut PgConnection, command_id: i32, attempts: &[Attempt]) -> Result<Vec<Payment>, sqlx::Error> {
+    if attempts.is_empty() {
+        return Ok(Vec::new());
+    }
+    let rows: Vec<(i32, String, f64, String, String)> = sqlx::query_as(
+        r#"INSERT INTO Payment (commandId, status, amount, method)
+        SELECT $1, * FROM UNNEST($2::VARCHAR[], $3::FLOAT[], $4::VARCHAR[])
+        RETURNING id, status, amount, method, TO_CHAR(at AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS.MS"Z"')"#,
+    )
+    .bind(command_id)
+    .bind(attempts.iter().map(|attempt| attempt.status).collect::<Vec<&str>>())
+    .bind(attempts.iter().map(|attempt| attempt.amount).collect::<Vec<f64>>())
+    .bind(attempts.iter().map(|attempt| attempt.method).collect::<Vec<&str>>())
+    .fetch_all(conn)
+    .await?;
+    let mut payments: Vec<Payment> = rows
+        .into_iter()
+        .map(|(id, status, amount, method, at)| Payment { id, command_id, status, amount, method, at })
+        .collect();
+    payments.sort_by_key(|payment| payment.id);
+    Ok(payments)
+}
+
+/// Publishes the payment events to the `Payment` topic.
+pub async fn publish(sender: &Sender, encoder: &AvroEncoder<'_>, payments: Vec<Payment>) {
+    if payments.is_empty() {
+        return;
+    }
+    let payments = futures::stream::iter(payments.into_iter().map(Ok));
+    if let Err(e) = publish_all(sender, encoder, "Payment", payments, |payment| payment.command_id).await {
+        eprintln!("Failed to publish payments: {:?}", e);
+    }
+}
\ No newline at end of file
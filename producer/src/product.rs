@@ -1,35 +1,56 @@
 use async_trait::async_trait;
 use common::product::ProductInterface;
 use fake::{faker::lorem::fr_fr::Word, Fake};
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
 use rand::Rng;
 use sqlx::PgPool;
 
+/// Product categories, with their share of the catalog and their price band.
+const CATEGORIES: [(&str, u32, f64, f64); 5] = [
+    ("grocery", 35, 1.0, 15.0),
+    ("books", 20, 5.0, 40.0),
+    ("clothing", 20, 10.0, 120.0),
+    ("home", 15, 20.0, 300.0),
+    ("electronics", 10, 50.0, 1500.0),
+];
+
 #[derive(Debug)]
 #[allow(dead_code)]
 pub struct MyProduct {
     pub id: i32,
     pub name: String,
     pub price: f64,
+    pub category: String,
 }
 
 #[async_trait]
 impl ProductInterface for MyProduct {
-    fn generate_random() -> Self {
+    fn generate_random(rng: &mut StdRng) -> Self {
+        let index = WeightedIndex::new(CATEGORIES.iter().map(|(_, share, _, _)| share))
+            .map(|shares| shares.sample(rng))
+            .unwrap_or(0);
+        let (category, _, min_price, max_price) = CATEGORIES[index];
+
+        // Prices are spread log-uniformly, so that cheap products are as common in a band as
+        // expensive ones relative to their price
+        let price = (min_price.ln() + rng.gen::<f64>() * (max_price.ln() - min_price.ln())).exp();
+
         MyProduct {
             id: 0, // This will be set by the database
-            name: Word().fake::<String>(),
-            price: rand::thread_rng().gen_range(1.0..=100.0),
+            name: Word().fake_with_rng::<String, _>(rng),
+            price: (price * 100.0).round() / 100.0,
+            category: category.to_string(),
         }
     }
 
     async fn insert_into_db(&self, pool: &PgPool) -> Result<(), sqlx::Error> {
-        sqlx::query!(
-            "INSERT INTO Product (name, price) VALUES ($1, $2)",
-            self.name,
-            self.price
-        )
-        .execute(pool)
-        .await?;
+        sqlx::query("INSERT INTO Product (name, price, category) VALUES ($1, $2, $3)")
+            .bind(&self.name)
+            .bind(self.price)
+            .bind(&self.category)
+            .execute(pool)
+            .await?;
         Ok(())
     }
 }
\ No newline at end of file
@@ -0,0 +1,172 @@
+use std::time::Instant;
+
+use clap::Args;
+use common::client::{Client, ClientInterface, CLIENT_COLUMNS};
+use common::delivery::Sender;
+use common::product::{CatalogProduct, ProductInterface, CATALOG_PRODUCT_COLUMNS};
+use common::rng::SharedRng;
+use futures::future::join_all;
+use futures::TryStreamExt;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use schema_registry_converter::async_impl::avro::AvroEncoder;
+use schema_registry_converter::async_impl::schema_registry::SrSettings;
+use schema_registry_converter::schema_registry_common::SubjectNameStrategy;
+use serde::Serialize;
+use sqlx::PgPool;
+
+use crate::client::MyClient;
+use crate::product::MyProduct;
+
+/// Number of rows sent to `COPY` at once.
+const COPY_CHUNK_SIZE: u64 = 10_000;
+
+/// Number of messages published before waiting for their acknowledgements.
+const PUBLISH_CHUNK_SIZE: usize = 1_000;
+
+#[derive(Args, Debug)]
+pub struct SeedOptions {
+    /// Number of clients the database holds once seeded
+    #[arg(long, default_value_t = 100, requires = "seed")]
+    clients: u64,
+
+    /// Number of products the database holds once seeded
+    #[arg(long, default_value_t = 100, requires = "seed")]
+    products: u64,
+
+    /// If provided, deletes the clients, the products and the orders before seeding
+    #[arg(long, requires = "seed")]
+    reset: bool,
+
+    /// With `--reset`, also deletes the invoice numbers, the credit notes and the journal entries,
+    /// so that legal invoice numbering and FEC numbering start over
+    #[arg(long, requires = "reset")]
+    reset_ledger: bool,
+}
+
+/// Tops the catalog up to the requested number of clients and products with `COPY`, then
+/// publishes the whole catalog to the compacted `Client` and `ProductCatalog` topics.
+///
+/// Running it twice with the same counts inserts nothing the second time.
+pub async fn run(
+    options: &SeedOptions,
+    pool: &PgPool,
+    sender: &Sender,
+    sr_settings: &SrSettings,
+    rng: &SharedRng,
+) -> Result<(), sqlx::Error> {
+    let started = Instant::now();
+    let mut rng = StdRng::seed_from_u64(rng.lock().gen());
+
+    if options.reset_ledger {
+        sqlx::query("TRUNCATE Client, Product, Command, DeadLetter, CreditNote, InvoiceSequence, InvoiceEmail, JournalEntry RESTART IDENTITY CASCADE")
+            .execute(pool)
+            .await?;
+        println!("Clients, products, orders, invoice numbers and journal entries deleted");
+    } else if options.reset {
+        // Deleting the orders deletes the numbers of their invoices: the ledger is only reset on demand
+        let numbered: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM InvoiceSequence)").fetch_one(pool).await?;
+        if numbered {
+            return Err(sqlx::Error::Protocol(String::from(
+                "Invoices were numbered, add --reset-ledger to delete them and restart their numbering",
+            )));
+        }
+        sqlx::query("TRUNCATE Client, Product, Command, DeadLetter RESTART IDENTITY CASCADE")
+            .execute(pool)
+            .await?;
+        println!("Clients, products and orders deleted");
+    }
+
+    let existing: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM Client").fetch_one(pool).await?;
+    let missing = options.clients.saturating_sub(existing as u64);
+    copy_rows(pool, "COPY Client (name, email, address, currency) FROM STDIN WITH (FORMAT csv)", missing, |writer| {
+        let client = MyClient::generate_random(&mut rng);
+        writer.write_record([&client.name, &client.email, &client.address, &client.currency])
+    })
+    .await?;
+    println!("{} clients inserted in {:.2?}", missing, started.elapsed());
+
+    let existing: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM Product").fetch_one(pool).await?;
+    let missing = options.products.saturating_sub(existing as u64);
+    copy_rows(pool, "COPY Product (name, price, category) FROM STDIN WITH (FORMAT csv)", missing, |writer| {
+        let product = MyProduct::generate_random(&mut rng);
+        writer.write_record([&product.name, &product.price.to_string(), &product.category])
+    })
+    .await?;
+    println!("{} products inserted in {:.2?}", missing, started.elapsed());
+
+    let encoder = AvroEncoder::new(sr_settings.clone());
+    let query = format!("SELECT {} FROM Client ORDER BY id", CLIENT_COLUMNS);
+    let clients = sqlx::query_as::<_, Client>(&query).fetch(pool);
+    let published = publish_all(sender, &encoder, "Client", clients, |client| client.id).await?;
+    println!("{} clients published in {:.2?}", published, started.elapsed());
+
+    let query = format!("SELECT {} FROM Product ORDER BY id", CATALOG_PRODUCT_COLUMNS);
+    let products = sqlx::query_as::<_, CatalogProduct>(&query).fetch(pool);
+    let published = publish_all(sender, &encoder, "ProductCatalog", products, |product| product.id).await?;
+    println!("{} products published in {:.2?}", published, started.elapsed());
+
+    Ok(())
+}
+
+/// Inserts `count` rows written by `write_row` with `COPY`, in a single transaction.
+async fn copy_rows<F>(pool: &PgPool, statement: &str, count: u64, mut write_row: F) -> Result<(), sqlx::Error>
+where
+    F: FnMut(&mut csv::Writer<Vec<u8>>) -> csv::Result<()>,
+{
+    if count == 0 {
+        return Ok(());
+    }
+    let mut tx = pool.begin().await?;
+    let mut copy = tx.copy_in_raw(statement).await?;
+    let mut written = 0;
+    while written < count {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        for _ in 0..COPY_CHUNK_SIZE.min(count - written) {
+            write_row(&mut writer).map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+            written += 1;
+        }
+        let chunk = writer.into_inner().map_err(|e| sqlx::Error::Encode(Box::new(e.into_error())))?;
+        copy.send(chunk).await?;
+    }
+    copy.finish().await?;
+    tx.commit().await
+}
+
+/// Publishes every row of `rows` to `topic`, keyed by `key`. Returns the number of rows
+/// acknowledged by the broker.
+pub async fn publish_all<T, S, K>(
+    sender: &Sender,
+    encoder: &AvroEncoder<'_>,
+    topic: &str,
+    rows: S,
+    key: K,
+) -> Result<usize, sqlx::Error>
+where
+    T: Serialize,
+    S: futures::Stream<Item = Result<T, sqlx::Error>> + Unpin,
+    K: Fn(&T) -> i32,
+{
+    let strategy = SubjectNameStrategy::TopicNameStrategy(topic.to_string(), false);
+    let mut published = 0;
+    // Rows are acknowledged a chunk at a time, rather than waiting for each of them
+    let mut chunks = rows.try_chunks(PUBLISH_CHUNK_SIZE);
+    while let Some(chunk) = chunks.try_next().await.map_err(|e| e.1)? {
+        let mut messages = Vec::with_capacity(chunk.len());
+        for row in &chunk {
+            let payload = encoder
+                .encode_struct(row, &strategy)
+                .await
+                .unwrap_or_else(|e| panic!("Failed to encode {} object: {:?}", topic.to_lowercase(), e));
+            messages.push((key(row).to_string(), payload));
+        }
+        let results = join_all(messages.iter().map(|(key, payload)| sender.send(topic, key, payload, None))).await;
+        for result in results {
+            match result {
+                Ok(()) => published += 1,
+                Err(e) => eprintln!("Failed to deliver message to {}: {:?}", topic, e),
+            }
+        }
+    }
+    Ok(published)
+}
\ No newline at end of file
@@ -0,0 +1,130 @@
+use std::time::Duration;
+
+use clap::Args;
+use common::client::{Client, CLIENT_COLUMNS};
+use common::delivery::Sender;
+use common::product::{CatalogProduct, CATALOG_PRODUCT_COLUMNS};
+use fake::faker::address::en::SecondaryAddress;
+use fake::faker::internet::en::SafeEmail;
+use fake::Fake;
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand_distr::{Distribution, Exp};
+use schema_registry_converter::async_impl::avro::AvroEncoder;
+use schema_registry_converter::async_impl::schema_registry::SrSettings;
+use sqlx::PgPool;
+
+use crate::catalog::{Catalog, SharedCatalog};
+use crate::seed::publish_all;
+
+#[derive(Args, Clone, Debug)]
+pub struct UpdateOptions {
+    /// Average number of client updates per minute, half of them moves and half email changes
+    #[arg(long, default_value_t = 2.0)]
+    pub client_updates_per_minute: f64,
+
+    /// Average number of product price changes per minute
+    #[arg(long, default_value_t = 1.0)]
+    pub product_updates_per_minute: f64,
+}
+
+/// A change to a client or a product of the catalog.
+#[derive(Debug)]
+enum Update {
+    Move { client_id: i32, address: String },
+    Email { client_id: i32, email: String },
+    /// The price is multiplied by `factor`
+    Price { product_id: i32, factor: f64 },
+}
+
+impl Update {
+    /// Draws an update of a random client or product. Returns `None` while the catalog is empty.
+    fn draw(options: &UpdateOptions, rng: &mut StdRng, catalog: &Catalog) -> Option<Self> {
+        let total = options.client_updates_per_minute + options.product_updates_per_minute;
+        if rng.gen::<f64>() * total < options.client_updates_per_minute {
+            let client_id = catalog.random_client(rng)?;
+            match rng.gen_bool(0.5) {
+                true => Some(Update::Move { client_id, address: SecondaryAddress().fake_with_rng(rng) }),
+                false => Some(Update::Email { client_id, email: SafeEmail().fake_with_rng(rng) }),
+            }
+        } else {
+            let product_id = catalog.random_product(rng)?;
+            Some(Update::Price { product_id, factor: rng.gen_range(0.85..1.2) })
+        }
+    }
+
+    /// Applies the update to the database, bumping the version, then publishes the updated record
+    /// to the compacted `Client` or `ProductCatalog` topic.
+    async fn apply(&self, pool: &PgPool, sender: &Sender, encoder: &AvroEncoder<'_>) -> Result<(), sqlx::Error> {
+        match self {
+            Update::Move { client_id, address } => {
+                let query = format!(
+                    "UPDATE Client SET address = $2, version = version + 1, updatedAt = now() WHERE id = $1 RETURNING {}",
+                    CLIENT_COLUMNS
+                );
+                let client = sqlx::query_as::<_, Client>(&query).bind(client_id).bind(address).fetch_one(pool).await?;
+                println!("Client {} moved to {}, version {}", client.id, client.address, client.version);
+                publish_all(sender, encoder, "Client", futures::stream::iter([Ok(client)]), |client| client.id).await?;
+            }
+            Update::Email { client_id, email } => {
+                let query = format!(
+                    "UPDATE Client SET email = $2, version = version + 1, updatedAt = now() WHERE id = $1 RETURNING {}",
+                    CLIENT_COLUMNS
+                );
+                let client = sqlx::query_as::<_, Client>(&query).bind(client_id).bind(email).fetch_one(pool).await?;
+                println!("Client {} changed email to {}, version {}", client.id, client.email, client.version);
+                publish_all(sender, encoder, "Client", futures::stream::iter([Ok(client)]), |client| client.id).await?;
+            }
+            Update::Price { product_id, factor } => {
+                let query = format!(
+                    "UPDATE Product SET price = GREATEST(ROUND((price * $2)::NUMERIC, 2), 0.01)::FLOAT,
+                    version = version + 1, updatedAt = now()
+                    WHERE id = $1 RETURNING {}",
+                    CATALOG_PRODUCT_COLUMNS
+                );
+                let product =
+                    sqlx::query_as::<_, CatalogProduct>(&query).bind(product_id).bind(factor).fetch_one(pool).await?;
+                println!("Product {} now costs {}, version {}", product.id, product.price, product.version);
+                publish_all(sender, encoder, "ProductCatalog", futures::stream::iter([Ok(product)]), |product| {
+                    product.id
+                })
+                .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Updates random clients and products at the given rates, with exponentially distributed
+/// intervals, until the producer stops. Every draw comes from `rng`, seeded from the shared
+/// generator before the load starts, so that the updates don't change what the load workers draw.
+pub async fn run(
+    options: UpdateOptions,
+    pool: PgPool,
+    sender: Sender,
+    sr_settings: SrSettings,
+    mut rng: StdRng,
+    catalog: SharedCatalog,
+) {
+    let per_second = (options.client_updates_per_minute + options.product_updates_per_minute) / 60.0;
+    if per_second <= 0.0 {
+        return;
+    }
+    let Ok(interval) = Exp::new(per_second) else {
+        return;
+    };
+    let encoder = AvroEncoder::new(sr_settings);
+    loop {
+        let wait = interval.sample(&mut rng);
+        tokio::time::sleep(Duration::from_secs_f64(wait)).await;
+        let update = {
+            let catalog = catalog.read().await;
+            Update::draw(&options, &mut rng, &catalog)
+        };
+        if let Some(update) = update {
+            if let Err(e) = update.apply(&pool, &sender, &encoder).await {
+                eprintln!("Failed to apply {:?}: {:?}", update, e);
+            }
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,273 @@
+use std::collections::{BTreeMap, HashSet};
+
+use common::payment::{InvoiceStatus, Payment};
+
+/// Amounts closer than half a cent are equal.
+const TOLERANCE: f64 = 0.005;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Settlement {
+    Paid,
+    Underpaid,
+    Overpaid,
+    Unpaid,
+}
+
+impl Settlement {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Settlement::Paid => "paid",
+            Settlement::Underpaid => "underpaid",
+            Settlement::Overpaid => "overpaid",
+            Settlement::Unpaid => "unpaid",
+        }
+    }
+}
+
+/// What an order owes according to its invoice and credit notes, and what was paid for it.
+#[derive(Default)]
+pub struct Account {
+    /// Kafka timestamp of the invoice in milliseconds, `None` until the invoice arrives
+    invoiced_at: Option<i64>,
+    invoiced: f64,
+    /// Sum of the credit note totals, which are negative
+    credited: f64,
+    /// Sum of the captured payments and of the refunds, which are negative
+    pub paid: f64,
+    /// Ids of the credit notes and payments already counted, so that a replay counts them once
+    credit_notes: HashSet<i32>,
+    payments: HashSet<i32>,
+    /// Last published settlement, with the amounts it was computed from
+    published: Option<(Settlement, f64, f64)>,
+}
+
+impl Account {
+    pub fn is_invoiced(&self) -> bool {
+        self.invoiced_at.is_some()
+    }
+
+    pub fn due(&self) -> f64 {
+        self.invoiced + self.credited
+    }
+
+    /// The settlement at `now` in milliseconds, or `None` while the invoice is missing or an
+    /// order without payment is younger than `unpaid_after` milliseconds.
+    pub fn settlement(&self, now: i64, unpaid_after: i64) -> Option<Settlement> {
+        let invoiced_at = self.invoiced_at?;
+        if self.paid.abs() < TOLERANCE {
+            // A fully credited invoice owes nothing
+            if self.due().abs() < TOLERANCE {
+                return Some(Settlement::Paid);
+            }
+            return (now - invoiced_at >= unpaid_after).then_some(Settlement::Unpaid);
+        }
+        let difference = self.paid - self.due();
+        if difference.abs() < TOLERANCE {
+            Some(Settlement::Paid)
+        } else if difference < 0.0 {
+            Some(Settlement::Underpaid)
+        } else {
+            Some(Settlement::Overpaid)
+        }
+    }
+}
+
+/// The accounts of every order, by invoice id, which is the command id.
+pub struct Ledger {
+    accounts: BTreeMap<i32, Account>,
+    /// Milliseconds after its invoice an order without payment is unpaid
+    unpaid_after: i64,
+}
+
+impl Ledger {
+    pub fn new(unpaid_after: i64) -> Self {
+        Ledger {
+            accounts: BTreeMap::new(),
+            unpaid_after,
+        }
+    }
+
+    pub fn accounts(&self) -> &BTreeMap<i32, Account> {
+        &self.accounts
+    }
+
+    pub fn unpaid_after(&self) -> i64 {
+        self.unpaid_after
+    }
+
+    pub fn invoice(&mut self, invoice_id: i32, total: f64, at: i64) {
+        let account = self.accounts.entry(invoice_id).or_default();
+        account.invoiced = total;
+        account.invoiced_at = Some(at);
+    }
+
+    pub fn credit(&mut self, invoice_id: i32, credit_note_id: i32, total: f64) {
+        let account = self.accounts.entry(invoice_id).or_default();
+        if account.credit_notes.insert(credit_note_id) {
+            account.credited += total;
+        }
+    }
+
+    /// Counts the captured payments and the refunds. Authorizations and failures don't move money.
+    pub fn pay(&mut self, payment: &Payment) {
+        let account = self.accounts.entry(payment.command_id).or_default();
+        let moves_money = matches!(payment.status.as_str(), "captured" | "refunded");
+        if moves_money && account.payments.insert(payment.id) {
+            account.paid += payment.amount;
+        }
+    }
+
+    /// The status of the invoice if it changed since it was last returned.
+    pub fn change(&mut self, invoice_id: i32, now: i64) -> Option<InvoiceStatus> {
+        let unpaid_after = self.unpaid_after;
+        let account = self.accounts.get_mut(&invoice_id)?;
+        let settlement = account.settlement(now, unpaid_after)?;
+        let current = (settlement, account.due(), account.paid);
+        if account.published == Some(current) {
+            return None;
+        }
+        account.published = Some(current);
+        Some(InvoiceStatus {
+            invoice_id,
+            due: account.due(),
+            paid: account.paid,
+            status: settlement.as_str().to_string(),
+            at: chrono::Utc::now().to_rfc3339(),
+        })
+    }
+
+    /// The statuses of all the invoices that changed since they were last returned, such as the
+    /// orders that became unpaid with time.
+    pub fn changes(&mut self, now: i64) -> Vec<InvoiceStatus> {
+        let ids: Vec<i32> = self.accounts.keys().copied().collect();
+        ids.into_iter().filter_map(|invoice_id| self.change(invoice_id, now)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Milliseconds after its invoice an order without payment is unpaid.
+    const UNPAID_AFTER: i64 = 60_000;
+
+    fn payment(id: i32, status: &str, amount: f64) -> Payment {
+        Payment {
+            id,
+            command_id: 1,
+            status: status.to_string(),
+            amount,
+            method: String::from("card"),
+            at: String::from("2026-03-02T10:00:00Z"),
+        }
+    }
+
+    fn ledger() -> Ledger {
+        let mut ledger = Ledger::new(UNPAID_AFTER);
+        ledger.invoice(1, 100.0, 0);
+        ledger
+    }
+
+    fn settlement(ledger: &Ledger, now: i64) -> Option<Settlement> {
+        ledger.accounts()[&1].settlement(now, UNPAID_AFTER)
+    }
+
+    #[test]
+    fn exact_payment_is_paid() {
+        let mut ledger = ledger();
+        ledger.pay(&payment(1, "authorized", 100.0));
+        ledger.pay(&payment(2, "captured", 60.0));
+        ledger.pay(&payment(3, "captured", 40.004));
+        assert_eq!(settlement(&ledger, 1), Some(Settlement::Paid));
+    }
+
+    #[test]
+    fn short_and_excess_payments() {
+        let mut ledger = ledger();
+        ledger.pay(&payment(1, "captured", 99.0));
+        assert_eq!(settlement(&ledger, 1), Some(Settlement::Underpaid));
+        // A credit note for the missing unit settles it
+        ledger.credit(1, 7, -1.0);
+        assert_eq!(settlement(&ledger, 1), Some(Settlement::Paid));
+        ledger.pay(&payment(2, "captured", 2.0));
+        assert_eq!(settlement(&ledger, 1), Some(Settlement::Overpaid));
+    }
+
+    #[test]
+    fn unpaid_only_after_the_timeout() {
+        let mut ledger = ledger();
+        ledger.pay(&payment(1, "failed", 100.0));
+        assert_eq!(settlement(&ledger, UNPAID_AFTER - 1), None);
+        assert_eq!(settlement(&ledger, UNPAID_AFTER), Some(Settlement::Unpaid));
+
+        // Payments before their invoice wait for it
+        let mut ledger = Ledger::new(UNPAID_AFTER);
+        ledger.pay(&payment(1, "captured", 100.0));
+        assert!(!ledger.accounts()[&1].is_invoiced());
+        assert_eq!(settlement(&ledger, UNPAID_AFTER), None);
+        assert!(ledger.change(1, UNPAID_AFTER).is_none());
+    }
+
+    #[test]
+    fn fully_credited_invoice_owes_nothing() {
+        let mut ledger = ledger();
+        ledger.credit(1, 7, -30.0);
+        ledger.credit(1, 8, -70.0);
+        assert_eq!(ledger.accounts()[&1].due(), 0.0);
+        // Paid at once, without waiting for the timeout
+        assert_eq!(settlement(&ledger, 1), Some(Settlement::Paid));
+    }
+
+    #[test]
+    fn replays_are_counted_once() {
+        let mut ledger = ledger();
+        ledger.pay(&payment(1, "captured", 100.0));
+        ledger.pay(&payment(1, "captured", 100.0));
+        ledger.credit(1, 7, -10.0);
+        ledger.credit(1, 7, -10.0);
+        let account = &ledger.accounts()[&1];
+        assert_eq!((account.due(), account.paid), (90.0, 100.0));
+        assert_eq!(settlement(&ledger, 1), Some(Settlement::Overpaid));
+    }
+
+    #[test]
+    fn refund_after_capture_is_paid_back() {
+        let mut ledger = ledger();
+        ledger.pay(&payment(1, "captured", 100.0));
+        // The refund of a unit credits it and pays it back
+        ledger.credit(1, 7, -30.0);
+        assert_eq!(settlement(&ledger, 1), Some(Settlement::Overpaid));
+        ledger.pay(&payment(2, "refunded", -30.0));
+        let account = &ledger.accounts()[&1];
+        assert_eq!((account.due(), account.paid), (70.0, 70.0));
+        assert_eq!(settlement(&ledger, 1), Some(Settlement::Paid));
+
+        // Cancelling the rest pays back everything left
+        ledger.credit(1, 8, -70.0);
+        ledger.pay(&payment(3, "refunded", -70.0));
+        ledger.pay(&payment(3, "refunded", -70.0));
+        let account = &ledger.accounts()[&1];
+        assert_eq!((account.due(), account.paid), (0.0, 0.0));
+        assert_eq!(settlement(&ledger, 1), Some(Settlement::Paid));
+    }
+
+    #[test]
+    fn changes_are_published_once() {
+        let mut ledger = ledger();
+        assert!(ledger.change(1, 1).is_none());
+        let unpaid = ledger.change(1, UNPAID_AFTER).unwrap();
+        assert_eq!((unpaid.status.as_str(), unpaid.due, unpaid.paid), ("unpaid", 100.0, 0.0));
+        assert!(ledger.changes(UNPAID_AFTER + 1).is_empty());
+
+        ledger.pay(&payment(1, "captured", 40.0));
+        let underpaid = ledger.changes(UNPAID_AFTER + 2);
+        assert_eq!(underpaid.len(), 1);
+        assert_eq!((underpaid[0].status.as_str(), underpaid[0].paid), ("underpaid", 40.0));
+
+        // A replayed payment changes nothing, so nothing is published again
+        ledger.pay(&payment(1, "captured", 40.0));
+        assert!(ledger.change(1, UNPAID_AFTER + 3).is_none());
+        // Neither does an unknown invoice
+        assert!(ledger.change(2, UNPAID_AFTER + 3).is_none());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,176 @@
+mod ledger;
+mod report;
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::Parser;
+use common::avro::decode_payload;
+use common::credit_note::CreditNote;
+use common::invoice::Invoice;
+use common::payment::{InvoiceStatus, Payment};
+use ledger::Ledger;
+use rdkafka::config::RDKafkaLogLevel;
+use rdkafka::consumer::{Consumer, StreamConsumer};
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::ClientConfig;
+use rdkafka::Message;
+use schema_registry_converter::async_impl::avro::{AvroDecoder, AvroEncoder};
+use schema_registry_converter::async_impl::schema_registry::{post_schema, SrSettings};
+use schema_registry_converter::schema_registry_common::{SchemaType, SubjectNameStrategy, SuppliedSchema};
+use serde_avro_derive::BuildSchema;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    /// Seconds after its invoice an order without captured payment is unpaid
+    #[arg(long, default_value_t = 600)]
+    unpaid_after_secs: i64,
+
+    /// CSV file the reconciliation report is written to
+    #[arg(long, default_value = "reconciliation-report.csv")]
+    report: PathBuf,
+
+    /// Seconds between two reconciliation reports
+    #[arg(long, default_value_t = 60)]
+    report_interval_secs: u64,
+}
+
+async fn send_status(producer: &FutureProducer, encoder: &AvroEncoder<'_>, status: &InvoiceStatus) {
+    let status_msg = match encoder
+        .encode_struct(
+            status,
+            &SubjectNameStrategy::TopicNameStrategy("InvoiceStatus".to_string(), false),
+        )
+        .await
+    {
+        Ok(status_msg) => status_msg,
+        Err(e) => {
+            eprintln!("Failed to encode the status of invoice {}: {}", status.invoice_id, e);
+            return;
+        }
+    };
+    let sent = producer
+        .send(
+            FutureRecord::to("InvoiceStatus")
+                .key(&status.invoice_id.to_string())
+                .payload(&status_msg),
+            Duration::from_secs(0),
+        )
+        .await;
+    match sent {
+        Ok(_) => println!("Invoice {} is {} ({:.2} due, {:.2} paid)", status.invoice_id, status.status, status.due, status.paid),
+        Err((e, _)) => eprintln!("Failed to send the status of invoice {}: {}", status.invoice_id, e),
+    }
+}
+
+/// Applies a message to the ledger and returns the invoice it concerns.
+async fn apply(
+    decoder: &AvroDecoder<'_>,
+    ledger: &mut Ledger,
+    topic: &str,
+    payload: &[u8],
+    timestamp: i64,
+) -> Result<Option<i32>, Box<dyn std::error::Error>> {
+    match topic {
+        "Invoice" => {
+            let invoice = decode_payload::<Invoice>(decoder, payload).await?;
+            ledger.invoice(invoice.id, invoice.total_price, timestamp);
+            Ok(Some(invoice.id))
+        }
+        "CreditNote" => {
+            let credit_note = decode_payload::<CreditNote>(decoder, payload).await?;
+            ledger.credit(credit_note.invoice_id, credit_note.id, credit_note.total_price);
+            Ok(Some(credit_note.invoice_id))
+        }
+        "Payment" => {
+            let payment = decode_payload::<Payment>(decoder, payload).await?;
+            ledger.pay(&payment);
+            Ok(Some(payment.command_id))
+        }
+        _ => Ok(None),
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+    let sr_settings = SrSettings::new(String::from("http://localhost:8085"));
+    let decoder = AvroDecoder::new(sr_settings.clone());
+    let encoder = AvroEncoder::new(sr_settings.clone());
+
+    // Offsets are never committed: the ledger is rebuilt by replaying the topics at every start.
+    let consumer: StreamConsumer = ClientConfig::new()
+        .set("group.id", "reconciler")
+        .set("bootstrap.servers", "localhost:19092")
+        .set("enable.partition.eof", "false")
+        .set("auto.offset.reset", "earliest")
+        .set("session.timeout.ms", "6000")
+        .set("enable.auto.commit", "false")
+        .set_log_level(RDKafkaLogLevel::Debug)
+        .create()
+        .expect("Consumer creation failed");
+
+    let producer: FutureProducer = ClientConfig::new()
+        .set("bootstrap.servers", "localhost:19092")
+        .create()
+        .expect("Failed to create Kafka producer");
+
+    let invoice_status_schema = InvoiceStatus::schema().unwrap();
+    let invoice_status_supplied_schema = SuppliedSchema {
+        name: Some(String::from("InvoiceStatus")),
+        schema_type: SchemaType::Avro,
+        schema: String::from(invoice_status_schema.json()),
+        references: vec![],
+    };
+    if let Err(e) = post_schema(&sr_settings, "InvoiceStatus-value".to_string(), invoice_status_supplied_schema).await {
+        eprintln!("Failed to post invoice status schema: {}", e);
+    };
+
+    consumer
+        .subscribe(&["Invoice", "CreditNote", "Payment"])
+        .expect("Failed to subscribe to topics");
+
+    let mut ledger = Ledger::new(cli.unpaid_after_secs * 1000);
+    let mut reports = tokio::time::interval(Duration::from_secs(cli.report_interval_secs.max(1)));
+
+    loop {
+        tokio::select! {
+            message = consumer.recv() => match message {
+                Ok(message) => {
+                    let Some(payload) = message.payload() else {
+                        continue;
+                    };
+                    let now = chrono::Utc::now().timestamp_millis();
+                    let timestamp = message.timestamp().to_millis().unwrap_or(now);
+                    match apply(&decoder, &mut ledger, message.topic(), payload, timestamp).await {
+                        Ok(Some(invoice_id)) => {
+                            if let Some(status) = ledger.change(invoice_id, now) {
+                                send_status(&producer, &encoder, &status).await;
+                            }
+                        }
+                        Ok(None) => (),
+                        Err(e) => eprintln!(
+                            "Skipping message {}/{}/{}: {}",
+                            message.topic(),
+                            message.partition(),
+                            message.offset(),
+                            e
+                        ),
+                    }
+                }
+                Err(e) => eprintln!("Error while consuming: {:?}", e),
+            },
+            _ = reports.tick() => {
+                // Orders without payment become unpaid with time, not with a message
+                let now = chrono::Utc::now().timestamp_millis();
+                for status in ledger.changes(now) {
+                    send_status(&producer, &encoder, &status).await;
+                }
+                if let Err(e) = report::write(&cli.report, &ledger, now) {
+                    eprintln!("Failed to write the reconciliation report: {}", e);
+                }
+            }
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,36 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::ledger::Ledger;
+
+/// Writes one row per invoice with what it owes, what was paid and its settlement, then prints
+/// the number of invoices and the amounts per settlement.
+pub fn write(path: &Path, ledger: &Ledger, now: i64) -> Result<(), csv::Error> {
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record(["invoice_id", "due", "paid", "status"])?;
+
+    let mut totals: BTreeMap<&str, (usize, f64, f64)> = BTreeMap::new();
+    for (invoice_id, account) in ledger.accounts().iter().filter(|(_, account)| account.is_invoiced()) {
+        let status = account
+            .settlement(now, ledger.unpaid_after())
+            .map(|settlement| settlement.as_str())
+            .unwrap_or("pending");
+        writer.write_record([
+            invoice_id.to_string(),
+            format!("{:.2}", account.due()),
+            format!("{:.2}", account.paid),
+            status.to_string(),
+        ])?;
+        let total = totals.entry(status).or_default();
+        total.0 += 1;
+        total.1 += account.due();
+        total.2 += account.paid;
+    }
+    writer.flush()?;
+
+    println!("Reconciliation report written to {}", path.display());
+    for (status, (count, due, paid)) in totals {
+        println!("{:>10}: {} invoices, {:.2} due, {:.2} paid", status, count, due, paid);
+    }
+    Ok(())
+}
\ No newline at end of file
@@ -0,0 +1,12 @@
+use minijinja::Environment;
+
+use crate::view::InvoiceView;
+
+const TEMPLATE: &str = include_str!("../templates/invoice.html");
+
+/// Renders the invoice as a standalone HTML page. Values are escaped by the template engine.
+pub fn render(view: &InvoiceView) -> Result<String, minijinja::Error> {
+    let mut environment = Environment::new();
+    environment.add_template("invoice.html", TEMPLATE)?;
+    environment.get_template("invoice.html")?.render(view)
+}
\ No newline at end of file
@@ -0,0 +1,7 @@
+pub mod html;
+pub mod locale;
+pub mod pdf;
+pub mod view;
+
+pub use locale::Locale;
+pub use view::{InvoiceView, RenderOptions};
\ No newline at end of file
@@ -0,0 +1,190 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{Datelike, NaiveDate};
+use serde::Serialize;
+
+/// Narrow no-break space, the French thousands separator.
+const NARROW_NBSP: char = '\u{202F}';
+const NBSP: char = '\u{A0}';
+
+const EN_MONTHS: [&str; 12] = [
+    "January", "February", "March", "April", "May", "June", "July", "August", "September", "October", "November",
+    "December",
+];
+const FR_MONTHS: [&str; 12] = [
+    "janvier", "février", "mars", "avril", "mai", "juin", "juillet", "août", "septembre", "octobre", "novembre",
+    "décembre",
+];
+
+/// The words of an invoice.
+#[derive(Serialize)]
+pub struct Labels {
+    pub invoice: &'static str,
+    pub number: &'static str,
+    pub date: &'static str,
+    pub order: &'static str,
+    pub vat_id: &'static str,
+    pub bill_to: &'static str,
+    pub product: &'static str,
+    pub price: &'static str,
+    pub backordered: &'static str,
+    pub total_excluding_vat: &'static str,
+    pub vat: &'static str,
+    pub rounding: &'static str,
+    pub total: &'static str,
+}
+
+const EN_LABELS: Labels = Labels {
+    invoice: "Invoice",
+    number: "No.",
+    date: "Date",
+    order: "Order",
+    vat_id: "VAT ID",
+    bill_to: "Bill to",
+    product: "Product",
+    price: "Price incl. VAT",
+    backordered: "backordered",
+    total_excluding_vat: "Total excl. VAT",
+    vat: "VAT",
+    rounding: "Rounding",
+    total: "Total incl. VAT",
+};
+
+const FR_LABELS: Labels = Labels {
+    invoice: "Facture",
+    number: "N°",
+    date: "Date",
+    order: "Commande",
+    vat_id: "N° TVA",
+    bill_to: "Facturé à",
+    product: "Produit",
+    price: "Prix TTC",
+    backordered: "en réapprovisionnement",
+    total_excluding_vat: "Total HT",
+    vat: "TVA",
+    rounding: "Arrondi",
+    total: "Total TTC",
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Locale {
+    FrFr,
+    EnUs,
+}
+
+impl Locale {
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Locale::FrFr => "fr-FR",
+            Locale::EnUs => "en-US",
+        }
+    }
+
+    pub fn language(&self) -> &'static str {
+        match self {
+            Locale::FrFr => "fr",
+            Locale::EnUs => "en",
+        }
+    }
+
+    pub fn labels(&self) -> &'static Labels {
+        match self {
+            Locale::FrFr => &FR_LABELS,
+            Locale::EnUs => &EN_LABELS,
+        }
+    }
+
+    /// Formats `value` with `decimals` digits after the decimal separator and grouped thousands:
+    /// `1,234.50` in en-US, `1 234,50` in fr-FR.
+    pub fn number(&self, value: f64, decimals: usize) -> String {
+        let (group, decimal) = match self {
+            Locale::FrFr => (NARROW_NBSP, ','),
+            Locale::EnUs => (',', '.'),
+        };
+        let digits = format!("{:.*}", decimals, value.abs());
+        let (integer, fraction) = digits.split_once('.').unwrap_or((&digits, ""));
+
+        let mut formatted = String::new();
+        // No sign when the value rounds to zero
+        if value < 0.0 && digits.chars().any(|c| c.is_ascii_digit() && c != '0') {
+            formatted.push('-');
+        }
+        for (index, digit) in integer.chars().enumerate() {
+            if index > 0 && (integer.len() - index) % 3 == 0 {
+                formatted.push(group);
+            }
+            formatted.push(digit);
+        }
+        if !fraction.is_empty() {
+            formatted.push(decimal);
+            formatted.push_str(fraction);
+        }
+        formatted
+    }
+
+    /// Formats an amount in `currency`, an ISO 4217 code: `€1,234.50` in en-US,
+    /// `1 234,50 €` in fr-FR.
+    pub fn currency(&self, value: f64, currency: &str) -> String {
+        let number = self.number(value.abs(), 2);
+        let sign = if number.chars().any(|c| c.is_ascii_digit() && c != '0') && value < 0.0 { "-" } else { "" };
+        match self {
+            Locale::EnUs => match currency {
+                "EUR" => format!("{}€{}", sign, number),
+                "USD" => format!("{}${}", sign, number),
+                "GBP" => format!("{}£{}", sign, number),
+                _ => format!("{}{}{}{}", sign, currency, NBSP, number),
+            },
+            Locale::FrFr => {
+                let symbol = match currency {
+                    "EUR" => "€",
+                    "USD" => "$US",
+                    "GBP" => "£GB",
+                    _ => currency,
+                };
+                format!("{}{}{}{}", sign, number, NBSP, symbol)
+            }
+        }
+    }
+
+    /// Formats a rate in percent: `20%` in en-US, `20 %` in fr-FR.
+    pub fn percent(&self, value: f64) -> String {
+        let decimals = if value.fract() == 0.0 { 0 } else { 1 };
+        match self {
+            Locale::FrFr => format!("{}{}%", self.number(value, decimals), NARROW_NBSP),
+            Locale::EnUs => format!("{}%", self.number(value, decimals)),
+        }
+    }
+
+    /// Formats a `YYYY-MM-DD` date: `March 4, 2026` in en-US, `4 mars 2026` in fr-FR. Any other
+    /// text is kept as is.
+    pub fn date(&self, date: &str) -> String {
+        let Ok(date) = NaiveDate::parse_from_str(date, "%Y-%m-%d") else {
+            return date.to_string();
+        };
+        let month = date.month0() as usize;
+        match self {
+            Locale::EnUs => format!("{} {}, {}", EN_MONTHS[month], date.day(), date.year()),
+            Locale::FrFr if date.day() == 1 => format!("1er {} {}", FR_MONTHS[month], date.year()),
+            Locale::FrFr => format!("{} {} {}", date.day(), FR_MONTHS[month], date.year()),
+        }
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.tag())
+    }
+}
+
+impl FromStr for Locale {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fr-FR" | "fr" => Ok(Locale::FrFr),
+            "en-US" | "en" => Ok(Locale::EnUs),
+            _ => Err(format!("Unknown locale {}, expected fr-FR or en-US", s)),
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,216 @@
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+
+use crate::view::InvoiceView;
+
+/// A4, in points.
+const WIDTH: f32 = 595.0;
+const HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 50.0;
+const RIGHT: f32 = WIDTH - MARGIN;
+/// Left edge of the totals labels.
+const TOTALS: f32 = 330.0;
+const LINE_HEIGHT: f32 = 16.0;
+
+const REGULAR: Name = Name(b"F1");
+const BOLD: Name = Name(b"F2");
+
+/// Widths of the printable ASCII characters in Helvetica, in thousandths of the font size. Close
+/// enough for Helvetica-Bold to right-align amounts.
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556, 556, 556, 556,
+    556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667,
+    556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556, 333, 556,
+    556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722,
+    500, 500, 500, 334, 260, 334, 584,
+];
+
+/// Encodes `text` for the standard fonts, which use the Windows-1252 encoding. Characters it
+/// lacks become `?`.
+fn win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            ' '..='~' | '\u{A0}'..='\u{FF}' => c as u8,
+            // The narrow no-break space of French numbers
+            '\u{202F}' => 0xA0,
+            '€' => 0x80,
+            '…' => 0x85,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '–' => 0x96,
+            '—' => 0x97,
+            'Œ' => 0x8C,
+            'œ' => 0x9C,
+            _ => b'?',
+        })
+        .collect()
+}
+
+fn text_width(text: &[u8], size: f32) -> f32 {
+    let width: u32 = text
+        .iter()
+        .map(|byte| match byte {
+            32..=126 => HELVETICA_WIDTHS[(byte - 32) as usize] as u32,
+            0xA0 => 278,
+            _ => 556,
+        })
+        .sum();
+    width as f32 * size / 1000.0
+}
+
+/// Writes text top-down, starting a new page when the current one is full.
+struct Layout {
+    pages: Vec<Vec<u8>>,
+    content: Content,
+    y: f32,
+}
+
+impl Layout {
+    fn new() -> Self {
+        Layout {
+            pages: Vec::new(),
+            content: Content::new(),
+            y: HEIGHT - MARGIN,
+        }
+    }
+
+    /// Moves down by `height`, on a new page if there is no room left.
+    fn advance(&mut self, height: f32) {
+        if self.y - height < MARGIN {
+            let content = std::mem::replace(&mut self.content, Content::new());
+            self.pages.push(content.finish());
+            self.y = HEIGHT - MARGIN;
+        }
+        self.y -= height;
+    }
+
+    fn text(&mut self, font: Name, size: f32, x: f32, text: &str) {
+        self.content
+            .begin_text()
+            .set_font(font, size)
+            .next_line(x, self.y)
+            .show(Str(&win_ansi(text)))
+            .end_text();
+    }
+
+    fn text_right(&mut self, font: Name, size: f32, right: f32, text: &str) {
+        let encoded = win_ansi(text);
+        self.content
+            .begin_text()
+            .set_font(font, size)
+            .next_line(right - text_width(&encoded, size), self.y)
+            .show(Str(&encoded))
+            .end_text();
+    }
+
+    fn rule(&mut self, from: f32) {
+        self.content
+            .set_line_width(0.5)
+            .move_to(from, self.y)
+            .line_to(RIGHT, self.y)
+            .stroke();
+    }
+
+    fn finish(mut self) -> Vec<Vec<u8>> {
+        self.pages.push(self.content.finish());
+        self.pages
+    }
+}
+
+fn lay_out(view: &InvoiceView) -> Vec<Vec<u8>> {
+    let labels = view.labels;
+    let mut layout = Layout::new();
+
+    layout.advance(20.0);
+    layout.text(BOLD, 12.0, MARGIN, &view.seller.name);
+    layout.text_right(BOLD, 20.0, RIGHT, labels.invoice);
+    layout.advance(LINE_HEIGHT);
+    layout.text(REGULAR, 9.0, MARGIN, &view.seller.address);
+    layout.text_right(REGULAR, 10.0, RIGHT, &format!("{} {}", labels.number, view.number));
+    layout.advance(LINE_HEIGHT);
+    layout.text(REGULAR, 9.0, MARGIN, &view.seller.contact);
+    layout.text_right(REGULAR, 10.0, RIGHT, &format!("{} {}", labels.date, view.date));
+    layout.advance(LINE_HEIGHT);
+    layout.text_right(REGULAR, 10.0, RIGHT, &format!("{} {}", labels.order, view.command_id));
+
+    layout.advance(2.0 * LINE_HEIGHT);
+    layout.text(BOLD, 10.0, MARGIN, labels.bill_to);
+    for line in [&view.client.name, &view.client.address, &view.client.contact] {
+        layout.advance(LINE_HEIGHT);
+        layout.text(REGULAR, 10.0, MARGIN, line);
+    }
+
+    layout.advance(2.0 * LINE_HEIGHT);
+    layout.text(BOLD, 10.0, MARGIN, labels.product);
+    layout.text_right(BOLD, 10.0, RIGHT, labels.price);
+    layout.advance(6.0);
+    layout.rule(MARGIN);
+    for line in &view.lines {
+        layout.advance(LINE_HEIGHT);
+        let name = match line.backordered {
+            true => format!("{} ({})", line.name, labels.backordered),
+            false => line.name.clone(),
+        };
+        layout.text(REGULAR, 10.0, MARGIN, &name);
+        layout.text_right(REGULAR, 10.0, RIGHT, &line.price);
+    }
+
+    layout.advance(10.0);
+    layout.rule(TOTALS);
+    let mut totals = vec![
+        (labels.total_excluding_vat, &view.net),
+        (view.vat_label.as_str(), &view.vat),
+    ];
+    if let Some(rounding) = &view.rounding {
+        totals.push((labels.rounding, rounding));
+    }
+    for (label, amount) in totals {
+        layout.advance(LINE_HEIGHT);
+        layout.text(REGULAR, 10.0, TOTALS, label);
+        layout.text_right(REGULAR, 10.0, RIGHT, amount);
+    }
+    layout.advance(LINE_HEIGHT + 4.0);
+    layout.text(BOLD, 12.0, TOTALS, labels.total);
+    layout.text_right(BOLD, 12.0, RIGHT, &view.total);
+
+    layout.finish()
+}
+
+/// Renders the invoice as an A4 PDF with the standard Helvetica fonts, which readers provide, so
+/// nothing is embedded. The output only depends on the view.
+pub fn render(view: &InvoiceView) -> Vec<u8> {
+    let pages = lay_out(view);
+
+    let catalog_id = Ref::new(1);
+    let page_tree_id = Ref::new(2);
+    let regular_id = Ref::new(3);
+    let bold_id = Ref::new(4);
+    let info_id = Ref::new(5);
+    // Each page is followed by its content
+    let page_ids: Vec<Ref> = (0..pages.len() as i32).map(|index| Ref::new(6 + 2 * index)).collect();
+
+    let mut pdf = Pdf::new();
+    pdf.catalog(catalog_id).pages(page_tree_id);
+    pdf.pages(page_tree_id).kids(page_ids.iter().copied()).count(pages.len() as i32);
+    for (page_id, content) in page_ids.iter().zip(&pages) {
+        let content_id = Ref::new(page_id.get() + 1);
+        let mut page = pdf.page(*page_id);
+        page.media_box(Rect::new(0.0, 0.0, WIDTH, HEIGHT));
+        page.parent(page_tree_id);
+        page.contents(content_id);
+        page.resources().fonts().pair(REGULAR, regular_id).pair(BOLD, bold_id);
+        page.finish();
+        pdf.stream(content_id, content);
+    }
+    pdf.type1_font(regular_id)
+        .base_font(Name(b"Helvetica"))
+        .encoding_predefined(Name(b"WinAnsiEncoding"));
+    pdf.type1_font(bold_id)
+        .base_font(Name(b"Helvetica-Bold"))
+        .encoding_predefined(Name(b"WinAnsiEncoding"));
+    pdf.document_info(info_id)
+        .title(TextStr(&format!("{} {}", view.labels.invoice, view.number)))
+        .producer(TextStr("OhMyShop"));
+    pdf.finish()
+}
\ No newline at end of file
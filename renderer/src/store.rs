@@ -0,0 +1,86 @@
+use common::client::Client;
+use common::invoice::Invoice;
+use common::product::Product;
+use sqlx::{FromRow, PgPool};
+
+#[derive(FromRow)]
+struct InvoiceRow {
+    id: i32,
+    number: String,
+    client_id: i32,
+    client_name: String,
+    client_email: String,
+    client_address: String,
+    date: String,
+    total_price: f64,
+    size: i32,
+    currency: String,
+}
+
+#[derive(FromRow)]
+struct InvoiceLineRow {
+    product_id: i32,
+    name: String,
+    price: f64,
+    backordered: bool,
+    currency: String,
+    exchange_rate: f64,
+}
+
+/// Loads an invoice stored by the sink, with its lines.
+pub async fn find_invoice(pool: &PgPool, id: i32) -> Result<Option<Invoice>, sqlx::Error> {
+    let row = sqlx::query_as::<_, InvoiceRow>(
+        r#"SELECT id, number, clientId AS client_id, clientName AS client_name, clientEmail AS client_email,
+            clientAddress AS client_address, TO_CHAR(date, 'YYYY-MM-DD') AS date, totalPrice AS total_price, size, currency
+        FROM Invoice
+        WHERE id = $1"#,
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+
+    let products = sqlx::query_as::<_, InvoiceLineRow>(
+        r#"SELECT productId AS product_id, name, price, backordered, currency, exchangeRate AS exchange_rate
+        FROM InvoiceLine
+        WHERE invoiceId = $1
+        ORDER BY lineNumber"#,
+    )
+    .bind(id)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|line| Product {
+        id: line.product_id,
+        name: line.name,
+        price: line.price,
+        command_id: id,
+        backordered: line.backordered,
+        currency: line.currency,
+        exchange_rate: line.exchange_rate,
+        unit: None,
+    })
+    .collect();
+
+    Ok(Some(Invoice {
+        id: row.id,
+        number: row.number,
+        date: row.date,
+        client: Client {
+            id: row.client_id,
+            name: row.client_name,
+            email: row.client_email,
+            address: row.client_address,
+            // The invoice table keeps the client data, not its version
+            version: 0,
+            updated_at: String::new(),
+            currency: row.currency.clone(),
+        },
+        products,
+        total_price: row.total_price,
+        size: row.size,
+        currency: row.currency,
+    }))
+}
\ No newline at end of file
@@ -0,0 +1,109 @@
+use clap::Args;
+use common::invoice::Invoice;
+use common::vat::{VatBreakdown, STANDARD_RATE};
+use serde::Serialize;
+
+use crate::locale::{Labels, Locale};
+
+#[derive(Args, Clone, Debug)]
+pub struct RenderOptions {
+    /// Locale of the documents: fr-FR or en-US
+    #[arg(long, default_value = "fr-FR")]
+    pub locale: Locale,
+
+    /// VAT rate included in the catalog prices, in percent
+    #[arg(long, default_value_t = STANDARD_RATE)]
+    pub vat_rate: f64,
+
+    /// Legal name of the seller
+    #[arg(long, default_value = "OhMyShop SAS")]
+    pub seller_name: String,
+
+    #[arg(long, default_value = "10 rue de la Paix, 75002 Paris")]
+    pub seller_address: String,
+
+    /// VAT identifier of the seller
+    #[arg(long, default_value = "FR40123456789")]
+    pub seller_vat_id: String,
+}
+
+#[derive(Serialize)]
+pub struct Party {
+    pub name: String,
+    pub address: String,
+    /// VAT identifier of the seller, email of the client
+    pub contact: String,
+}
+
+#[derive(Serialize)]
+pub struct Line {
+    pub name: String,
+    pub price: String,
+    pub backordered: bool,
+}
+
+/// An invoice with every amount and date formatted for a locale, which the HTML template and the
+/// PDF writer lay out.
+#[derive(Serialize)]
+pub struct InvoiceView {
+    pub language: &'static str,
+    pub labels: &'static Labels,
+    /// Legal number, or the command id of an invoice sent before legal numbers existed
+    pub number: String,
+    pub command_id: i32,
+    pub date: String,
+    pub seller: Party,
+    pub client: Party,
+    pub lines: Vec<Line>,
+    pub net: String,
+    /// Label of the VAT line, with its rate
+    pub vat_label: String,
+    pub vat: String,
+    /// Only when the VAT computed on the total without VAT is a cent or so off
+    pub rounding: Option<String>,
+    pub total: String,
+}
+
+impl InvoiceView {
+    pub fn new(invoice: &Invoice, options: &RenderOptions) -> Self {
+        let locale = options.locale;
+        let currency = |amount: f64| locale.currency(amount, &invoice.currency);
+        let vat = VatBreakdown::new(invoice.products.iter().map(|product| product.price), options.vat_rate);
+        let labels = locale.labels();
+
+        InvoiceView {
+            language: locale.language(),
+            labels,
+            number: match invoice.number.is_empty() {
+                true => invoice.id.to_string(),
+                false => invoice.number.clone(),
+            },
+            command_id: invoice.id,
+            date: locale.date(&invoice.date),
+            seller: Party {
+                name: options.seller_name.clone(),
+                address: options.seller_address.clone(),
+                contact: format!("{} {}", labels.vat_id, options.seller_vat_id),
+            },
+            client: Party {
+                name: invoice.client.name.clone(),
+                address: invoice.client.address.clone(),
+                contact: invoice.client.email.clone(),
+            },
+            lines: invoice
+                .products
+                .iter()
+                .map(|product| Line {
+                    name: product.name.clone(),
+                    price: currency(product.price),
+                    backordered: product.backordered,
+                })
+                .collect(),
+            net: currency(vat.net),
+            vat_label: format!("{} {}", labels.vat, locale.percent(vat.rate)),
+            vat: currency(vat.vat),
+            rounding: (vat.rounding != 0.0).then(|| currency(vat.rounding)),
+            total: currency(vat.payable),
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,94 @@
+//! Compares the rendered documents with the files of `tests/golden`. Run with `UPDATE_GOLDEN=1`
+//! to write the files again after an intended change, then review their diff.
+
+use std::fs;
+use std::path::Path;
+
+use common::invoice::Invoice;
+use common::product::Product;
+use renderer::{html, pdf, InvoiceView, Locale, RenderOptions};
+
+fn invoice() -> Invoice {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/invoice.json");
+    serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+}
+
+fn options(locale: Locale) -> RenderOptions {
+    RenderOptions {
+        locale,
+        vat_rate: 20.0,
+        seller_name: String::from("OhMyShop SAS"),
+        seller_address: String::from("10 rue de la Paix, 75002 Paris"),
+        seller_vat_id: String::from("FR40123456789"),
+    }
+}
+
+fn assert_golden(name: &str, actual: &[u8]) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = fs::read(&path)
+        .unwrap_or_else(|e| panic!("Cannot read {}: {}. Run with UPDATE_GOLDEN=1 to create it", path.display(), e));
+    match std::str::from_utf8(actual) {
+        // Text documents get a line diff
+        Ok(actual) if !name.ends_with(".pdf") => {
+            assert_eq!(String::from_utf8_lossy(&expected), actual, "{} is outdated", path.display())
+        }
+        _ => assert!(expected == actual, "{} is outdated", path.display()),
+    }
+}
+
+#[test]
+fn html_fr_fr() {
+    let view = InvoiceView::new(&invoice(), &options(Locale::FrFr));
+    assert_golden("invoice.fr-FR.html", html::render(&view).unwrap().as_bytes());
+}
+
+#[test]
+fn html_en_us() {
+    let view = InvoiceView::new(&invoice(), &options(Locale::EnUs));
+    assert_golden("invoice.en-US.html", html::render(&view).unwrap().as_bytes());
+}
+
+#[test]
+fn html_en_us_in_dollars() {
+    let mut invoice = invoice();
+    invoice.currency = String::from("USD");
+    let view = InvoiceView::new(&invoice, &options(Locale::EnUs));
+    assert_golden("invoice.en-US.usd.html", html::render(&view).unwrap().as_bytes());
+}
+
+#[test]
+fn pdf_fr_fr() {
+    let view = InvoiceView::new(&invoice(), &options(Locale::FrFr));
+    assert_golden("invoice.fr-FR.pdf", &pdf::render(&view));
+}
+
+#[test]
+fn pdf_en_us() {
+    let view = InvoiceView::new(&invoice(), &options(Locale::EnUs));
+    assert_golden("invoice.en-US.pdf", &pdf::render(&view));
+}
+
+#[test]
+fn pdf_several_pages() {
+    let mut invoice = invoice();
+    invoice.products = (1..=60)
+        .map(|id| Product {
+            id,
+            name: format!("Product {}", id),
+            price: id as f64 * 1.5,
+            command_id: invoice.id,
+            backordered: false,
+            currency: String::from("EUR"),
+            exchange_rate: 1.0,
+            unit: None,
+        })
+        .collect();
+    invoice.total_price = invoice.products.iter().map(|product| product.price).sum();
+    invoice.size = invoice.products.len() as i32;
+    let view = InvoiceView::new(&invoice, &options(Locale::EnUs));
+    assert_golden("invoice.pages.pdf", &pdf::render(&view));
+}
\ No newline at end of file
@@ -0,0 +1,52 @@
+use common::credit_note::CreditNote;
+use sqlx::PgPool;
+
+/// Writes the credit note and its lines, replacing any previous version of the same credit note.
+pub async fn upsert_credit_note(pool: &PgPool, credit_note: &CreditNote) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO CreditNote (id, invoiceId, clientId, date, reason, totalPrice, size, currency)
+        VALUES ($1, $2, $3, $4::date, $5, $6, $7, $8)
+        ON CONFLICT (id) DO UPDATE SET
+            invoiceId = EXCLUDED.invoiceId,
+            clientId = EXCLUDED.clientId,
+            date = EXCLUDED.date,
+            reason = EXCLUDED.reason,
+            totalPrice = EXCLUDED.totalPrice,
+            size = EXCLUDED.size,
+            currency = EXCLUDED.currency
+        "#,
+    )
+    .bind(credit_note.id)
+    .bind(credit_note.invoice_id)
+    .bind(credit_note.client.id)
+    .bind(&credit_note.date)
+    .bind(&credit_note.reason)
+    .bind(credit_note.total_price)
+    .bind(credit_note.size)
+    .bind(&credit_note.currency)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query("DELETE FROM CreditNoteLine WHERE creditNoteId = $1")
+        .bind(credit_note.id)
+        .execute(&mut *tx)
+        .await?;
+
+    for (line_number, product) in credit_note.products.iter().enumerate() {
+        sqlx::query(
+            "INSERT INTO CreditNoteLine (creditNoteId, lineNumber, productId, name, price) VALUES ($1, $2, $3, $4, $5)",
+        )
+        .bind(credit_note.id)
+        .bind(line_number as i32)
+        .bind(product.id)
+        .bind(&product.name)
+        .bind(product.price)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await
+}
\ No newline at end of file
@@ -0,0 +1,11 @@
+use sqlx::PgPool;
+
+/// Records a message the merger gave up on, keyed by the command it belongs to.
+pub async fn insert_dead_letter(pool: &PgPool, command_id: i32, payload: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT INTO DeadLetter (commandId, payload) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+        .bind(command_id)
+        .bind(payload)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use common::invoice::Invoice;
+use sqlx::PgPool;
+
+/// Writes the invoice and its lines, replacing any previous version of the same invoice.
+///
+/// Replaying the `Invoice` topic is therefore safe: the rows end up identical.
+pub async fn upsert_invoice(pool: &PgPool, invoice: &Invoice) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO Invoice (id, clientId, clientName, clientEmail, clientAddress, date, totalPrice, size, number, currency)
+        VALUES ($1, $2, $3, $4, $5, $6::date, $7, $8, $9, $10)
+        ON CONFLICT (id) DO UPDATE SET
+            clientId = EXCLUDED.clientId,
+            clientName = EXCLUDED.clientName,
+            clientEmail = EXCLUDED.clientEmail,
+            clientAddress = EXCLUDED.clientAddress,
+            date = EXCLUDED.date,
+            totalPrice = EXCLUDED.totalPrice,
+            size = EXCLUDED.size,
+            number = EXCLUDED.number,
+            currency = EXCLUDED.currency
+        "#,
+    )
+    .bind(invoice.id)
+    .bind(invoice.client.id)
+    .bind(&invoice.client.name)
+    .bind(&invoice.client.email)
+    .bind(&invoice.client.address)
+    .bind(&invoice.date)
+    .bind(invoice.total_price)
+    .bind(invoice.size)
+    .bind(&invoice.number)
+    .bind(&invoice.currency)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query("DELETE FROM InvoiceLine WHERE invoiceId = $1")
+        .bind(invoice.id)
+        .execute(&mut *tx)
+        .await?;
+
+    for (line_number, product) in invoice.products.iter().enumerate() {
+        sqlx::query(
+            "INSERT INTO InvoiceLine (invoiceId, lineNumber, productId, name, price, backordered, currency, exchangeRate)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+        )
+        .bind(invoice.id)
+        .bind(line_number as i32)
+        .bind(product.id)
+        .bind(&product.name)
+        .bind(product.price)
+        .bind(product.backordered)
+        .bind(&product.currency)
+        .bind(product.exchange_rate)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await
+}